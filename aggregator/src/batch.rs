@@ -0,0 +1,597 @@
+use crate::{
+    error::Error,
+    state::{IssuerState, IssuerUpdate},
+    transition::{parse_raw_transaction, Transition},
+};
+use mol::{
+    ckb_types::{
+        bytes::Bytes,
+        core::{Capacity, TransactionBuilder, TransactionView},
+        packed::{CellDep, CellInput, CellOutput, OutPoint, Script, WitnessArgs},
+        prelude::{Builder, Entity, Pack, Unpack},
+    },
+    NftIds, NftTransaction, NftTransactionVec, NftValues, RawCreateSetTransaction,
+    RawDistributeTransaction, RawExtractTransaction, RawInsertTransaction, RawTransferTransaction,
+    RawUpdateTransaction,
+};
+use script_utils::{
+    class::Class,
+    error::Error as ScriptError,
+    helpers::{
+        CLASS_CELL, CLASS_ITEM, CREATE_SET_TRANSACTION, DISTRIBUTE_TRANSACTION,
+        EXTRACT_TRANSACTION, INSERT_TRANSACTION, ISSUER_CELL, NFT_CELL, NFT_ITEM, NFT_SET_CELL,
+        SET_ITEM, TRANSFER_TRANSACTION, UPDATE_TRANSACTION,
+    },
+    issuer::Issuer,
+    nft::Nft,
+    nft_set::NftSet,
+    signature::signing_message,
+    type_args::TypeArgs,
+};
+use sparse_merkle_tree::H256;
+
+/// A live cell on L1
+#[derive(Debug, Clone)]
+pub struct LiveCell {
+    pub out_point: OutPoint,
+    pub output: CellOutput,
+    pub data: Bytes,
+}
+
+impl LiveCell {
+    pub fn lock_hash(&self) -> [u8; 32] {
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(self.output.lock().calc_script_hash().as_slice());
+        hash
+    }
+}
+
+/// A NftTransaction of a user and the L1 cells it brings into the batch:
+/// 1) output_locks: the locks of the cells created by an extract transaction, in the order
+///    of its nft_ids, the hash of every lock must be the owner of the leaf
+/// 2) input_cells: the nft, class or set cells consumed by an insert transaction, in the order
+///    of its nft_ids
+#[derive(Debug, Clone)]
+pub struct Submission {
+    pub tx: NftTransaction,
+    pub output_locks: Vec<Script>,
+    pub input_cells: Vec<LiveCell>,
+}
+
+impl Submission {
+    pub fn new(tx: NftTransaction) -> Self {
+        Submission {
+            tx,
+            output_locks: Vec::new(),
+            input_cells: Vec::new(),
+        }
+    }
+}
+
+// the submissions sent in the L1 transaction, the issuer cell created by it and the
+// leaves it changes
+struct PendingBatch {
+    submissions: Vec<Submission>,
+    issuer_output: CellOutput,
+    issuer_data: Bytes,
+    leaves: Vec<(H256, Vec<u8>)>,
+}
+
+fn parse_keys(nft_ids: NftIds) -> Vec<H256> {
+    nft_ids
+        .into_iter()
+        .map(|nft_id| {
+            let mut key = [0u8; 32];
+            key.copy_from_slice(nft_id.as_slice());
+            key.into()
+        })
+        .collect()
+}
+
+fn pack_leaves(leaves: Vec<Vec<u8>>) -> NftValues {
+    NftValues::new_builder()
+        .set(leaves.iter().map(|leaf| leaf.pack()).collect())
+        .build()
+}
+
+/// Collects the NftTransactions of users into batches, every batch is sent in one L1
+/// transaction consuming and recreating the issuer cell.
+/// A submission is accepted only if it passes the rules of the contract on the leaves left
+/// by the submissions accepted before it, so the pool is always a valid batch in the order
+/// of acceptance. The owners authorize their transactions by signatures, except the issuer
+/// lock and the locks of the inserted cells, which are in the inputs of the L1 transaction.
+pub struct Aggregator {
+    state: IssuerState,
+    issuer_cell: LiveCell,
+    issuer_type: Script,
+    cell_deps: Vec<CellDep>,
+    fee: Capacity,
+    pool: Vec<Submission>,
+    pending: Option<PendingBatch>,
+}
+
+impl Aggregator {
+    /// The issuer cell must be the live cell holding the tree of the state, the cell deps
+    /// are the ones of the L1 transactions, e.g. the contract and the issuer lock.
+    pub fn new(
+        mut state: IssuerState,
+        issuer_cell: LiveCell,
+        cell_deps: Vec<CellDep>,
+    ) -> Result<Self, Error> {
+        let issuer_type = issuer_cell
+            .output
+            .type_()
+            .to_opt()
+            .ok_or(Error::IssuerNotMatch)?;
+        let args: Bytes = issuer_type.args().unpack();
+        if args[..] != state.issuer_id()[..] {
+            return Err(Error::IssuerNotMatch);
+        }
+        if issuer_cell.data.first() != Some(&ISSUER_CELL) {
+            return Err(ScriptError::IssuerDataInvalid.into());
+        }
+        let issuer = Issuer::from_data(&issuer_cell.data[1..])?;
+        // the last batch can be committed on L1 while the store is behind it
+        state.recover(&issuer)?;
+        if issuer.smt_root[..] != state.root().as_slice()[..]
+            || issuer.class_count != state.issuer().class_count
+            || issuer.set_count != state.issuer().set_count
+        {
+            return Err(Error::IssuerNotMatch);
+        }
+
+        Ok(Aggregator {
+            state,
+            issuer_cell,
+            issuer_type,
+            cell_deps,
+            fee: Capacity::zero(),
+            pool: Vec::new(),
+            pending: None,
+        })
+    }
+
+    /// The fee of every L1 transaction, paid by the issuer cell
+    pub fn with_fee(mut self, fee: u64) -> Self {
+        self.fee = Capacity::shannons(fee);
+        self
+    }
+
+    /// The state including the pending batch
+    pub fn state(&self) -> &IssuerState {
+        &self.state
+    }
+
+    pub fn issuer_cell(&self) -> &LiveCell {
+        &self.issuer_cell
+    }
+
+    /// The accepted submissions of the next batch
+    pub fn pool(&self) -> &[Submission] {
+        &self.pool
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// The new leaves of the keys touched by the pending batch, empty if no batch is pending
+    pub fn pending_leaves(&self) -> &[(H256, Vec<u8>)] {
+        self.pending
+            .as_ref()
+            .map(|batch| batch.leaves.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The leaf of the key after the pending batch and the pool, empty if the key is absent
+    pub fn leaf(&self, key: &H256) -> Result<Vec<u8>, Error> {
+        let submissions: Vec<&Submission> = self.pool.iter().collect();
+        self.replay(&submissions, |transition| transition.leaf(key))
+    }
+
+    /// Fill the old values of the transaction with the current leaves, then return it with
+    /// the message signed by the owners of the leaves.
+    /// The class_old_value of a distribute transaction is refreshed by the ids in it.
+    pub fn prepare(&self, tx: &NftTransaction) -> Result<(NftTransaction, [u8; 32]), Error> {
+        let typ: u8 = tx.typ().into();
+        let submissions: Vec<&Submission> = self.pool.iter().collect();
+        let raw = self.replay(&submissions, |transition| {
+            let current_leaves = |nft_ids: NftIds| -> Result<NftValues, Error> {
+                let leaves = parse_keys(nft_ids)
+                    .iter()
+                    .map(|key| transition.leaf(key))
+                    .collect::<Result<_, _>>()?;
+                Ok(pack_leaves(leaves))
+            };
+            let raw = match typ {
+                DISTRIBUTE_TRANSACTION => {
+                    let distribute_tx: RawDistributeTransaction = parse_raw_transaction(tx)?;
+                    let class =
+                        Class::from_data(&distribute_tx.class_old_value().raw_data(), false)?;
+                    let class_old_value = transition.leaf(&class.to_key())?;
+                    distribute_tx
+                        .as_builder()
+                        .class_old_value(class_old_value.pack())
+                        .build()
+                        .as_bytes()
+                }
+                TRANSFER_TRANSACTION => {
+                    let transfer_tx: RawTransferTransaction = parse_raw_transaction(tx)?;
+                    let nft_old_values = current_leaves(transfer_tx.nft_ids())?;
+                    transfer_tx
+                        .as_builder()
+                        .nft_old_values(nft_old_values)
+                        .build()
+                        .as_bytes()
+                }
+                UPDATE_TRANSACTION => {
+                    let update_tx: RawUpdateTransaction = parse_raw_transaction(tx)?;
+                    let nft_old_values = current_leaves(update_tx.nft_ids())?;
+                    update_tx
+                        .as_builder()
+                        .nft_old_values(nft_old_values)
+                        .build()
+                        .as_bytes()
+                }
+                EXTRACT_TRANSACTION => {
+                    let extract_tx: RawExtractTransaction = parse_raw_transaction(tx)?;
+                    let nft_old_values = current_leaves(extract_tx.nft_ids())?;
+                    extract_tx
+                        .as_builder()
+                        .nft_old_values(nft_old_values)
+                        .build()
+                        .as_bytes()
+                }
+                CREATE_SET_TRANSACTION => {
+                    let create_set_tx: RawCreateSetTransaction = parse_raw_transaction(tx)?;
+                    let member_old_values = current_leaves(create_set_tx.members())?;
+                    create_set_tx
+                        .as_builder()
+                        .member_old_values(member_old_values)
+                        .build()
+                        .as_bytes()
+                }
+                _ => tx.transaction().raw_data(),
+            };
+            Ok(raw)
+        })?;
+
+        let message = signing_message(self.state.issuer_id(), typ, &raw);
+        let tx = tx.clone().as_builder().transaction(raw.pack()).build();
+        Ok((tx, message))
+    }
+
+    /// Accept a submission into the pool. The old values of an unsigned transaction are
+    /// filled by the aggregator, a signed one must carry the current leaves as the
+    /// signature covers them.
+    pub fn submit(&mut self, mut submission: Submission) -> Result<(), Error> {
+        if submission.tx.signature().raw_data().is_empty() {
+            submission.tx = self.prepare(&submission.tx)?.0;
+        }
+        let duplicated = self
+            .pool
+            .iter()
+            .chain(
+                self.pending
+                    .iter()
+                    .flat_map(|batch| batch.submissions.iter()),
+            )
+            .any(|accepted| accepted.tx.as_slice() == submission.tx.as_slice());
+        if duplicated {
+            return Err(Error::DuplicateTransaction);
+        }
+
+        let mut submissions: Vec<&Submission> = self.pool.iter().collect();
+        submissions.push(&submission);
+        self.replay(&submissions, |_| Ok(()))?;
+        self.pool.push(submission);
+        Ok(())
+    }
+
+    /// Apply the pool to the state as a batch and return the L1 transaction of it:
+    /// inputs: the issuer cell + the cells of insert transactions
+    /// outputs: the issuer cell + the cells of extract transactions
+    /// witnesses: WitnessArgs { lock: NftTransactionVec, input_type: SmtUpdateAction }
+    ///            + an empty witness for every other input
+    /// The issuer cell pays the capacity of the extracted cells and the fee, the capacity of
+    /// an inserted cell goes back to its lock in a cell without type.
+    /// The batch is pending until it's committed or rolled back.
+    pub fn build(&mut self) -> Result<TransactionView, Error> {
+        if self.pending.is_some() {
+            return Err(Error::BatchPending);
+        }
+        if self.pool.is_empty() {
+            return Err(Error::NoTransactions);
+        }
+
+        let mut inputs = vec![CellInput::new(self.issuer_cell.out_point.clone(), 0)];
+        let mut inserted_leaves = Vec::new();
+        let mut outputs = Vec::new();
+        let mut outputs_data = Vec::new();
+        let mut issuer_capacity: Capacity = self.issuer_cell.output.capacity().unpack();
+        for submission in &self.pool {
+            for cell in &submission.input_cells {
+                inputs.push(CellInput::new(cell.out_point.clone(), 0));
+                inserted_leaves.push(self.inserted_leaf(cell)?.1);
+                let refund = CellOutput::new_builder()
+                    .lock(cell.output.lock())
+                    .capacity(cell.output.capacity())
+                    .build();
+                outputs.push(refund);
+                outputs_data.push(Bytes::new().pack());
+            }
+            for (output, data) in self.extracted_cells(submission)? {
+                issuer_capacity = issuer_capacity
+                    .safe_sub(Unpack::<Capacity>::unpack(&output.capacity()))
+                    .map_err(|_| Error::InsufficientCapacity)?;
+                outputs.push(output);
+                outputs_data.push(data.pack());
+            }
+        }
+        let issuer_capacity = issuer_capacity
+            .safe_sub(self.fee)
+            .map_err(|_| Error::InsufficientCapacity)?;
+        let issuer_output = self
+            .issuer_cell
+            .output
+            .clone()
+            .as_builder()
+            .capacity(issuer_capacity.pack())
+            .build();
+        // the issuer cell data keeps its length
+        let data_capacity =
+            Capacity::bytes(self.issuer_cell.data.len()).map_err(|_| Error::CapacityOverflow)?;
+        if issuer_output
+            .is_lack_of_capacity(data_capacity)
+            .map_err(|_| Error::CapacityOverflow)?
+        {
+            return Err(Error::InsufficientCapacity);
+        }
+
+        let txs = NftTransactionVec::new_builder()
+            .set(
+                self.pool
+                    .iter()
+                    .map(|submission| submission.tx.clone())
+                    .collect(),
+            )
+            .build();
+        // the other fields of the issuer cell, e.g. info, are kept as they are
+        let mut output_issuer = Issuer::from_data(&self.issuer_cell.data[1..])?;
+        let IssuerUpdate {
+            issuer,
+            action,
+            leaves,
+        } = self.state.apply(&txs, &inserted_leaves)?;
+        output_issuer.class_count = issuer.class_count;
+        output_issuer.set_count = issuer.set_count;
+        output_issuer.smt_root = issuer.smt_root;
+        let mut issuer_data = vec![ISSUER_CELL];
+        issuer_data.extend_from_slice(&output_issuer.to_data()?);
+        let issuer_data = Bytes::from(issuer_data);
+
+        let witness = WitnessArgs::new_builder()
+            .lock(Some(txs.as_bytes()).pack())
+            .input_type(Some(action.as_bytes()).pack())
+            .build();
+        let mut witnesses = vec![witness.as_bytes().pack()];
+        witnesses.resize(inputs.len(), Bytes::new().pack());
+
+        let tx = TransactionBuilder::default()
+            .cell_deps(self.cell_deps.clone())
+            .inputs(inputs)
+            .output(issuer_output.clone())
+            .output_data(issuer_data.pack())
+            .outputs(outputs)
+            .outputs_data(outputs_data)
+            .witnesses(witnesses)
+            .build();
+
+        self.pending = Some(PendingBatch {
+            submissions: std::mem::take(&mut self.pool),
+            issuer_output,
+            issuer_data,
+            leaves,
+        });
+        Ok(tx)
+    }
+
+    /// The L1 transaction of the pending batch is committed, the new issuer cell is its
+    /// first output
+    pub fn commit(&mut self, issuer_out_point: OutPoint) -> Result<(), Error> {
+        if self.pending.is_none() {
+            return Err(Error::NoPendingBatch);
+        }
+        self.state.commit()?;
+        let pending = self.pending.take().ok_or(Error::NoPendingBatch)?;
+        self.issuer_cell = LiveCell {
+            out_point: issuer_out_point,
+            output: pending.issuer_output,
+            data: pending.issuer_data,
+        };
+        Ok(())
+    }
+
+    /// The L1 transaction of the pending batch has failed, its submissions are put back in
+    /// front of the pool. The pool is replayed again and the submissions that are not valid
+    /// anymore are dropped and returned.
+    pub fn rollback(&mut self) -> Result<Vec<Submission>, Error> {
+        if self.pending.is_none() {
+            return Err(Error::NoPendingBatch);
+        }
+        self.state.rollback()?;
+        let pending = self.pending.take().ok_or(Error::NoPendingBatch)?;
+
+        let candidates: Vec<Submission> = pending
+            .submissions
+            .into_iter()
+            .chain(self.pool.drain(..))
+            .collect();
+        let mut dropped = Vec::new();
+        for submission in candidates {
+            let replayed = {
+                let mut submissions: Vec<&Submission> = self.pool.iter().collect();
+                submissions.push(&submission);
+                self.replay(&submissions, |_| Ok(()))
+            };
+            match replayed {
+                Ok(()) => self.pool.push(submission),
+                Err(_) => dropped.push(submission),
+            }
+        }
+        Ok(dropped)
+    }
+
+    // replay the submissions on the state with the rules of the contract, then run f on the
+    // leaves left by them
+    fn replay<T, F>(&self, submissions: &[&Submission], f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&Transition) -> Result<T, Error>,
+    {
+        let mut inserted_leaves = Vec::new();
+        let mut input_lock_hashes = vec![self.issuer_cell.lock_hash()];
+        for submission in submissions {
+            self.check_inserted_cells(submission)?;
+            for cell in &submission.input_cells {
+                inserted_leaves.push(self.inserted_leaf(cell)?.1);
+                input_lock_hashes.push(cell.lock_hash());
+            }
+        }
+
+        let mut transition = Transition::new(
+            self.state.smt(),
+            *self.state.issuer_id(),
+            self.state.issuer(),
+            &inserted_leaves,
+        )
+        .authorize_by(&input_lock_hashes);
+        for submission in submissions {
+            transition.apply(&submission.tx)?;
+            self.extracted_cells(submission)?;
+        }
+        f(&transition)
+    }
+
+    // the cells consumed by a submission must be the ones inserted by it
+    fn check_inserted_cells(&self, submission: &Submission) -> Result<(), Error> {
+        let tx = &submission.tx;
+        let (typ, keys) = if u8::from(tx.typ()) == INSERT_TRANSACTION {
+            let insert_tx: RawInsertTransaction = parse_raw_transaction(tx)?;
+            (insert_tx.typ().into(), parse_keys(insert_tx.nft_ids()))
+        } else {
+            (NFT_ITEM, Vec::new())
+        };
+        let cell_keys = submission
+            .input_cells
+            .iter()
+            .map(|cell| self.inserted_leaf(cell).map(|(key, _)| key))
+            .collect::<Result<Vec<_>, _>>()?;
+        if cell_keys != keys {
+            return match typ {
+                CLASS_ITEM => Err(ScriptError::ClassCellsCountError.into()),
+                SET_ITEM => Err(ScriptError::NFTSetCellsCountError.into()),
+                _ => Err(ScriptError::NFTCellsCountError.into()),
+            };
+        }
+        Ok(())
+    }
+
+    // the key and leaf of a consumed nft, class or set cell, the leaf is owned by the cell lock
+    fn inserted_leaf(&self, cell: &LiveCell) -> Result<(H256, Vec<u8>), Error> {
+        let type_ = cell
+            .output
+            .type_()
+            .to_opt()
+            .filter(|type_| {
+                type_.code_hash() == self.issuer_type.code_hash()
+                    && type_.hash_type() == self.issuer_type.hash_type()
+            })
+            .ok_or(ScriptError::TypeArgsInvalid)?;
+        let args: Bytes = type_.args().unpack();
+        let type_args = TypeArgs::from_slice(&args)?;
+        let data = &cell.data;
+        match data.first() {
+            Some(&NFT_CELL) => {
+                let mut nft = Nft::from_data_cell(&data[1..])?;
+                type_args.fill_nft(&mut nft)?;
+                nft.owner = cell.lock_hash();
+                Ok((nft.to_key(), nft.to_leaf_data()))
+            }
+            Some(&CLASS_CELL) => {
+                let mut class = Class::from_data(&data[1..], true)?;
+                type_args.fill_class(&mut class)?;
+                class.owner = cell.lock_hash();
+                Ok((class.to_key(), class.to_leaf_data()?))
+            }
+            Some(&NFT_SET_CELL) => {
+                let mut set = NftSet::from_data_cell(&data[1..])?;
+                type_args.fill_set(&mut set)?;
+                set.owner = cell.lock_hash();
+                Ok((set.to_key(), set.to_leaf_data()?))
+            }
+            _ => Err(ScriptError::NFTDataInvalid.into()),
+        }
+    }
+
+    // the cells created by a submission, one for every leaf removed by an extract transaction
+    fn extracted_cells(&self, submission: &Submission) -> Result<Vec<(CellOutput, Bytes)>, Error> {
+        let tx = &submission.tx;
+        if u8::from(tx.typ()) != EXTRACT_TRANSACTION {
+            if !submission.output_locks.is_empty() {
+                return Err(ScriptError::TransactionLenNotMatch.into());
+            }
+            return Ok(Vec::new());
+        }
+        let extract_tx: RawExtractTransaction = parse_raw_transaction(tx)?;
+        let typ: u8 = extract_tx.typ().into();
+        let old_values = extract_tx.nft_old_values();
+        if old_values.len() != submission.output_locks.len() {
+            return Err(ScriptError::TransactionLenNotMatch.into());
+        }
+
+        let mut cells = Vec::new();
+        for (old_value, lock) in old_values.into_iter().zip(&submission.output_locks) {
+            let leaf = old_value.raw_data();
+            let (owner, args, data) = match typ {
+                NFT_ITEM => {
+                    let nft = Nft::from_data_leaf(&leaf)?;
+                    let mut data = vec![NFT_CELL];
+                    data.extend_from_slice(&nft.to_data(true));
+                    (nft.owner, TypeArgs::from_nft(&nft).to_vec(), data)
+                }
+                CLASS_ITEM => {
+                    let class = Class::from_data(&leaf, false)?;
+                    let mut data = vec![CLASS_CELL];
+                    data.extend_from_slice(&class.to_data(true)?);
+                    (class.owner, TypeArgs::from_class(&class).to_vec(), data)
+                }
+                SET_ITEM => {
+                    let set = NftSet::from_data_leaf(&leaf)?;
+                    let mut data = vec![NFT_SET_CELL];
+                    data.extend_from_slice(&set.to_data(true)?);
+                    (set.owner, TypeArgs::from_set(&set).to_vec(), data)
+                }
+                _ => return Err(ScriptError::NFTDataInvalid.into()),
+            };
+            if lock.calc_script_hash().as_slice() != owner {
+                return Err(ScriptError::NFTOwnerNotMatch.into());
+            }
+
+            let type_ = self
+                .issuer_type
+                .clone()
+                .as_builder()
+                .args(args.pack())
+                .build();
+            let output = CellOutput::new_builder()
+                .lock(lock.clone())
+                .type_(Some(type_).pack())
+                .build_exact_capacity(
+                    Capacity::bytes(data.len()).map_err(|_| Error::CapacityOverflow)?,
+                )
+                .map_err(|_| Error::CapacityOverflow)?;
+            cells.push((output, Bytes::from(data)));
+        }
+        Ok(cells)
+    }
+}
@@ -0,0 +1,63 @@
+use std::{fmt, io};
+
+/// Error
+/// The rules shared with the contract fail with the script error, so the exit code the
+/// contract would return can be reported to the user.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Smt(sparse_merkle_tree::error::Error),
+    Script(script_utils::error::Error),
+    // the log of the store has an unknown record
+    StoreCorrupted,
+    // the store holds the tree of another issuer
+    IssuerNotMatch,
+    // the L1 transaction of the last batch is neither committed nor rolled back
+    BatchPending,
+    NoPendingBatch,
+    // the transaction is already in the pool or the pending batch
+    DuplicateTransaction,
+    NoTransactions,
+    // the occupied capacity of a cell overflows
+    CapacityOverflow,
+    // the issuer cell can't pay the extracted cells and the fee
+    InsufficientCapacity,
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<sparse_merkle_tree::error::Error> for Error {
+    fn from(err: sparse_merkle_tree::error::Error) -> Self {
+        Error::Smt(err)
+    }
+}
+
+impl From<script_utils::error::Error> for Error {
+    fn from(err: script_utils::error::Error) -> Self {
+        Error::Script(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "io error: {}", err),
+            Error::Smt(err) => write!(f, "smt error: {}", err),
+            Error::Script(err) => write!(f, "script error {:?}({})", err, *err as i8),
+            Error::StoreCorrupted => write!(f, "store corrupted"),
+            Error::IssuerNotMatch => write!(f, "store belongs to another issuer"),
+            Error::BatchPending => write!(f, "a batch is pending"),
+            Error::NoPendingBatch => write!(f, "no pending batch"),
+            Error::DuplicateTransaction => write!(f, "duplicate transaction"),
+            Error::NoTransactions => write!(f, "no transactions to batch"),
+            Error::CapacityOverflow => write!(f, "capacity overflow"),
+            Error::InsufficientCapacity => write!(f, "insufficient capacity of the issuer cell"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,195 @@
+use crate::{batch::LiveCell, state::IssuerState};
+use mol::ckb_types::{
+    bytes::Bytes,
+    core::TransactionView,
+    packed::{CellOutput, OutPoint, Script},
+    prelude::{Entity, Unpack},
+};
+use script_utils::{helpers::NFT_CELL, nft::Nft, type_args::TypeArgs};
+use sparse_merkle_tree::H256;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// The ids of a nft, unique among all issuers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NftId {
+    pub issuer_id: [u8; 20],
+    pub class_id: u32,
+    pub token_id: u32,
+}
+
+/// A nft is either a leaf of the issuer tree or an extracted nft cell
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NftLocation {
+    Leaf(H256),
+    Cell(OutPoint),
+}
+
+#[derive(Debug, Clone)]
+pub struct OwnedNft {
+    pub id: NftId,
+    // the owner of a leaf, or the lock hash of a cell
+    pub owner: [u8; 32],
+    pub location: NftLocation,
+}
+
+/// Indexes the nfts of the added issuers by their owners.
+/// The index is kept up to date by the leaves changed by every issuer cell update and by
+/// the L1 transactions creating and consuming nft cells, which are applied in the order of
+/// the chain. A nft moves between a leaf and a cell by extract and insert transactions, both
+/// in the same L1 transaction as the issuer cell update, so the leaves and the cells of it
+/// can be applied in any order.
+#[derive(Default)]
+pub struct OwnerIndex {
+    // the type scripts of the issuer cells, a nft cell shares code_hash and hash_type
+    // with the issuer cell
+    issuer_types: BTreeMap<[u8; 20], Script>,
+    nfts: BTreeMap<NftId, OwnedNft>,
+    owners: BTreeMap<[u8; 32], BTreeSet<NftId>>,
+    leaves: BTreeMap<H256, NftId>,
+    cells: HashMap<OutPoint, NftId>,
+}
+
+impl OwnerIndex {
+    /// Index the leaves of the issuer tree and watch the nft cells of the issuer
+    pub fn add_issuer(&mut self, issuer_type: &Script, state: &IssuerState) {
+        self.issuer_types
+            .insert(*state.issuer_id(), issuer_type.clone());
+        for (key, leaf) in state.leaves() {
+            self.apply_leaf(key, leaf);
+        }
+    }
+
+    /// Apply the leaves changed by an issuer cell update, an empty leaf is removed.
+    /// The leaves other than nft leaves are skipped.
+    pub fn apply_leaves(&mut self, leaves: &[(H256, Vec<u8>)]) {
+        for (key, leaf) in leaves {
+            self.apply_leaf(key, leaf);
+        }
+    }
+
+    /// Remove the nft cells consumed by the transaction and add the ones created by it
+    pub fn apply_transaction(&mut self, tx: &TransactionView) {
+        for out_point in tx.input_pts_iter() {
+            if let Some(id) = self.cells.remove(&out_point) {
+                self.remove(&id, &NftLocation::Cell(out_point));
+            }
+        }
+        for (index, (output, data)) in tx.outputs_with_data_iter().enumerate() {
+            self.apply_cell(OutPoint::new(tx.hash(), index as u32), &output, &data);
+        }
+    }
+
+    /// Add the live nft cells, e.g. the cells of an issuer which were created before the
+    /// issuer is added
+    pub fn apply_live_cells(&mut self, cells: &[LiveCell]) {
+        for cell in cells {
+            self.apply_cell(cell.out_point.clone(), &cell.output, &cell.data);
+        }
+    }
+
+    pub fn get(&self, id: &NftId) -> Option<&OwnedNft> {
+        self.nfts.get(id)
+    }
+
+    /// The nfts of the owner, in the order of their ids
+    pub fn nfts_of<'a>(&'a self, owner: &[u8; 32]) -> impl Iterator<Item = &'a OwnedNft> {
+        self.owners
+            .get(owner)
+            .into_iter()
+            .flatten()
+            .filter_map(move |id| self.nfts.get(id))
+    }
+
+    fn apply_leaf(&mut self, key: &H256, leaf: &[u8]) {
+        if leaf.is_empty() {
+            if let Some(id) = self.leaves.remove(key) {
+                self.remove(&id, &NftLocation::Leaf(*key));
+            }
+            return;
+        }
+        // the key tells nft leaves from the others
+        let nft = match Nft::from_data_leaf(leaf) {
+            Ok(nft) if &nft.to_key() == key => nft,
+            _ => return,
+        };
+        let id = NftId {
+            issuer_id: nft.issuer_id,
+            class_id: nft.class_id,
+            token_id: nft.token_id,
+        };
+        self.leaves.insert(*key, id);
+        self.insert(OwnedNft {
+            id,
+            owner: nft.owner,
+            location: NftLocation::Leaf(*key),
+        });
+    }
+
+    fn apply_cell(&mut self, out_point: OutPoint, output: &CellOutput, data: &Bytes) {
+        if let Some(id) = self.parse_nft_cell(output, data) {
+            let mut owner = [0u8; 32];
+            owner.copy_from_slice(output.lock().calc_script_hash().as_slice());
+            self.cells.insert(out_point.clone(), id);
+            self.insert(OwnedNft {
+                id,
+                owner,
+                location: NftLocation::Cell(out_point),
+            });
+        }
+    }
+
+    // the ids of the cell if it's a nft cell of an added issuer
+    fn parse_nft_cell(&self, output: &CellOutput, data: &Bytes) -> Option<NftId> {
+        let type_ = output.type_().to_opt()?;
+        let args: Bytes = type_.args().unpack();
+        let (issuer_id, class_id, token_id) = match TypeArgs::from_slice(&args).ok()? {
+            TypeArgs::Nft {
+                issuer_id,
+                class_id,
+                token_id,
+            } => (issuer_id, class_id, token_id),
+            _ => return None,
+        };
+        let issuer_type = self.issuer_types.get(&issuer_id)?;
+        if type_.code_hash() != issuer_type.code_hash()
+            || type_.hash_type() != issuer_type.hash_type()
+            || data.first() != Some(&NFT_CELL)
+        {
+            return None;
+        }
+        Some(NftId {
+            issuer_id,
+            class_id,
+            token_id,
+        })
+    }
+
+    fn insert(&mut self, nft: OwnedNft) {
+        if let Some(old) = self.nfts.get(&nft.id) {
+            let (old_owner, id) = (old.owner, old.id);
+            self.remove_owner(&old_owner, &id);
+        }
+        self.owners.entry(nft.owner).or_default().insert(nft.id);
+        self.nfts.insert(nft.id, nft);
+    }
+
+    // remove the nft only if it's still at the location, it may have moved between a leaf
+    // and a cell in the same L1 transaction
+    fn remove(&mut self, id: &NftId, location: &NftLocation) {
+        let owner = match self.nfts.get(id) {
+            Some(nft) if &nft.location == location => nft.owner,
+            _ => return,
+        };
+        self.nfts.remove(id);
+        self.remove_owner(&owner, id);
+    }
+
+    fn remove_owner(&mut self, owner: &[u8; 32], id: &NftId) {
+        if let Some(ids) = self.owners.get_mut(owner) {
+            ids.remove(id);
+            if ids.is_empty() {
+                self.owners.remove(owner);
+            }
+        }
+    }
+}
@@ -0,0 +1,6 @@
+pub mod batch;
+pub mod error;
+pub mod indexer;
+pub mod state;
+pub mod store;
+pub mod transition;
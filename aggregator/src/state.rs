@@ -0,0 +1,327 @@
+use crate::{
+    error::Error,
+    store::{FileStore, Reader},
+    transition::Transition,
+};
+use mol::{
+    ckb_types::{
+        packed::Byte,
+        prelude::{Builder, Entity},
+    },
+    NftTransactionVec, SmtProof, SmtUpdateAction,
+};
+use script_utils::{
+    issuer::{Issuer, ISSUER_TYPE_ARGS_LEN},
+    misc::{CKBBlake2bHasher, NftValue},
+};
+use sparse_merkle_tree::{traits::Value, SparseMerkleTree, H256};
+use std::path::Path;
+
+pub type IssuerSmt = SparseMerkleTree<CKBBlake2bHasher, NftValue, FileStore>;
+
+/// The output issuer cell data and the witness of the L1 transaction of a batch
+pub struct IssuerUpdate {
+    pub issuer: Issuer,
+    pub action: SmtUpdateAction,
+    // the new leaves of the keys touched by the batch, empty if the key is removed
+    pub leaves: Vec<(H256, Vec<u8>)>,
+}
+
+// the issuer and the touched leaves before the pending batch
+struct PendingBatch {
+    issuer: Issuer,
+    old_leaves: Vec<(H256, Vec<u8>)>,
+}
+
+// the issuer and the touched leaves after a batch, written before its L1 transaction is sent
+struct SentBatch {
+    issuer: Issuer,
+    leaves: Vec<(H256, Vec<u8>)>,
+}
+
+/// The off-chain state of an issuer cell: its data and every leaf of the tree behind
+/// its smt_root, persisted in a FileStore.
+/// A batch of NftTransactions is applied in memory and stays pending until its L1
+/// transaction is committed or has failed, only committed batches are persisted. The
+/// leaves of the pending batch are kept with the checkpoint, so the batch can be recovered
+/// if its L1 transaction is committed but the process stops before it's persisted.
+pub struct IssuerState {
+    issuer_id: [u8; 20],
+    issuer: Issuer,
+    smt: IssuerSmt,
+    pending: Option<PendingBatch>,
+    // the batch pending when the store was written last
+    sent: Option<SentBatch>,
+}
+
+fn update_leaves(smt: &mut IssuerSmt, leaves: &[(H256, Vec<u8>)]) -> Result<(), Error> {
+    for (key, leaf) in leaves {
+        smt.update(*key, NftValue(leaf.clone()))?;
+    }
+    Ok(())
+}
+
+// restore the leaves before a batch, the changes of the batch are reverted instead of
+// being undone by new records
+fn restore_leaves(smt: &mut IssuerSmt, old_leaves: &[(H256, Vec<u8>)]) -> Result<(), Error> {
+    update_leaves(smt, old_leaves)?;
+    smt.store_mut().revert()
+}
+
+// the issuer cell has the tree, only the info can differ after the cell is updated
+fn same_tree(issuer: &Issuer, other: &Issuer) -> bool {
+    issuer.smt_root == other.smt_root
+        && issuer.class_count == other.class_count
+        && issuer.set_count == other.set_count
+}
+
+fn extend_bytes(data: &mut Vec<u8>, bytes: &[u8]) {
+    data.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    data.extend_from_slice(bytes);
+}
+
+// checkpoint: issuer_id + <size: u32> + <issuer cell data without the cell type>, followed
+// by the sent batch if any: <size: u32> + <issuer cell data> + (key + <size: u32> + <leaf>)*
+fn encode_checkpoint(
+    issuer_id: &[u8; 20],
+    issuer: &Issuer,
+    sent: Option<&SentBatch>,
+) -> Result<Vec<u8>, Error> {
+    let mut data = issuer_id.to_vec();
+    extend_bytes(&mut data, &issuer.to_data()?);
+    if let Some(sent) = sent {
+        extend_bytes(&mut data, &sent.issuer.to_data()?);
+        for (key, leaf) in &sent.leaves {
+            data.extend_from_slice(key.as_slice());
+            extend_bytes(&mut data, leaf);
+        }
+    }
+    Ok(data)
+}
+
+fn decode_checkpoint(
+    issuer_id: &[u8; 20],
+    data: &[u8],
+) -> Result<(Issuer, Option<SentBatch>), Error> {
+    let mut reader = Reader::new(data);
+    if reader.take(ISSUER_TYPE_ARGS_LEN) != Some(&issuer_id[..]) {
+        return Err(Error::IssuerNotMatch);
+    }
+    let issuer = Issuer::from_data(&reader.bytes().ok_or(Error::StoreCorrupted)?)?;
+    if reader.is_end() {
+        return Ok((issuer, None));
+    }
+
+    let sent_issuer = Issuer::from_data(&reader.bytes().ok_or(Error::StoreCorrupted)?)?;
+    let mut leaves = Vec::new();
+    while !reader.is_end() {
+        let key = reader.h256().ok_or(Error::StoreCorrupted)?;
+        let leaf = reader.bytes().ok_or(Error::StoreCorrupted)?;
+        leaves.push((key, leaf));
+    }
+    let sent = SentBatch {
+        issuer: sent_issuer,
+        leaves,
+    };
+    Ok((issuer, Some(sent)))
+}
+
+fn compile_proof(smt: &IssuerSmt, old_leaves: &[(H256, Vec<u8>)]) -> Result<Vec<u8>, Error> {
+    // nothing changed, the contract requires no proof
+    if old_leaves.is_empty() {
+        return Ok(Vec::new());
+    }
+    let keys = old_leaves.iter().map(|(key, _)| *key).collect();
+    let leaves = old_leaves
+        .iter()
+        .map(|(key, leaf)| (*key, NftValue(leaf.clone()).to_h256()))
+        .collect();
+    Ok(smt.merkle_proof(keys)?.compile(leaves)?.0)
+}
+
+impl IssuerState {
+    /// Open the state of the issuer stored at the path, a new store starts from the
+    /// issuer cell created with an empty tree
+    pub fn open<P: AsRef<Path>>(path: P, issuer_id: [u8; 20]) -> Result<Self, Error> {
+        let store = FileStore::open(path)?;
+        let checkpoint = store.checkpoint();
+        let (issuer, sent) = if checkpoint.is_empty() {
+            let issuer = Issuer {
+                version: 0,
+                class_count: 0,
+                set_count: 0,
+                smt_root: [0u8; 32],
+                info: Vec::new(),
+            };
+            (issuer, None)
+        } else {
+            decode_checkpoint(&issuer_id, checkpoint)?
+        };
+        let smt = IssuerSmt::new(issuer.smt_root.into(), store);
+        Ok(IssuerState {
+            issuer_id,
+            issuer,
+            smt,
+            pending: None,
+            sent,
+        })
+    }
+
+    pub fn issuer_id(&self) -> &[u8; 20] {
+        &self.issuer_id
+    }
+
+    /// The issuer cell data, including the pending batch
+    pub fn issuer(&self) -> &Issuer {
+        &self.issuer
+    }
+
+    pub fn root(&self) -> &H256 {
+        self.smt.root()
+    }
+
+    pub fn smt(&self) -> &IssuerSmt {
+        &self.smt
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Persist the batch which was pending when the store was written last if the issuer
+    /// cell is the one created by its L1 transaction, i.e. the transaction is committed but
+    /// the batch wasn't persisted. Nothing is changed for another issuer cell.
+    pub fn recover(&mut self, issuer: &Issuer) -> Result<(), Error> {
+        if self.pending.is_some() {
+            return Err(Error::BatchPending);
+        }
+        let sent = match self.sent.take() {
+            Some(sent) if same_tree(&sent.issuer, issuer) => sent,
+            sent => {
+                self.sent = sent;
+                return Ok(());
+            }
+        };
+        update_leaves(&mut self.smt, &sent.leaves)?;
+        if sent.issuer.smt_root[..] != self.smt.root().as_slice()[..] {
+            return Err(Error::StoreCorrupted);
+        }
+        self.issuer = sent.issuer;
+        let checkpoint = encode_checkpoint(&self.issuer_id, &self.issuer, None)?;
+        self.smt.store_mut().flush(&checkpoint)
+    }
+
+    /// The leaf of the key, empty if the key is absent
+    pub fn leaf(&self, key: &H256) -> Result<Vec<u8>, Error> {
+        Ok(self.smt.get(key)?.0)
+    }
+
+    pub fn leaves(&self) -> impl Iterator<Item = (&H256, &[u8])> {
+        self.smt
+            .store()
+            .leaves()
+            .map(|(key, value)| (key, value.0.as_slice()))
+    }
+
+    /// Apply a batch of transactions to the tree and return the issuer cell data and
+    /// the proof of the L1 transaction. The leaves of the nft and class cells consumed by
+    /// insert transactions are given by inserted_leaves.
+    /// Nothing is changed if any transaction fails.
+    pub fn apply(
+        &mut self,
+        txs: &NftTransactionVec,
+        inserted_leaves: &[Vec<u8>],
+    ) -> Result<IssuerUpdate, Error> {
+        if self.pending.is_some() {
+            return Err(Error::BatchPending);
+        }
+
+        let mut transition =
+            Transition::new(&self.smt, self.issuer_id, &self.issuer, inserted_leaves);
+        for tx in txs.clone().into_iter() {
+            transition.apply(&tx)?;
+        }
+        let class_count = transition.class_count();
+        let set_count = transition.set_count();
+        let (old_leaves, new_leaves): (Vec<_>, Vec<_>) = transition
+            .into_updates()
+            .into_iter()
+            .map(|(key, (old_leaf, new_leaf))| ((key, old_leaf), (key, new_leaf)))
+            .unzip();
+
+        let proof = compile_proof(&self.smt, &old_leaves)?;
+        if let Err(err) = update_leaves(&mut self.smt, &new_leaves) {
+            // the keys updated before the failure are restored
+            restore_leaves(&mut self.smt, &old_leaves)?;
+            return Err(err);
+        }
+
+        let mut issuer = self.issuer.clone();
+        issuer.class_count = class_count;
+        issuer.set_count = set_count;
+        issuer.smt_root.copy_from_slice(self.smt.root().as_slice());
+        // the batch is written with the checkpoint before its L1 transaction can be sent
+        let sent = SentBatch {
+            issuer: issuer.clone(),
+            leaves: new_leaves,
+        };
+        let written = encode_checkpoint(&self.issuer_id, &self.issuer, Some(&sent))
+            .and_then(|checkpoint| self.smt.store_mut().write_checkpoint(&checkpoint));
+        if let Err(err) = written {
+            restore_leaves(&mut self.smt, &old_leaves)?;
+            return Err(err);
+        }
+        let new_leaves = sent.leaves;
+        self.sent = None;
+        self.pending = Some(PendingBatch {
+            issuer: std::mem::replace(&mut self.issuer, issuer.clone()),
+            old_leaves,
+        });
+
+        let action = SmtUpdateAction::new_builder()
+            .proof(
+                SmtProof::new_builder()
+                    .set(proof.into_iter().map(Byte::new).collect())
+                    .build(),
+            )
+            .build();
+        Ok(IssuerUpdate {
+            issuer,
+            action,
+            leaves: new_leaves,
+        })
+    }
+
+    /// The L1 transaction of the pending batch is committed, persist the batch
+    pub fn commit(&mut self) -> Result<(), Error> {
+        if self.pending.is_none() {
+            return Err(Error::NoPendingBatch);
+        }
+        let checkpoint = encode_checkpoint(&self.issuer_id, &self.issuer, None)?;
+        self.smt.store_mut().flush(&checkpoint)?;
+        self.pending = None;
+        Ok(())
+    }
+
+    /// The L1 transaction of the pending batch has failed, restore the tree before the batch
+    /// and drop its changes from the store. The batch stays pending if the tree can't be
+    /// restored, so the rollback can be retried.
+    /// The batch is left in the checkpoint, but it's only recovered for the issuer cell
+    /// created by it.
+    pub fn rollback(&mut self) -> Result<(), Error> {
+        let pending = self.pending.as_ref().ok_or(Error::NoPendingBatch)?;
+        restore_leaves(&mut self.smt, &pending.old_leaves)?;
+        if let Some(pending) = self.pending.take() {
+            self.issuer = pending.issuer;
+        }
+        Ok(())
+    }
+
+    /// Shrink the store file to the leaves of the last committed batch
+    pub fn compact(&mut self) -> Result<(), Error> {
+        if self.pending.is_some() {
+            return Err(Error::BatchPending);
+        }
+        self.smt.store_mut().compact()
+    }
+}
@@ -0,0 +1,376 @@
+use crate::error::Error;
+use script_utils::misc::NftValue;
+use sparse_merkle_tree::{
+    default_store::DefaultStore,
+    error::Error as SmtError,
+    traits::Store,
+    tree::{BranchNode, LeafNode, NodeType},
+    H256,
+};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+const INSERT_BRANCH: u8 = 0;
+const INSERT_LEAF: u8 = 1;
+const REMOVE_BRANCH: u8 = 2;
+const REMOVE_LEAF: u8 = 3;
+const CHECKPOINT: u8 = 4;
+
+const SINGLE_NODE: u8 = 0;
+const PAIR_NODE: u8 = 1;
+
+/// A SMT store persisted in an append-only log file
+/// The nodes are kept in memory and every change of them is appended to the log as a record:
+/// 1) insert branch: <0> + node + fork_height: u8 + key + <0> + node | <1> + node + sibling
+/// 2) insert leaf: <1> + leaf_hash + key + <size: u32> + <value>
+/// 3) remove branch: <2> + node
+/// 4) remove leaf: <3> + leaf_hash
+/// 5) checkpoint: <4> + <size: u32> + <data>
+/// The changes are only written by flush with a checkpoint after them, a log whose tail is
+/// not ended by a checkpoint(e.g. the process crashed while writing) is opened at the last
+/// checkpoint. The changes not written yet can be reverted instead.
+pub struct FileStore {
+    path: PathBuf,
+    file: File,
+    // the length of the log up to the last checkpoint
+    len: u64,
+    nodes: DefaultStore<NftValue>,
+    // records not written yet
+    journal: Vec<u8>,
+    // the nodes replaced by the records not written yet, in the order of the changes
+    undo: Vec<Undo>,
+    checkpoint: Vec<u8>,
+}
+
+// a node before a change, None if the change inserts it
+enum Undo {
+    Branch(H256, Option<BranchNode>),
+    Leaf(H256, Option<LeafNode<NftValue>>),
+}
+
+enum Record {
+    InsertBranch(H256, BranchNode),
+    InsertLeaf(H256, LeafNode<NftValue>),
+    RemoveBranch(H256),
+    RemoveLeaf(H256),
+    Checkpoint(Vec<u8>),
+}
+
+impl Record {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Record::InsertBranch(node, branch) => {
+                buf.push(INSERT_BRANCH);
+                buf.extend_from_slice(node.as_slice());
+                buf.push(branch.fork_height);
+                buf.extend_from_slice(branch.key.as_slice());
+                match &branch.node_type {
+                    NodeType::Single(node) => {
+                        buf.push(SINGLE_NODE);
+                        buf.extend_from_slice(node.as_slice());
+                    }
+                    NodeType::Pair(node, sibling) => {
+                        buf.push(PAIR_NODE);
+                        buf.extend_from_slice(node.as_slice());
+                        buf.extend_from_slice(sibling.as_slice());
+                    }
+                }
+            }
+            Record::InsertLeaf(leaf_hash, leaf) => {
+                buf.push(INSERT_LEAF);
+                buf.extend_from_slice(leaf_hash.as_slice());
+                buf.extend_from_slice(leaf.key.as_slice());
+                buf.extend_from_slice(&(leaf.value.0.len() as u32).to_be_bytes());
+                buf.extend_from_slice(&leaf.value.0);
+            }
+            Record::RemoveBranch(node) => {
+                buf.push(REMOVE_BRANCH);
+                buf.extend_from_slice(node.as_slice());
+            }
+            Record::RemoveLeaf(leaf_hash) => {
+                buf.push(REMOVE_LEAF);
+                buf.extend_from_slice(leaf_hash.as_slice());
+            }
+            Record::Checkpoint(data) => {
+                buf.push(CHECKPOINT);
+                buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+                buf.extend_from_slice(data);
+            }
+        }
+    }
+
+    // returns None if the record is cut off by the end of the log
+    fn decode(reader: &mut Reader) -> Result<Option<Self>, Error> {
+        let tag = match reader.u8() {
+            Some(tag) => tag,
+            None => return Ok(None),
+        };
+        let record = match tag {
+            INSERT_BRANCH => (|| {
+                let node = reader.h256()?;
+                let fork_height = reader.u8()?;
+                let key = reader.h256()?;
+                let node_type = match reader.u8()? {
+                    SINGLE_NODE => NodeType::Single(reader.h256()?),
+                    PAIR_NODE => NodeType::Pair(reader.h256()?, reader.h256()?),
+                    _ => return Some(Err(Error::StoreCorrupted)),
+                };
+                let branch = BranchNode {
+                    fork_height,
+                    key,
+                    node_type,
+                };
+                Some(Ok(Record::InsertBranch(node, branch)))
+            })(),
+            INSERT_LEAF => (|| {
+                let leaf_hash = reader.h256()?;
+                let key = reader.h256()?;
+                let value = NftValue(reader.bytes()?);
+                Some(Ok(Record::InsertLeaf(leaf_hash, LeafNode { key, value })))
+            })(),
+            REMOVE_BRANCH => reader.h256().map(|node| Ok(Record::RemoveBranch(node))),
+            REMOVE_LEAF => reader
+                .h256()
+                .map(|leaf_hash| Ok(Record::RemoveLeaf(leaf_hash))),
+            CHECKPOINT => reader.bytes().map(|data| Ok(Record::Checkpoint(data))),
+            _ => return Err(Error::StoreCorrupted),
+        };
+        record.transpose()
+    }
+}
+
+/// Reads the fields of a record, every field is None if the data is cut off
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    pub(crate) fn is_end(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(len)?;
+        let slice = self.data.get(self.pos..end)?;
+        self.pos = end;
+        Some(slice)
+    }
+
+    pub(crate) fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|slice| slice[0])
+    }
+
+    pub(crate) fn h256(&mut self) -> Option<H256> {
+        let mut buf = [0u8; 32];
+        buf.copy_from_slice(self.take(32)?);
+        Some(buf.into())
+    }
+
+    // <size: u32> + <content>
+    pub(crate) fn bytes(&mut self) -> Option<Vec<u8>> {
+        let mut len = [0u8; 4];
+        len.copy_from_slice(self.take(4)?);
+        self.take(u32::from_be_bytes(len) as usize)
+            .map(|slice| slice.to_vec())
+    }
+}
+
+fn apply_records(nodes: &mut DefaultStore<NftValue>, records: Vec<Record>) -> Result<(), Error> {
+    for record in records {
+        match record {
+            Record::InsertBranch(node, branch) => nodes.insert_branch(node, branch)?,
+            Record::InsertLeaf(leaf_hash, leaf) => nodes.insert_leaf(leaf_hash, leaf)?,
+            Record::RemoveBranch(node) => nodes.remove_branch(&node)?,
+            Record::RemoveLeaf(leaf_hash) => nodes.remove_leaf(&leaf_hash)?,
+            Record::Checkpoint(_) => {}
+        }
+    }
+    Ok(())
+}
+
+impl FileStore {
+    /// Open the log at the path, the file is created if it doesn't exist
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+
+        let mut nodes = DefaultStore::default();
+        let mut checkpoint = Vec::new();
+        let mut len = 0;
+        let mut records = Vec::new();
+        let mut reader = Reader::new(&data);
+        while let Some(record) = Record::decode(&mut reader)? {
+            match record {
+                Record::Checkpoint(data) => {
+                    apply_records(&mut nodes, records.drain(..).collect())?;
+                    checkpoint = data;
+                    len = reader.pos as u64;
+                }
+                record => records.push(record),
+            }
+        }
+
+        // drop the changes after the last checkpoint, so new records follow it
+        if len < data.len() as u64 {
+            file.set_len(len)?;
+            file.sync_data()?;
+        }
+
+        Ok(FileStore {
+            path,
+            file,
+            len,
+            nodes,
+            journal: Vec::new(),
+            undo: Vec::new(),
+            checkpoint,
+        })
+    }
+
+    /// The data of the last checkpoint, empty if nothing is flushed yet
+    pub fn checkpoint(&self) -> &[u8] {
+        &self.checkpoint
+    }
+
+    /// The key and value of every leaf in the store
+    pub fn leaves(&self) -> impl Iterator<Item = (&H256, &NftValue)> {
+        self.nodes
+            .leaves_map()
+            .values()
+            .map(|leaf| (&leaf.key, &leaf.value))
+    }
+
+    /// Write the changes since the last flush and a new checkpoint after them
+    pub fn flush(&mut self, checkpoint: &[u8]) -> Result<(), Error> {
+        let mut data = std::mem::take(&mut self.journal);
+        let changes_len = data.len();
+        Record::Checkpoint(checkpoint.to_vec()).encode(&mut data);
+        if let Err(err) = self.append(&data) {
+            data.truncate(changes_len);
+            self.journal = data;
+            return Err(err);
+        }
+
+        self.undo.clear();
+        self.checkpoint = checkpoint.to_vec();
+        Ok(())
+    }
+
+    /// Write a new checkpoint after the changes written so far, the changes since the last
+    /// flush stay unwritten, so they can still be flushed or reverted
+    pub fn write_checkpoint(&mut self, checkpoint: &[u8]) -> Result<(), Error> {
+        let mut data = Vec::new();
+        Record::Checkpoint(checkpoint.to_vec()).encode(&mut data);
+        self.append(&data)?;
+        self.checkpoint = checkpoint.to_vec();
+        Ok(())
+    }
+
+    /// Drop the changes since the last flush, the nodes are the written ones again
+    pub fn revert(&mut self) -> Result<(), Error> {
+        while let Some(undo) = self.undo.pop() {
+            match undo {
+                Undo::Branch(node, Some(branch)) => self.nodes.insert_branch(node, branch)?,
+                Undo::Branch(node, None) => self.nodes.remove_branch(&node)?,
+                Undo::Leaf(leaf_hash, Some(leaf)) => self.nodes.insert_leaf(leaf_hash, leaf)?,
+                Undo::Leaf(leaf_hash, None) => self.nodes.remove_leaf(&leaf_hash)?,
+            }
+        }
+        self.journal.clear();
+        Ok(())
+    }
+
+    // append the records to the log, a partial write is cut off or the next records
+    // would follow it
+    fn append(&mut self, data: &[u8]) -> Result<(), Error> {
+        let written = self
+            .file
+            .write_all(data)
+            .and_then(|_| self.file.sync_data());
+        if let Err(err) = written {
+            self.file.set_len(self.len)?;
+            return Err(err.into());
+        }
+        self.len += data.len() as u64;
+        Ok(())
+    }
+
+    /// Rewrite the log with the current nodes and the last checkpoint only.
+    /// The nodes must be the ones of the last checkpoint, i.e. there is no change to flush
+    /// or the changes are undone.
+    pub fn compact(&mut self) -> Result<(), Error> {
+        let mut data = Vec::new();
+        for (node, branch) in self.nodes.branches_map() {
+            Record::InsertBranch(*node, branch.clone()).encode(&mut data);
+        }
+        for (leaf_hash, leaf) in self.nodes.leaves_map() {
+            Record::InsertLeaf(*leaf_hash, leaf.clone()).encode(&mut data);
+        }
+        Record::Checkpoint(self.checkpoint.clone()).encode(&mut data);
+
+        // replace the log only after the new one is completely written
+        let tmp_path = self.path.with_extension("compact");
+        {
+            let mut tmp_file = File::create(&tmp_path)?;
+            tmp_file.write_all(&data)?;
+            tmp_file.sync_all()?;
+        }
+        fs::rename(&tmp_path, &self.path)?;
+
+        self.file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&self.path)?;
+        self.len = data.len() as u64;
+        self.journal.clear();
+        self.undo.clear();
+        Ok(())
+    }
+}
+
+impl Store<NftValue> for FileStore {
+    fn get_branch(&self, node: &H256) -> Result<Option<BranchNode>, SmtError> {
+        self.nodes.get_branch(node)
+    }
+    fn get_leaf(&self, leaf_hash: &H256) -> Result<Option<LeafNode<NftValue>>, SmtError> {
+        self.nodes.get_leaf(leaf_hash)
+    }
+    fn insert_branch(&mut self, node: H256, branch: BranchNode) -> Result<(), SmtError> {
+        let old_branch = self.nodes.get_branch(&node)?;
+        self.undo.push(Undo::Branch(node, old_branch));
+        Record::InsertBranch(node, branch.clone()).encode(&mut self.journal);
+        self.nodes.insert_branch(node, branch)
+    }
+    fn insert_leaf(&mut self, leaf_hash: H256, leaf: LeafNode<NftValue>) -> Result<(), SmtError> {
+        let old_leaf = self.nodes.get_leaf(&leaf_hash)?;
+        self.undo.push(Undo::Leaf(leaf_hash, old_leaf));
+        Record::InsertLeaf(leaf_hash, leaf.clone()).encode(&mut self.journal);
+        self.nodes.insert_leaf(leaf_hash, leaf)
+    }
+    fn remove_branch(&mut self, node: &H256) -> Result<(), SmtError> {
+        let old_branch = self.nodes.get_branch(node)?;
+        self.undo.push(Undo::Branch(*node, old_branch));
+        Record::RemoveBranch(*node).encode(&mut self.journal);
+        self.nodes.remove_branch(node)
+    }
+    fn remove_leaf(&mut self, leaf_hash: &H256) -> Result<(), SmtError> {
+        let old_leaf = self.nodes.get_leaf(leaf_hash)?;
+        self.undo.push(Undo::Leaf(*leaf_hash, old_leaf));
+        Record::RemoveLeaf(*leaf_hash).encode(&mut self.journal);
+        self.nodes.remove_leaf(leaf_hash)
+    }
+}
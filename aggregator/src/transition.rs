@@ -0,0 +1,210 @@
+use crate::{error::Error, state::IssuerSmt};
+use mol::{ckb_types::prelude::Entity, NftTransaction};
+use script_utils::{
+    error::Error as ScriptError,
+    helpers::{
+        CLASS_ITEM, CREATE_SET_TRANSACTION, DISTRIBUTE_TRANSACTION, EXTRACT_TRANSACTION,
+        INSERT_TRANSACTION, ISSUE_TRANSACTION, NFT_ITEM, TRANSFER_TRANSACTION, UPDATE_TRANSACTION,
+    },
+    issuer::Issuer,
+    transition::{self, parse_item, recover_signer, Authorize, Item, LeafUpdates},
+};
+use sparse_merkle_tree::H256;
+use std::collections::BTreeMap;
+
+/// The leaf transitions of a batch of NftTransactions on an issuer tree, the new leaves
+/// are built by the transitions the contract checks.
+/// A key may be touched by several transactions of the batch, so only the first old leaf
+/// and the last new leaf of every key are kept. An empty leaf means the key is absent.
+/// The owners of the leaves are only checked if the lock hashes of the input cells are
+/// given by authorize_by.
+pub struct Transition<'a> {
+    smt: &'a IssuerSmt,
+    issuer_id: [u8; 20],
+    class_count: u32,
+    set_count: u32,
+    // leaves of the consumed nft, class and set cells put back by insert transactions
+    inserted_leaves: &'a [Vec<u8>],
+    updates: BTreeMap<H256, (Vec<u8>, Vec<u8>)>,
+    input_lock_hashes: Option<&'a [[u8; 32]]>,
+}
+
+/// The owners authorize a transaction either by signing it or by a cell with their lock
+/// in the inputs of the L1 transaction, anyone is authorized without the input lock hashes
+struct Authorization<'a> {
+    input_lock_hashes: Option<&'a [[u8; 32]]>,
+    // lock hash of the signer
+    signer: Option<[u8; 32]>,
+}
+
+impl<'a> Authorize for Authorization<'a> {
+    fn check(&self, owner: &[u8; 32]) -> Result<(), ScriptError> {
+        let input_lock_hashes = match self.input_lock_hashes {
+            Some(input_lock_hashes) => input_lock_hashes,
+            None => return Ok(()),
+        };
+        if self.signer.as_ref() == Some(owner) || input_lock_hashes.contains(owner) {
+            return Ok(());
+        }
+        Err(ScriptError::OwnerNotAuthorized)
+    }
+}
+
+// decode the raw transaction of the NftTransaction according to its typ
+pub fn parse_raw_transaction<T: Entity>(tx: &NftTransaction) -> Result<T, Error> {
+    Ok(transition::parse_raw_transaction(tx)?)
+}
+
+impl<'a> Transition<'a> {
+    pub fn new(
+        smt: &'a IssuerSmt,
+        issuer_id: [u8; 20],
+        issuer: &Issuer,
+        inserted_leaves: &'a [Vec<u8>],
+    ) -> Self {
+        Transition {
+            smt,
+            issuer_id,
+            class_count: issuer.class_count,
+            set_count: issuer.set_count,
+            inserted_leaves,
+            updates: BTreeMap::new(),
+            input_lock_hashes: None,
+        }
+    }
+
+    /// Check the owners of the touched leaves, they authorize a transaction either by
+    /// signing it or by a cell with their lock in the inputs of the L1 transaction
+    pub fn authorize_by(mut self, input_lock_hashes: &'a [[u8; 32]]) -> Self {
+        self.input_lock_hashes = Some(input_lock_hashes);
+        self
+    }
+
+    pub fn class_count(&self) -> u32 {
+        self.class_count
+    }
+
+    pub fn set_count(&self) -> u32 {
+        self.set_count
+    }
+
+    /// The leaf of the key after the transactions applied so far
+    pub fn leaf(&self, key: &H256) -> Result<Vec<u8>, Error> {
+        match self.updates.get(key) {
+            Some((_, new_leaf)) => Ok(new_leaf.clone()),
+            None => Ok(self.smt.get(key)?.0),
+        }
+    }
+
+    /// Consume the transition and return the old and new leaf of every touched key
+    pub fn into_updates(self) -> BTreeMap<H256, (Vec<u8>, Vec<u8>)> {
+        self.updates
+    }
+
+    pub fn apply(&mut self, tx: &NftTransaction) -> Result<(), Error> {
+        // the signature is only recovered when the owners are checked
+        let signer = match self.input_lock_hashes {
+            Some(_) => recover_signer(&self.issuer_id, tx)?,
+            None => None,
+        };
+        let auth = Authorization {
+            input_lock_hashes: self.input_lock_hashes,
+            signer,
+        };
+        let issuer_id = self.issuer_id;
+        match tx.typ().into() {
+            ISSUE_TRANSACTION => {
+                transition::issue(
+                    &issuer_id,
+                    self.class_count,
+                    parse_raw_transaction(tx)?,
+                    self,
+                )?;
+                self.class_count = self
+                    .class_count
+                    .checked_add(1)
+                    .ok_or(ScriptError::IssuerClassCountError)?;
+                Ok(())
+            }
+            DISTRIBUTE_TRANSACTION => {
+                transition::distribute(&issuer_id, &auth, parse_raw_transaction(tx)?, self)
+            }
+            TRANSFER_TRANSACTION => {
+                transition::transfer(Some(&issuer_id), &auth, parse_raw_transaction(tx)?, self)
+            }
+            UPDATE_TRANSACTION => {
+                transition::update_nfts(Some(&issuer_id), &auth, parse_raw_transaction(tx)?, self)
+            }
+            // the extracted leaves become cells of the L1 transaction
+            EXTRACT_TRANSACTION => transition::extract(
+                Some(&issuer_id),
+                &auth,
+                parse_raw_transaction(tx)?,
+                self,
+                |_| Ok(()),
+            )
+            .map(|_| ()),
+            INSERT_TRANSACTION => {
+                let inserted_leaves = self.inserted_leaves;
+                transition::insert(
+                    Some(&issuer_id),
+                    None,
+                    parse_raw_transaction(tx)?,
+                    self,
+                    |typ, id| find_inserted_item(inserted_leaves, &issuer_id, typ, id.as_slice()),
+                )
+                .map(|_| ())
+            }
+            CREATE_SET_TRANSACTION => {
+                transition::create_set(
+                    &issuer_id,
+                    self.set_count,
+                    parse_raw_transaction(tx)?,
+                    self,
+                )?;
+                self.set_count = self
+                    .set_count
+                    .checked_add(1)
+                    .ok_or(ScriptError::IssuerSetCountError)?;
+                Ok(())
+            }
+            _ => Err(ScriptError::NFTDataInvalid.into()),
+        }
+    }
+}
+
+impl<'a> LeafUpdates for Transition<'a> {
+    type Error = Error;
+
+    // the old leaf given by the transaction must be the current one
+    fn update(&mut self, key: H256, old_leaf: &[u8], new_leaf: &[u8]) -> Result<(), Error> {
+        let current_leaf = self.leaf(&key)?;
+        if current_leaf != old_leaf {
+            return Err(ScriptError::SMTLeafValueInvalid.into());
+        }
+        self.updates
+            .entry(key)
+            .or_insert_with(|| (current_leaf, Vec::new()))
+            .1 = new_leaf.to_vec();
+        Ok(())
+    }
+}
+
+// the leaf of a consumed nft, class or set cell put back by the insert transaction
+fn find_inserted_item(
+    inserted_leaves: &[Vec<u8>],
+    issuer_id: &[u8; 20],
+    typ: u8,
+    key: &[u8],
+) -> Result<Item, Error> {
+    let item = inserted_leaves
+        .iter()
+        .filter_map(|leaf| parse_item(typ, Some(issuer_id), leaf).ok())
+        .find(|item| item.key().as_slice() == key);
+    match (item, typ) {
+        (Some(item), _) => Ok(item),
+        (None, NFT_ITEM) => Err(ScriptError::NFTCellsCountError.into()),
+        (None, CLASS_ITEM) => Err(ScriptError::ClassCellsCountError.into()),
+        (None, _) => Err(ScriptError::NFTSetCellsCountError.into()),
+    }
+}
@@ -1,13 +1,28 @@
-use alloc::vec::Vec;
-use ckb_std::{ckb_constants::Source, ckb_types::{packed::Byte, prelude::{Entity, Unpack}}, high_level::load_witness_args};
-use mol::NftTransactionVec;
+use ckb_std::{
+    ckb_constants::Source,
+    ckb_types::{
+        bytes::Bytes,
+        packed::Byte,
+        prelude::{Entity, Unpack},
+    },
+    high_level::{load_cell_data, load_cell_lock_hash, load_script},
+};
+use mol::RawDistributeTransaction;
 use script_utils::{
-    class::Class,
+    class::{Class, CLASS_TYPE_ARGS_LEN},
     error::Error,
     helpers::{
-        DISTRIBUTE_TRANSACTION, EXTRACT_TRANSACTION, INSERT_TRANSACTION,
-        TRANSFER_TRANSACTION, UPDATE_TRANSACTION,
+        count_unpaired_cells, load_cell_index_by_type, load_output_type_args_ids, CLASS_CELL,
+        DISTRIBUTE_TRANSACTION, NFT_CELL,
     },
+    nft::{Nft, NFT_TYPE_ARGS_LEN},
+    type_args::TypeArgs,
+};
+
+use crate::{
+    nft::{count_created_nft_cells, count_destroyed_nft_cells},
+    transaction::{load_nft_transactions, parse_raw_transaction},
+    validator::{validate_class_ext_info, validate_class_renderer},
 };
 
 pub fn handle_destroying_class(input_class: Class) -> Result<(), Error> {
@@ -26,30 +41,140 @@ pub fn handle_update_class(input_class: Class, output_class: Class) -> Result<()
         return Err(Error::ClassImmutableFieldsNotSame);
     }
 
-    let witness_args = load_witness_args(0, Source::GroupInput)?;
-    let lock_type = witness_args.lock();
-    //得到交易
-    let txs = if let Some(lock_type) = lock_type.to_opt() {
-        let lock_type: Vec<u8> = lock_type.unpack();
-        NftTransactionVec::from_compatible_slice(&lock_type).unwrap()
-    } else {
-        return Err(Error::ItemMissing);
-    };
+    // the renderer can only be changed by the owner, not while the class cell is handed
+    // to another lock
+    let owner_kept =
+        load_cell_lock_hash(0, Source::GroupInput)? == load_cell_lock_hash(0, Source::GroupOutput)?;
+    let classes = (input_class, output_class);
+    validate_class_renderer(&classes, owner_kept)?;
+    validate_class_ext_info(&classes)?;
+    let (mut input_class, mut output_class) = classes;
+
+    let txs = load_nft_transactions()?;
 
-    // let mut old_keys = Vec::new();
-    // let mut new_keys = Vec::new();
+    let script = load_script()?;
+    let args: Bytes = script.args().unpack();
+    let type_args = TypeArgs::from_slice(&args)?;
+    type_args.fill_class(&mut input_class)?;
+    type_args.fill_class(&mut output_class)?;
+    let mut issued = input_class.issued;
 
     // class_cell内不存在Issue交易
     for tx in txs.into_iter() {
         match <Byte as Into<u8>>::into(tx.typ()) {
-            DISTRIBUTE_TRANSACTION => {}
-            TRANSFER_TRANSACTION => {}
-            UPDATE_TRANSACTION => {}
-            EXTRACT_TRANSACTION => {}
-            INSERT_TRANSACTION => {}
-            _ => return Err(Error::NFTDataInvalid),
+            DISTRIBUTE_TRANSACTION => {
+                let distribute_tx: RawDistributeTransaction = parse_raw_transaction(&tx)?;
+                issued = handle_distribute(
+                    script.code_hash().as_slice(),
+                    &input_class,
+                    issued,
+                    distribute_tx,
+                )?;
+            }
+            // the other transactions work on a tree, a class cell has none
+            _ => return Err(Error::TransactionNotSupported),
         }
     }
 
+    if input_class.total > 0 && issued > input_class.total {
+        return Err(Error::ClassTotalSmallerThanIssued);
+    }
+    if output_class.issued != issued {
+        return Err(Error::ClassIssuedInvalid);
+    }
+
+    // nft cells can only be created by distributing them from the class
+    let code_hash = script.code_hash();
+    if count_created_nft_cells(code_hash.as_slice()) != (issued - input_class.issued) as usize {
+        return Err(Error::NFTCellsCountError);
+    }
+
+    // a class cell has no tree to insert nft cells into, so the consumed nft cells are
+    // burned and the class counts them, each of them checks its class is this one
+    let burned = output_class
+        .burned
+        .checked_sub(input_class.burned)
+        .ok_or(Error::ClassBurnedInvalid)?;
+    if count_destroyed_nft_cells(code_hash.as_slice()) != burned as usize {
+        return Err(Error::ClassBurnedInvalid);
+    }
+
+    check_created_token_ids(code_hash.as_slice(), &input_class, issued)
+}
+
+// the nft cells of the class created by the transaction must take the token ids
+// from the old issued to the new issued one by one
+fn check_created_token_ids(code_hash: &[u8], class: &Class, issued: u32) -> Result<(), Error> {
+    let class_args = TypeArgs::from_class(class).to_vec();
+    let mut token_ids = load_output_type_args_ids(code_hash, CLASS_TYPE_ARGS_LEN, &|args| {
+        args.len() == NFT_TYPE_ARGS_LEN && args[..CLASS_TYPE_ARGS_LEN] == class_args[..]
+    });
+    if token_ids.len() != (issued - class.issued) as usize {
+        return Err(Error::NFTCellsCountError);
+    }
+
+    token_ids.sort_unstable();
+    for (index, token_id) in token_ids.into_iter().enumerate() {
+        if class.issued.checked_add(index as u32) != Some(token_id) {
+            return Err(Error::NFTTokenIdIncreaseError);
+        }
+    }
     Ok(())
 }
+
+// a class cell has no SMT, so the distributed nfts must be created as nft cells,
+// class_old_value is not needed because the class lives in the cell itself
+fn handle_distribute(
+    code_hash: &[u8],
+    class: &Class,
+    issued: u32,
+    distribute_tx: RawDistributeTransaction,
+) -> Result<u32, Error> {
+    let targets = distribute_tx.to();
+    let count = targets.len() as u32;
+    for (index, target) in targets.into_iter().enumerate() {
+        let token_id = issued
+            .checked_add(index as u32)
+            .ok_or(Error::ClassIssuedInvalid)?;
+        let nft_args = TypeArgs::Nft {
+            issuer_id: class.issuer_id,
+            class_id: class.class_id,
+            token_id,
+        }
+        .to_vec();
+
+        // the distributed nft cells take the token ids from issued one by one
+        let nft_index = load_cell_index_by_type(Source::Output, code_hash, &nft_args)
+            .ok_or(Error::NFTTokenIdIncreaseError)?;
+        if load_cell_lock_hash(nft_index, Source::Output)?[..] != target.as_slice()[..] {
+            return Err(Error::NFTOwnerNotMatch);
+        }
+
+        let nft_data = load_cell_data(nft_index, Source::Output)?;
+        if nft_data.is_empty() || nft_data[0] != NFT_CELL {
+            return Err(Error::NFTDataInvalid);
+        }
+        let nft = Nft::from_data_cell(&nft_data[1..])?;
+        if nft.configure != class.configure {
+            return Err(Error::NFTAndClassConfigureNotSame);
+        }
+        // the same nft as distributing it into the tree builds, see transition::distribute
+        let distributed = Nft {
+            configure: class.configure,
+            ..Nft::default()
+        };
+        if nft_data[1..] != distributed.to_data(true)[..] {
+            return Err(Error::NFTDataInvalid);
+        }
+    }
+
+    issued.checked_add(count).ok_or(Error::ClassIssuedInvalid)
+}
+
+pub fn count_created_class_cells(code_hash: &[u8]) -> usize {
+    count_unpaired_cells(Source::Output, Source::Input, code_hash, CLASS_CELL)
+}
+
+pub fn count_destroyed_class_cells(code_hash: &[u8]) -> usize {
+    count_unpaired_cells(Source::Input, Source::Output, code_hash, CLASS_CELL)
+}
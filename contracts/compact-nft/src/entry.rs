@@ -4,17 +4,19 @@ use core::{mem, result::Result};
 // Import CKB syscalls and structures
 // https://nervosnetwork.github.io/ckb-std/riscv64imac-unknown-none-elf/doc/ckb_std/index.html
 use ckb_std::{
-    ckb_constants::Source::{GroupInput, GroupOutput, Input, Output},
+    ckb_constants::Source::{self, GroupInput, GroupOutput, Input, Output},
     ckb_types::prelude::*,
-    high_level::{load_cell_data, load_cell_type},
+    high_level::{load_cell_data, load_script},
 };
 use script_utils::{
     class::Class,
     helpers::{
-        load_smt_cell_count_by_code_hash, CLASS_CELL, ISSUER_CELL, MULTI_OWNER_CELL,
-        SINGLE_OWNER_CELL,
+        load_smt_cell_count_by_code_hash, CLASS_CELL, ISSUER_CELL, MULTI_OWNER_CELL, NFT_CELL,
+        NFT_SET_CELL, SINGLE_OWNER_CELL,
     },
     issuer::Issuer,
+    multi_owner::MultiOwner,
+    single_owner::SingleOwner,
 };
 
 use crate::{
@@ -23,16 +25,59 @@ use crate::{
     issuer::{handle_creation_issuer, handle_update_issuer},
 };
 
-use crate::{issuer::handle_destroying_issuer, nft::handle_update_nft};
+use crate::{
+    issuer::handle_destroying_issuer,
+    multi_owner::{
+        handle_creation_multi_owner, handle_destroying_multi_owner, handle_update_multi_owner,
+    },
+    nft::{handle_nft, handle_nft_with_smt_update},
+    nft_set::{handle_nft_set, handle_nft_set_with_smt_update},
+    single_owner::{
+        handle_creation_single_owner, handle_destroying_single_owner, handle_update_single_owner,
+    },
+};
 
 fn check_code_hash<'a>(hash: &'a [u8]) -> impl Fn(&[u8]) -> bool + 'a {
     move |code_hash: &[u8]| code_hash[0..32] == hash[0..32]
 }
 
+// a cell created or consumed alongside the SMT cell update, which has no cell of the
+// same type script on the other side, is only valid if its type is counted by the
+// handler of the SMT cell
+fn check_unpaired_cell(source: Source, counted_cell_types: &[u8]) -> Result<(), Error> {
+    let data = load_cell_data(0, source)?;
+    match data.first() {
+        Some(cell_type) if counted_cell_types.contains(cell_type) => Ok(()),
+        _ => Err(Error::ClassDataInvalid),
+    }
+}
+
+// the first byte of the cell data of the current group, which tells the cell type
+fn load_group_cell_type() -> Result<Option<u8>, Error> {
+    let data = load_cell_data(0, GroupInput).or_else(|_| load_cell_data(0, GroupOutput))?;
+    Ok(data.first().copied())
+}
+
+// the current cell is not a SMT cell, it's a standalone nft cell or nft set cell
+fn handle_standalone_cell() -> Result<(), Error> {
+    match load_group_cell_type()? {
+        Some(NFT_SET_CELL) => handle_nft_set(),
+        _ => handle_nft(),
+    }
+}
+
+// the current cell is not the SMT cell of a transaction updating a SMT cell of the type
+fn handle_standalone_cell_with_smt_update(smt_cell_type: u8) -> Result<(), Error> {
+    match load_group_cell_type()? {
+        Some(NFT_SET_CELL) => handle_nft_set_with_smt_update(smt_cell_type == ISSUER_CELL),
+        _ => handle_nft_with_smt_update(),
+    }
+}
+
 pub fn main() -> Result<(), Error> {
-    // 得到当前脚本的code_hash
-    let type_ = load_cell_type(0, GroupInput).unwrap().unwrap();
-    let code_hash = type_.code_hash();
+    // 得到当前脚本的code_hash，新创建的cell不存在GroupInput
+    let script = load_script()?;
+    let code_hash = script.code_hash();
 
     // 计算输入的cell中同code_hash，且具有smt属性的cell的数量
     let (input_smt_cell_count, input_smt_cell_data) =
@@ -42,16 +87,16 @@ pub fn main() -> Result<(), Error> {
         load_smt_cell_count_by_code_hash(Output, &check_code_hash(code_hash.as_slice()));
 
     match (input_smt_cell_count, output_smt_cell_count) {
-        // 没有SMT_cell，只有一种情况，即游离态NFT的update，NFT只能在smt中创建或者销毁
+        // 没有SMT_cell，只有一种情况，即游离态NFT的update或者销毁，NFT只能在smt中创建
         (0, 0) => {
-            return handle_update_nft();
+            return handle_standalone_cell();
         }
         // 某种SMT_cell的销毁
         (1, 0) => {
-            // 如果当前type_script是NFT_cell，则处理nft_cell的update
-            let data = load_cell_data(0, GroupInput).unwrap();
+            // 如果当前type_script是NFT_cell，则处理nft_cell的update或者销毁
+            let data = load_cell_data(0, GroupInput)?;
             if data != input_smt_cell_data {
-                return handle_update_nft();
+                return handle_standalone_cell();
             }
             //否则的话，当前type_script处理销毁
             match input_smt_cell_data[0] {
@@ -67,11 +112,13 @@ pub fn main() -> Result<(), Error> {
                 }
                 // single_owner cell
                 SINGLE_OWNER_CELL => {
-                    todo!()
+                    let input_single_owner = SingleOwner::from_data(&input_smt_cell_data[1..])?;
+                    return handle_destroying_single_owner(input_single_owner);
                 }
                 // multi_owner cell
                 MULTI_OWNER_CELL => {
-                    todo!()
+                    let input_multi_owner = MultiOwner::from_data(&input_smt_cell_data[1..])?;
+                    return handle_destroying_multi_owner(input_multi_owner);
                 }
                 _ => return Err(Error::ClassDataInvalid),
             }
@@ -80,9 +127,9 @@ pub fn main() -> Result<(), Error> {
         // class_cell的创建只能从issuer中抽取
         (0, 1) => {
             // 如果当前type_script是NFT_cell，则处理nft_cell的update
-            let data = load_cell_data(0, GroupOutput).unwrap();
+            let data = load_cell_data(0, GroupOutput)?;
             if data != output_smt_cell_data {
-                return handle_update_nft();
+                return handle_standalone_cell();
             }
             match output_smt_cell_data[0] {
                 // issuer_cell
@@ -92,11 +139,13 @@ pub fn main() -> Result<(), Error> {
                 }
                 // single_owner cell
                 SINGLE_OWNER_CELL => {
-                    todo!()
+                    let single_owner = SingleOwner::from_data(&output_smt_cell_data[1..])?;
+                    return handle_creation_single_owner(single_owner);
                 }
                 // multi_owner cell
                 MULTI_OWNER_CELL => {
-                    todo!()
+                    let multi_owner = MultiOwner::from_data(&output_smt_cell_data[1..])?;
+                    return handle_creation_multi_owner(multi_owner);
                 }
                 _ => return Err(Error::ClassDataInvalid),
             }
@@ -105,9 +154,19 @@ pub fn main() -> Result<(), Error> {
         // 不允许与此交易附带游离NFT更新
         (1, 1) => {
             // 仅由SMT_cell来检查具体逻辑
-            let data = load_cell_data(0, GroupInput)?;
-            if data != input_smt_cell_data {
-                return Ok(());
+            match load_cell_data(0, GroupInput) {
+                Ok(data) => {
+                    // nft_cell被插入SMT，或者是nft_cell的update
+                    if data != input_smt_cell_data {
+                        return handle_standalone_cell_with_smt_update(input_smt_cell_data[0]);
+                    }
+                }
+                // 新创建的nft_cell不存在Input，由SMT_cell检查创建的nft_cell的数量
+                // 只有issuer_cell检查抽取的nft_set_cell
+                Err(_) if input_smt_cell_data[0] == ISSUER_CELL => {
+                    return check_unpaired_cell(GroupOutput, &[NFT_CELL, NFT_SET_CELL])
+                }
+                Err(_) => return check_unpaired_cell(GroupOutput, &[NFT_CELL]),
             }
 
             // 必须是同种类型的
@@ -127,10 +186,14 @@ pub fn main() -> Result<(), Error> {
                     return handle_update_class(input_class, output_class);
                 }
                 SINGLE_OWNER_CELL => {
-                    todo!()
+                    let input_single_owner = SingleOwner::from_data(&input_smt_cell_data[1..])?;
+                    let output_single_owner = SingleOwner::from_data(&output_smt_cell_data[1..])?;
+                    return handle_update_single_owner(input_single_owner, output_single_owner);
                 }
                 MULTI_OWNER_CELL => {
-                    todo!()
+                    let input_multi_owner = MultiOwner::from_data(&input_smt_cell_data[1..])?;
+                    let output_multi_owner = MultiOwner::from_data(&output_smt_cell_data[1..])?;
+                    return handle_update_multi_owner(input_multi_owner, output_multi_owner);
                 }
                 _ => return Err(Error::ClassDataInvalid),
             }
@@ -142,17 +205,22 @@ pub fn main() -> Result<(), Error> {
             match load_cell_data(0, GroupInput) {
                 Ok(data) => {
                     if data != input_smt_cell_data {
-                        return Ok(());
+                        return handle_standalone_cell_with_smt_update(input_smt_cell_data[0]);
                     }
                 }
-                // 对于class_cell来说，不存在Input
-                Err(_) => return Ok(()),
+                // 对于class_cell来说，不存在Input，由issuer_cell检查抽取的class_cell，nft_cell和nft_set_cell
+                // 其他SMT_cell不能与issuer_cell的更新一起创建
+                Err(_) => {
+                    return check_unpaired_cell(GroupOutput, &[NFT_CELL, CLASS_CELL, NFT_SET_CELL])
+                }
             }
             mem::drop(output_smt_cell_data);
             //当前Cell一定是SMT_cell了
             let output_smt_cell_data = load_cell_data(0, GroupOutput)?;
             // 必须是issuer_cell
-            if input_smt_cell_data[0] != ISSUER_CELL || output_smt_cell_data[0] != ISSUER_CELL {
+            if input_smt_cell_data.first() != Some(&ISSUER_CELL)
+                || output_smt_cell_data.first() != Some(&ISSUER_CELL)
+            {
                 return Err(Error::ClassDataInvalid);
             }
 
@@ -166,12 +234,15 @@ pub fn main() -> Result<(), Error> {
             // 仅由SMT_cell来检查具体逻辑
             match load_cell_data(0, GroupOutput) {
                 Ok(data) => {
-                    if data != input_smt_cell_data {
-                        return Ok(());
+                    if data != output_smt_cell_data {
+                        return handle_standalone_cell_with_smt_update(output_smt_cell_data[0]);
                     }
                 }
-                // 对于class_cell来说，不存在Output
-                Err(_) => return Ok(()),
+                // 对于class_cell来说，不存在Output，由issuer_cell检查插入的class_cell，nft_cell和nft_set_cell
+                // 其他SMT_cell不能与issuer_cell的更新一起销毁
+                Err(_) => {
+                    return check_unpaired_cell(GroupInput, &[NFT_CELL, CLASS_CELL, NFT_SET_CELL])
+                }
             }
 
             mem::drop(input_smt_cell_data);
@@ -180,7 +251,9 @@ pub fn main() -> Result<(), Error> {
             let input_smt_cell_data = load_cell_data(0, GroupInput)?;
 
             // 必须是issuer_cell
-            if input_smt_cell_data[0] != ISSUER_CELL || output_smt_cell_data[0] != ISSUER_CELL {
+            if input_smt_cell_data.first() != Some(&ISSUER_CELL)
+                || output_smt_cell_data.first() != Some(&ISSUER_CELL)
+            {
                 return Err(Error::ClassDataInvalid);
             }
 
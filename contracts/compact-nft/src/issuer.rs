@@ -1,42 +1,49 @@
-use alloc::vec::Vec;
 use ckb_std::{
-    ckb_constants::Source,
     ckb_types::{
         bytes::Bytes,
-        packed::Byte,
+        packed::{Byte, Script},
         prelude::{Entity, Unpack},
     },
     debug,
-    high_level::{load_script, load_witness_args},
+    high_level::load_script,
+};
+use mol::{
+    RawCreateSetTransaction, RawDistributeTransaction, RawExtractTransaction, RawInsertTransaction,
+    RawIssueTransaction, RawTransferTransaction, RawUpdateTransaction,
 };
-use mol::{NftTransactionVec, RawIssueTransaction};
 use script_utils::{
+    class::CLASS_TYPE_ARGS_LEN,
     helpers::{
-        DISTRIBUTE_TRANSACTION, EXTRACT_TRANSACTION, INSERT_TRANSACTION, ISSUE_TRANSACTION,
+        load_output_type_args_ids, CLASS_ITEM, CREATE_SET_TRANSACTION, DISTRIBUTE_TRANSACTION,
+        EXTRACT_TRANSACTION, INSERT_TRANSACTION, ISSUE_TRANSACTION, NFT_SET_CELL, SET_ITEM,
         TRANSFER_TRANSACTION, UPDATE_TRANSACTION,
     },
-    issuer::Issuer,
+    issuer::{Issuer, ISSUER_TYPE_ARGS_LEN},
     misc::SMT,
+    nft_set::NFT_SET_TYPE_ARGS_LEN,
+    transition::{create_set, distribute, issue, transfer, update_nfts},
+    type_args::TypeArgs,
 };
 
 use crate::{
+    class::{count_created_class_cells, count_destroyed_class_cells},
     error::Error,
-    type_id::{check_type_id, TYPE_ID_SIZE},
+    nft::{count_created_nft_cells, count_destroyed_nft_cells},
+    nft_set::{count_created_set_cells, count_destroyed_set_cells},
+    smt::SmtUpdates,
+    transaction::{
+        handle_extract, handle_insert, load_nft_transactions, parse_raw_transaction, Authorization,
+    },
+    type_id::check_type_id,
 };
 
 pub fn handle_creation_issuer(issuer: Issuer) -> Result<(), Error> {
-    // check type_id
+    // check type_id, the issuer_id is the prefix of it
     {
         let script = load_script()?;
-        let args: Bytes = Unpack::unpack(&script.args());
-
-        debug!("script args is {:?}", args);
-        if args.len() < TYPE_ID_SIZE {
-            return Err(Error::TypeArgsInvalid);
-        }
-        let mut type_id = [0u8; TYPE_ID_SIZE];
-        type_id.copy_from_slice(&args[..TYPE_ID_SIZE]);
-        check_type_id(type_id)?;
+        debug!("script args is {:?}", script.args());
+        let issuer_id = parse_issuer_id(&script)?;
+        check_type_id(&issuer_id)?;
     }
 
     if issuer.class_count != 0 {
@@ -66,36 +73,157 @@ pub fn handle_update_issuer(input_issuer: Issuer, output_issuer: Issuer) -> Resu
         return Err(Error::IssuerClassCountError);
     }
 
-    let witness_args = load_witness_args(0, Source::GroupInput)?;
-    let lock_type = witness_args.lock();
-    //得到交易
-    let txs = if let Some(lock_type) = lock_type.to_opt() {
-        let lock_type: Vec<u8> = lock_type.unpack();
-        NftTransactionVec::from_compatible_slice(&lock_type).unwrap()
-    } else {
-        return Err(Error::ItemMissing);
-    };
+    let txs = load_nft_transactions()?;
 
-    // let mut old_keys = Vec::new();
-    // let mut new_keys = Vec::new();
+    let script = load_script()?;
+    let code_hash = script.code_hash();
+    let issuer_id = parse_issuer_id(&script)?;
+    let mut updates = SmtUpdates::default();
+    let mut class_count = input_issuer.class_count;
+    let mut set_count = input_issuer.set_count;
+    let mut extracted_count = 0;
+    let mut inserted_count = 0;
+    let mut extracted_class_count = 0;
+    let mut inserted_class_count = 0;
+    let mut extracted_set_count = 0;
+    let mut inserted_set_count = 0;
 
     for tx in txs.into_iter() {
+        let auth = Authorization::new(&issuer_id, &tx)?;
         match <Byte as Into<u8>>::into(tx.typ()) {
             ISSUE_TRANSACTION => {
-                let _issue_tx =
-                    RawIssueTransaction::from_compatible_slice(tx.transaction().as_slice())
-                        .unwrap();
+                let issue_tx: RawIssueTransaction = parse_raw_transaction(&tx)?;
+                issue(&issuer_id, class_count, issue_tx, &mut updates)?;
+                class_count = class_count
+                    .checked_add(1)
+                    .ok_or(Error::IssuerClassCountError)?;
+            }
+            DISTRIBUTE_TRANSACTION => {
+                let distribute_tx: RawDistributeTransaction = parse_raw_transaction(&tx)?;
+                distribute(&issuer_id, &auth, distribute_tx, &mut updates)?;
+            }
+            TRANSFER_TRANSACTION => {
+                let transfer_tx: RawTransferTransaction = parse_raw_transaction(&tx)?;
+                transfer(Some(&issuer_id), &auth, transfer_tx, &mut updates)?;
+            }
+            UPDATE_TRANSACTION => {
+                let update_tx: RawUpdateTransaction = parse_raw_transaction(&tx)?;
+                update_nfts(Some(&issuer_id), &auth, update_tx, &mut updates)?;
+            }
+            EXTRACT_TRANSACTION => {
+                let extract_tx: RawExtractTransaction = parse_raw_transaction(&tx)?;
+                let typ: u8 = extract_tx.typ().into();
+                let count = handle_extract(
+                    code_hash.as_slice(),
+                    Some(&issuer_id),
+                    &auth,
+                    extract_tx,
+                    &mut updates,
+                )?;
+                match typ {
+                    CLASS_ITEM => extracted_class_count += count,
+                    SET_ITEM => extracted_set_count += count,
+                    _ => extracted_count += count,
+                }
+            }
+            INSERT_TRANSACTION => {
+                let insert_tx: RawInsertTransaction = parse_raw_transaction(&tx)?;
+                let typ: u8 = insert_tx.typ().into();
+                let count = handle_insert(
+                    code_hash.as_slice(),
+                    Some(&issuer_id),
+                    None,
+                    insert_tx,
+                    &mut updates,
+                )?;
+                match typ {
+                    CLASS_ITEM => inserted_class_count += count,
+                    SET_ITEM => inserted_set_count += count,
+                    _ => inserted_count += count,
+                }
+            }
+            CREATE_SET_TRANSACTION => {
+                let create_set_tx: RawCreateSetTransaction = parse_raw_transaction(&tx)?;
+                create_set(&issuer_id, set_count, create_set_tx, &mut updates)?;
+                set_count = set_count.checked_add(1).ok_or(Error::IssuerSetCountError)?;
             }
-            DISTRIBUTE_TRANSACTION => {}
-            TRANSFER_TRANSACTION => {}
-            UPDATE_TRANSACTION => {}
-            EXTRACT_TRANSACTION => {}
-            INSERT_TRANSACTION => {}
             _ => return Err(Error::NFTDataInvalid),
         }
     }
 
-    Ok(())
+    // every issued class takes the next class_id
+    if output_issuer.class_count != class_count {
+        return Err(Error::IssuerClassCountError);
+    }
+
+    // every created set takes the next set_id
+    if output_issuer.set_count != set_count {
+        return Err(Error::IssuerSetCountError);
+    }
+
+    // nft cells can only be created by extracting them from the tree
+    if count_created_nft_cells(code_hash.as_slice()) != extracted_count {
+        return Err(Error::NFTCellsCountError);
+    }
+
+    // nft cells can only be consumed by inserting them into the tree
+    if count_destroyed_nft_cells(code_hash.as_slice()) != inserted_count {
+        return Err(Error::NFTCellsCountError);
+    }
+
+    // class cells can only be created by extracting them from the tree
+    if count_created_class_cells(code_hash.as_slice()) != extracted_class_count {
+        return Err(Error::ClassCellsCountError);
+    }
+
+    // created class cells of the issuer can only hold the classes issued before
+    let class_ids =
+        load_output_type_args_ids(code_hash.as_slice(), ISSUER_TYPE_ARGS_LEN, &|args| {
+            args.len() == CLASS_TYPE_ARGS_LEN && args[..ISSUER_TYPE_ARGS_LEN] == issuer_id[..]
+        });
+    if class_ids
+        .into_iter()
+        .any(|class_id| class_id >= class_count)
+    {
+        return Err(Error::ClassIdIncreaseError);
+    }
+
+    // class cells can only be consumed by inserting them into the tree
+    if count_destroyed_class_cells(code_hash.as_slice()) != inserted_class_count {
+        return Err(Error::ClassCellsCountError);
+    }
+
+    // nft set cells can only be created by extracting them from the tree
+    if count_created_set_cells(code_hash.as_slice()) != extracted_set_count {
+        return Err(Error::NFTSetCellsCountError);
+    }
+
+    // created set cells of the issuer can only hold the sets created before
+    let set_ids =
+        load_output_type_args_ids(code_hash.as_slice(), ISSUER_TYPE_ARGS_LEN + 1, &|args| {
+            args.len() == NFT_SET_TYPE_ARGS_LEN
+                && args[..ISSUER_TYPE_ARGS_LEN] == issuer_id[..]
+                && args[ISSUER_TYPE_ARGS_LEN] == NFT_SET_CELL
+        });
+    if set_ids.into_iter().any(|set_id| set_id >= set_count) {
+        return Err(Error::IssuerSetCountError);
+    }
+
+    // nft set cells can only be consumed by inserting them into the tree
+    if count_destroyed_set_cells(code_hash.as_slice()) != inserted_set_count {
+        return Err(Error::NFTSetCellsCountError);
+    }
+
+    updates.verify(&input_issuer.smt_root, &output_issuer.smt_root)
+}
+
+// the issuer type args is the issuer_id
+fn parse_issuer_id(script: &Script) -> Result<[u8; 20], Error> {
+    let args: Bytes = Unpack::unpack(&script.args());
+    match TypeArgs::from_slice(&args)? {
+        TypeArgs::Issuer { issuer_id } => Ok(issuer_id),
+        _ => Err(Error::TypeArgsInvalid),
+    }
 }
 
 pub fn handle_destroying_issuer(input_issuer: Issuer) -> Result<(), Error> {
@@ -16,10 +16,15 @@ mod type_id;
 mod issuer;
 mod class;
 mod nft;
-mod validator;
+mod nft_set;
+mod smt;
+mod transaction;
+mod single_owner;
+mod multi_owner;
 
 pub use script_utils::error;
 pub use script_utils::misc;
+pub use script_utils::validator;
 
 use ckb_std::{
     default_alloc,
@@ -0,0 +1,112 @@
+use ckb_std::{
+    ckb_types::{
+        bytes::Bytes,
+        packed::Byte,
+        prelude::{Entity, Unpack},
+    },
+    high_level::load_script,
+};
+use mol::{
+    RawExtractTransaction, RawInsertTransaction, RawTransferTransaction, RawUpdateTransaction,
+};
+use script_utils::{
+    helpers::{
+        EXTRACT_TRANSACTION, INSERT_TRANSACTION, NFT_ITEM, TRANSFER_TRANSACTION, UPDATE_TRANSACTION,
+    },
+    misc::SMT,
+    multi_owner::MultiOwner,
+    transition::{transfer, update_nfts},
+};
+
+use crate::{
+    error::Error,
+    nft::{count_created_nft_cells, count_destroyed_nft_cells},
+    smt::SmtUpdates,
+    transaction::{
+        handle_extract, handle_insert, load_nft_transactions, parse_raw_transaction, Authorization,
+    },
+    type_id::check_owner_type_id,
+};
+
+pub fn handle_creation_multi_owner(multi_owner: MultiOwner) -> Result<(), Error> {
+    // check type_id
+    let args: Bytes = load_script()?.args().unpack();
+    check_owner_type_id(&args)?;
+
+    // check SMT is empty
+    let smt = SMT::default();
+    if !multi_owner.smt_root.eq(smt.root().as_slice()) {
+        return Err(Error::MultiOwnerDataInvalid);
+    }
+    Ok(())
+}
+
+// the tree only holds nft leaves, every transaction is authorized by the owners of the leaves
+pub fn handle_update_multi_owner(
+    input_multi_owner: MultiOwner,
+    output_multi_owner: MultiOwner,
+) -> Result<(), Error> {
+    if input_multi_owner.version != output_multi_owner.version {
+        return Err(Error::MultiOwnerDataInvalid);
+    }
+
+    let txs = load_nft_transactions()?;
+
+    let script = load_script()?;
+    let code_hash = script.code_hash();
+    let tree_id: Bytes = script.args().unpack();
+    let mut updates = SmtUpdates::default();
+    let mut extracted_count = 0;
+    let mut inserted_count = 0;
+
+    for tx in txs.into_iter() {
+        let auth = Authorization::new(&tree_id, &tx)?;
+        match <Byte as Into<u8>>::into(tx.typ()) {
+            TRANSFER_TRANSACTION => {
+                let transfer_tx: RawTransferTransaction = parse_raw_transaction(&tx)?;
+                // there are no class leaves in the tree
+                if <Byte as Into<u8>>::into(transfer_tx.typ()) != NFT_ITEM {
+                    return Err(Error::TransactionNotSupported);
+                }
+                transfer(None, &auth, transfer_tx, &mut updates)?;
+            }
+            UPDATE_TRANSACTION => {
+                let update_tx: RawUpdateTransaction = parse_raw_transaction(&tx)?;
+                update_nfts(None, &auth, update_tx, &mut updates)?;
+            }
+            EXTRACT_TRANSACTION => {
+                let extract_tx: RawExtractTransaction = parse_raw_transaction(&tx)?;
+                extracted_count +=
+                    handle_extract(code_hash.as_slice(), None, &auth, extract_tx, &mut updates)?;
+            }
+            // deposits keep the lock of the consumed nft cells as the owners of the leaves
+            INSERT_TRANSACTION => {
+                let insert_tx: RawInsertTransaction = parse_raw_transaction(&tx)?;
+                inserted_count +=
+                    handle_insert(code_hash.as_slice(), None, None, insert_tx, &mut updates)?;
+            }
+            _ => return Err(Error::TransactionNotSupported),
+        }
+    }
+
+    // nft cells can only be created by extracting them from the tree
+    if count_created_nft_cells(code_hash.as_slice()) != extracted_count {
+        return Err(Error::NFTCellsCountError);
+    }
+
+    // nft cells can only be consumed by inserting them into the tree
+    if count_destroyed_nft_cells(code_hash.as_slice()) != inserted_count {
+        return Err(Error::NFTCellsCountError);
+    }
+
+    updates.verify(&input_multi_owner.smt_root, &output_multi_owner.smt_root)
+}
+
+pub fn handle_destroying_multi_owner(input_multi_owner: MultiOwner) -> Result<(), Error> {
+    // check SMT is empty
+    let smt = SMT::default();
+    if !input_multi_owner.smt_root.eq(smt.root().as_slice()) {
+        return Err(Error::MultiOwnerCellCannotDestroyed);
+    }
+    Ok(())
+}
@@ -1,29 +1,164 @@
 use alloc::vec::Vec;
-use ckb_std::{ckb_constants::Source, high_level::load_cell_data};
+use ckb_std::{
+    ckb_constants::Source,
+    ckb_types::{bytes::Bytes, packed::Byte, prelude::*},
+    high_level::{load_cell_data, load_cell_lock, load_cell_type, load_script, QueryIter},
+};
+use mol::RawInsertTransaction;
 use script_utils::{
-    nft::Nft,
+    helpers::{
+        count_unpaired_cells, load_cell_index_by_type, INSERT_TRANSACTION, ISSUER_CELL,
+        MULTI_OWNER_CELL, NFT_CELL, NFT_ITEM, SINGLE_OWNER_CELL,
+    },
+    nft::{Nft, NFT_DATA_MIN_LEN_IN_CELL},
+    type_args::TypeArgs,
 };
 
-use crate::validator::{validate_immutable_nft_fields, validate_nft_claim, validate_nft_ext_info, validate_nft_lock, validate_nft_transfer};
 use crate::error::Error;
+use crate::transaction::{load_nft_transactions_at, parse_raw_transaction};
+use crate::validator::{
+    validate_immutable_nft_fields, validate_nft_claim, validate_nft_destroyable,
+    validate_nft_ext_info, validate_nft_lock, validate_nft_transfer,
+};
 
+// nft cell data: NFT_CELL + nft data in cell
 fn load_nft_data(source: Source) -> Result<Vec<u8>, Error> {
-    load_cell_data(0, source).map_err(|_| Error::NFTDataInvalid)
+    let data = load_cell_data(0, source).map_err(|_| Error::NFTDataInvalid)?;
+    if data.len() < 1 + NFT_DATA_MIN_LEN_IN_CELL || data[0] != NFT_CELL {
+        return Err(Error::NFTDataInvalid);
+    }
+    Ok(data)
+}
+
+// the ids of the nft cell are taken from its type args, and only one cell can hold them
+fn load_nft_type_args() -> Result<TypeArgs, Error> {
+    if load_cell_data(1, Source::GroupInput).is_ok()
+        || load_cell_data(1, Source::GroupOutput).is_ok()
+    {
+        return Err(Error::NFTCellsCountError);
+    }
+    let args: Bytes = load_script()?.args().unpack();
+    match TypeArgs::from_slice(&args)? {
+        type_args @ TypeArgs::Nft { .. } => Ok(type_args),
+        _ => Err(Error::TypeArgsInvalid),
+    }
+}
+
+// a standalone nft cell without output is destroyed, otherwise it's updated
+pub fn handle_nft() -> Result<(), Error> {
+    if load_cell_data(0, Source::GroupOutput).is_err() {
+        return handle_destroying_nft();
+    }
+    handle_update_nft()
+}
+
+pub fn handle_destroying_nft() -> Result<(), Error> {
+    let type_args = load_nft_type_args()?;
+    let nft_data = load_nft_data(Source::GroupInput)?;
+    let mut nft = Nft::from_data_cell(&nft_data[1..])?;
+    type_args.fill_nft(&mut nft)?;
+    validate_nft_destroyable(&nft)?;
+
+    // the burn is recorded in the class, so the class cell of the nft must be updated
+    // along with it, see class::handle_update_class
+    let code_hash = load_script()?.code_hash();
+    let class_args = TypeArgs::Class {
+        issuer_id: nft.issuer_id,
+        class_id: nft.class_id,
+    }
+    .to_vec();
+    if load_cell_index_by_type(Source::Input, code_hash.as_slice(), &class_args).is_none()
+        || load_cell_index_by_type(Source::Output, code_hash.as_slice(), &class_args).is_none()
+    {
+        return Err(Error::ClassCellsCountError);
+    }
+    Ok(())
 }
 
 pub fn handle_update_nft() -> Result<(), Error> {
+    let type_args = load_nft_type_args()?;
     let nft_data = (
         load_nft_data(Source::GroupInput)?,
         load_nft_data(Source::GroupOutput)?,
     );
-    let nfts = (
-        Nft::from_data(&nft_data.0[..], true)?,
-        Nft::from_data(&nft_data.1[..], true)?,
+    let mut nfts = (
+        Nft::from_data_cell(&nft_data.0[1..])?,
+        Nft::from_data_cell(&nft_data.1[1..])?,
     );
+    type_args.fill_nft(&mut nfts.0)?;
+    type_args.fill_nft(&mut nfts.1)?;
+    let transferred = load_cell_lock(0, Source::GroupInput)?.as_slice()
+        != load_cell_lock(0, Source::GroupOutput)?.as_slice();
     validate_immutable_nft_fields(&nfts)?;
     validate_nft_claim(&nfts)?;
     validate_nft_lock(&nfts)?;
-    validate_nft_transfer(&nfts.0, &nfts.1)?;
-    validate_nft_ext_info(&nfts.0, &nft_data)?;
+    validate_nft_transfer(&nfts, transferred)?;
+    validate_nft_ext_info(&nfts)?;
     Ok(())
 }
+
+// the nft cell is in a transaction which also updates a SMT cell, a created one is
+// extracted from the SMT and a consumed one inserted into it is checked by the SMT cell,
+// a consumed one which is not inserted is destroyed, otherwise it's a normal update of
+// the nft cell
+pub fn handle_nft_with_smt_update() -> Result<(), Error> {
+    let has_input = load_cell_data(0, Source::GroupInput).is_ok();
+    let has_output = load_cell_data(0, Source::GroupOutput).is_ok();
+    if has_input && has_output {
+        return handle_update_nft();
+    }
+    if has_input && !is_inserted_nft_cell()? {
+        return handle_destroying_nft();
+    }
+    Ok(())
+}
+
+// whether an insert transaction of the SMT cell in the inputs takes the consumed nft cell,
+// the inserted nft cells are counted by the SMT cell
+fn is_inserted_nft_cell() -> Result<bool, Error> {
+    let mut nft = Nft::default();
+    load_nft_type_args()?.fill_nft(&mut nft)?;
+    let nft_key = nft.to_key();
+
+    let script = load_script()?;
+    let code_hash = script.code_hash();
+    let smt_index = QueryIter::new(load_cell_type, Source::Input)
+        .enumerate()
+        .find(|(index, type_opt)| {
+            type_opt.as_ref().map_or(false, |type_| {
+                type_.code_hash().as_slice() == code_hash.as_slice()
+            }) && load_cell_data(*index, Source::Input).map_or(false, |data| match data.first() {
+                Some(&ISSUER_CELL) | Some(&SINGLE_OWNER_CELL) | Some(&MULTI_OWNER_CELL) => true,
+                _ => false,
+            })
+        })
+        .map(|(index, _)| index);
+    let smt_index = match smt_index {
+        Some(index) => index,
+        None => return Ok(false),
+    };
+
+    for tx in load_nft_transactions_at(smt_index, Source::Input)?.into_iter() {
+        if <Byte as Into<u8>>::into(tx.typ()) != INSERT_TRANSACTION {
+            continue;
+        }
+        let insert_tx: RawInsertTransaction = parse_raw_transaction(&tx)?;
+        if <Byte as Into<u8>>::into(insert_tx.typ()) == NFT_ITEM
+            && insert_tx
+                .nft_ids()
+                .into_iter()
+                .any(|nft_id| nft_id.as_slice() == nft_key.as_slice())
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+pub fn count_created_nft_cells(code_hash: &[u8]) -> usize {
+    count_unpaired_cells(Source::Output, Source::Input, code_hash, NFT_CELL)
+}
+
+pub fn count_destroyed_nft_cells(code_hash: &[u8]) -> usize {
+    count_unpaired_cells(Source::Input, Source::Output, code_hash, NFT_CELL)
+}
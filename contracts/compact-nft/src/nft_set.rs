@@ -0,0 +1,76 @@
+use ckb_std::{
+    ckb_constants::Source,
+    ckb_types::{bytes::Bytes, prelude::*},
+    high_level::{load_cell_data, load_script},
+};
+use script_utils::{
+    error::Error,
+    helpers::{count_unpaired_cells, NFT_SET_CELL},
+    nft_set::NftSet,
+    type_args::TypeArgs,
+};
+
+// the ids of the set cell are taken from its type args, and only one cell can hold them
+fn check_nft_set_type_args() -> Result<(), Error> {
+    if load_cell_data(1, Source::GroupInput).is_ok()
+        || load_cell_data(1, Source::GroupOutput).is_ok()
+    {
+        return Err(Error::NFTSetCellsCountError);
+    }
+    let args: Bytes = load_script()?.args().unpack();
+    match TypeArgs::from_slice(&args)? {
+        TypeArgs::NftSet { .. } => Ok(()),
+        _ => Err(Error::TypeArgsInvalid),
+    }
+}
+
+// a standalone nft set cell can only be handed to another lock, it's created and consumed
+// by extracting it from and inserting it into the issuer tree
+pub fn handle_nft_set() -> Result<(), Error> {
+    let has_input = load_cell_data(0, Source::GroupInput).is_ok();
+    let has_output = load_cell_data(0, Source::GroupOutput).is_ok();
+    match (has_input, has_output) {
+        (true, true) => handle_update_nft_set(),
+        (true, false) => Err(Error::NFTSetCellCannotDestroyed),
+        _ => Err(Error::NFTSetCellsCountError),
+    }
+}
+
+// the members of the set are bound to the set leaf in the issuer tree, so the set data
+// can't be changed
+pub fn handle_update_nft_set() -> Result<(), Error> {
+    check_nft_set_type_args()?;
+    let input_data = load_cell_data(0, Source::GroupInput)?;
+    let output_data = load_cell_data(0, Source::GroupOutput)?;
+    if input_data.first() != Some(&NFT_SET_CELL) {
+        return Err(Error::NFTSetDataInvalid);
+    }
+    NftSet::from_data_cell(&input_data[1..])?;
+    if input_data != output_data {
+        return Err(Error::NFTSetDataInvalid);
+    }
+    Ok(())
+}
+
+// the set cell is in a transaction which also updates a SMT cell, a created or consumed one
+// is extracted from or inserted into the issuer tree, which is only checked and counted by
+// an issuer cell, otherwise it's a normal update of the set cell
+pub fn handle_nft_set_with_smt_update(counted_by_issuer: bool) -> Result<(), Error> {
+    let has_input = load_cell_data(0, Source::GroupInput).is_ok();
+    let has_output = load_cell_data(0, Source::GroupOutput).is_ok();
+    if has_input && has_output {
+        return handle_update_nft_set();
+    }
+    if !counted_by_issuer {
+        return handle_nft_set();
+    }
+    check_nft_set_type_args()
+}
+
+pub fn count_created_set_cells(code_hash: &[u8]) -> usize {
+    count_unpaired_cells(Source::Output, Source::Input, code_hash, NFT_SET_CELL)
+}
+
+pub fn count_destroyed_set_cells(code_hash: &[u8]) -> usize {
+    count_unpaired_cells(Source::Input, Source::Output, code_hash, NFT_SET_CELL)
+}
@@ -0,0 +1,112 @@
+use ckb_std::{
+    ckb_constants::Source,
+    ckb_types::{
+        bytes::Bytes,
+        packed::Byte,
+        prelude::{Entity, Unpack},
+    },
+    high_level::{load_cell_lock_hash, load_script},
+};
+use mol::{RawExtractTransaction, RawInsertTransaction, RawUpdateTransaction};
+use script_utils::{
+    helpers::{EXTRACT_TRANSACTION, INSERT_TRANSACTION, UPDATE_TRANSACTION},
+    misc::SMT,
+    single_owner::SingleOwner,
+    transition::update_nfts,
+};
+
+use crate::{
+    error::Error,
+    nft::{count_created_nft_cells, count_destroyed_nft_cells},
+    smt::SmtUpdates,
+    transaction::{
+        handle_extract, handle_insert, load_nft_transactions, parse_raw_transaction, Authorization,
+    },
+    type_id::check_owner_type_id,
+};
+
+pub fn handle_creation_single_owner(single_owner: SingleOwner) -> Result<(), Error> {
+    // check type_id
+    let args: Bytes = load_script()?.args().unpack();
+    check_owner_type_id(&args)?;
+
+    // check SMT is empty
+    let smt = SMT::default();
+    if !single_owner.smt_root.eq(smt.root().as_slice()) {
+        return Err(Error::SingleOwnerDataInvalid);
+    }
+    Ok(())
+}
+
+// the cell is unlocked by its lock script, so the owner has authorized the update
+pub fn handle_update_single_owner(
+    input_single_owner: SingleOwner,
+    output_single_owner: SingleOwner,
+) -> Result<(), Error> {
+    if input_single_owner.version != output_single_owner.version {
+        return Err(Error::SingleOwnerDataInvalid);
+    }
+
+    // the leaves belong to the lock, so the lock can't be changed
+    let owner = load_cell_lock_hash(0, Source::GroupInput)?;
+    if load_cell_lock_hash(0, Source::GroupOutput)? != owner {
+        return Err(Error::SingleOwnerLockNotSame);
+    }
+
+    let txs = load_nft_transactions()?;
+
+    let script = load_script()?;
+    let code_hash = script.code_hash();
+    let tree_id: Bytes = script.args().unpack();
+    let mut updates = SmtUpdates::default();
+    let mut extracted_count = 0;
+    let mut inserted_count = 0;
+
+    for tx in txs.into_iter() {
+        let auth = Authorization::new(&tree_id, &tx)?;
+        match <Byte as Into<u8>>::into(tx.typ()) {
+            UPDATE_TRANSACTION => {
+                let update_tx: RawUpdateTransaction = parse_raw_transaction(&tx)?;
+                update_nfts(None, &auth, update_tx, &mut updates)?;
+            }
+            EXTRACT_TRANSACTION => {
+                let extract_tx: RawExtractTransaction = parse_raw_transaction(&tx)?;
+                extracted_count +=
+                    handle_extract(code_hash.as_slice(), None, &auth, extract_tx, &mut updates)?;
+            }
+            INSERT_TRANSACTION => {
+                let insert_tx: RawInsertTransaction = parse_raw_transaction(&tx)?;
+                inserted_count += handle_insert(
+                    code_hash.as_slice(),
+                    None,
+                    Some(&owner),
+                    insert_tx,
+                    &mut updates,
+                )?;
+            }
+            // the nfts can't be transferred to other owners in the tree, they must be extracted
+            _ => return Err(Error::TransactionNotSupported),
+        }
+    }
+
+    // nft cells can only be created by extracting them from the tree
+    if count_created_nft_cells(code_hash.as_slice()) != extracted_count {
+        return Err(Error::NFTCellsCountError);
+    }
+
+    // nft cells can only be consumed by inserting them into the tree
+    if count_destroyed_nft_cells(code_hash.as_slice()) != inserted_count {
+        return Err(Error::NFTCellsCountError);
+    }
+
+    updates.verify(&input_single_owner.smt_root, &output_single_owner.smt_root)
+}
+
+pub fn handle_destroying_single_owner(input_single_owner: SingleOwner) -> Result<(), Error> {
+    // check SMT is empty
+    let smt = SMT::default();
+    if !input_single_owner.smt_root.eq(smt.root().as_slice()) {
+        return Err(Error::SingleOwnerCellCannotDestroyed);
+    }
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+use ckb_std::{
+    ckb_constants::Source,
+    ckb_types::prelude::{Entity, Unpack},
+    high_level::load_witness_args,
+};
+use mol::SmtUpdateAction;
+use script_utils::{
+    error::Error,
+    misc::{CKBBlake2bHasher, NftValue},
+    transition::LeafUpdates,
+};
+use sparse_merkle_tree::{traits::Value, CompiledMerkleProof, H256};
+
+/// The leaf transitions of one batch of transactions.
+/// A key may be touched by several transactions of the same batch, so only the
+/// first old value and the last new value of every key are kept.
+#[derive(Default)]
+pub struct SmtUpdates(BTreeMap<H256, (H256, H256)>);
+
+impl LeafUpdates for SmtUpdates {
+    type Error = Error;
+
+    fn update(&mut self, key: H256, old_leaf: &[u8], new_leaf: &[u8]) -> Result<(), Error> {
+        let old_value = NftValue(old_leaf.to_vec()).to_h256();
+        let new_value = NftValue(new_leaf.to_vec()).to_h256();
+        match self.0.get_mut(&key) {
+            Some((_, current_value)) => {
+                // the old value must be the result of the previous transaction
+                if *current_value != old_value {
+                    return Err(Error::SMTLeafValueInvalid);
+                }
+                *current_value = new_value;
+            }
+            None => {
+                self.0.insert(key, (old_value, new_value));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SmtUpdates {
+    /// Check the proof of the input_type witness against the roots of the input and output cells
+    pub fn verify(&self, old_root: &[u8; 32], new_root: &[u8; 32]) -> Result<(), Error> {
+        // nothing changed, so the root must stay the same
+        if self.0.is_empty() {
+            if old_root != new_root {
+                return Err(Error::SMTProofVerifyFailed);
+            }
+            return Ok(());
+        }
+
+        let proof = load_smt_proof()?;
+
+        let old_leaves: Vec<(H256, H256)> = self
+            .0
+            .iter()
+            .map(|(key, (old_value, _))| (*key, *old_value))
+            .collect();
+        let root = proof
+            .compute_root::<CKBBlake2bHasher>(old_leaves)
+            .map_err(|_| Error::SMTProofVerifyFailed)?;
+        if root.as_slice() != &old_root[..] {
+            return Err(Error::SMTProofVerifyFailed);
+        }
+
+        let new_leaves: Vec<(H256, H256)> = self
+            .0
+            .iter()
+            .map(|(key, (_, new_value))| (*key, *new_value))
+            .collect();
+        let root = proof
+            .compute_root::<CKBBlake2bHasher>(new_leaves)
+            .map_err(|_| Error::SMTProofVerifyFailed)?;
+        if root.as_slice() != &new_root[..] {
+            return Err(Error::SMTProofVerifyFailed);
+        }
+
+        Ok(())
+    }
+}
+
+// SmtUpdateAction is placed in the input_type of the witness
+fn load_smt_proof() -> Result<CompiledMerkleProof, Error> {
+    let witness_args = load_witness_args(0, Source::GroupInput)?;
+    let input_type = match witness_args.input_type().to_opt() {
+        Some(input_type) => input_type,
+        None => return Err(Error::ItemMissing),
+    };
+    let input_type: Vec<u8> = input_type.unpack();
+    let action = SmtUpdateAction::from_compatible_slice(&input_type)
+        .map_err(|_| Error::SMTProofVerifyFailed)?;
+    Ok(CompiledMerkleProof(action.proof().raw_data().to_vec()))
+}
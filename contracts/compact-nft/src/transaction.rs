@@ -0,0 +1,294 @@
+use alloc::vec::Vec;
+use ckb_std::{
+    ckb_constants::Source,
+    ckb_types::{
+        bytes::Bytes,
+        packed::Byte32,
+        prelude::{Entity, Unpack},
+    },
+    high_level::{
+        load_cell_data, load_cell_lock_hash, load_cell_type, load_witness_args, QueryIter,
+    },
+};
+use mol::{NftTransaction, NftTransactionVec, RawExtractTransaction, RawInsertTransaction};
+use script_utils::{
+    class::Class,
+    error::Error,
+    helpers::{
+        count_cells_by_type, load_cell_index_by_type, CLASS_CELL, CLASS_ITEM, NFT_CELL, NFT_ITEM,
+        NFT_SET_CELL,
+    },
+    nft::{Nft, NFT_DATA_MIN_LEN_IN_CELL},
+    nft_set::NftSet,
+    transition::{extract, insert, recover_signer, Authorize, Item},
+    type_args::TypeArgs,
+};
+
+use crate::smt::SmtUpdates;
+
+pub use script_utils::transition::parse_raw_transaction;
+
+// NftTransactionVec is placed in the lock of the witness
+pub fn load_nft_transactions() -> Result<NftTransactionVec, Error> {
+    load_nft_transactions_at(0, Source::GroupInput)
+}
+
+// the NftTransactionVec of the SMT cell at the index of the source
+pub fn load_nft_transactions_at(index: usize, source: Source) -> Result<NftTransactionVec, Error> {
+    let witness_args = load_witness_args(index, source)?;
+    let lock_type = witness_args.lock();
+    //得到交易
+    if let Some(lock_type) = lock_type.to_opt() {
+        let lock_type: Vec<u8> = lock_type.unpack();
+        NftTransactionVec::from_compatible_slice(&lock_type)
+            .map_err(|_| Error::NftTransactionsInvalid)
+    } else {
+        Err(Error::ItemMissing)
+    }
+}
+
+/// The owners of the leaves touched by a NftTransaction authorize it either by
+/// signing it or by putting a cell with their lock into the inputs
+pub struct Authorization {
+    // lock hash of the signer
+    signer: Option<[u8; 32]>,
+}
+
+impl Authorization {
+    pub fn new(tree_id: &[u8], tx: &NftTransaction) -> Result<Self, Error> {
+        Ok(Authorization {
+            signer: recover_signer(tree_id, tx)?,
+        })
+    }
+}
+
+impl Authorize for Authorization {
+    fn check(&self, owner: &[u8; 32]) -> Result<(), Error> {
+        if self.signer.as_ref() == Some(owner) {
+            return Ok(());
+        }
+        if QueryIter::new(load_cell_lock_hash, Source::Input).any(|lock_hash| &lock_hash == owner) {
+            return Ok(());
+        }
+        Err(Error::OwnerNotAuthorized)
+    }
+}
+
+// remove leaves from the tree, every one of them must appear as a new nft, class or set
+// cell in the outputs, returns the number of extracted leaves.
+// The member leaves of an extracted set stay in the tree, so the membership can still be proved.
+pub fn handle_extract(
+    code_hash: &[u8],
+    issuer_id: Option<&[u8; 20]>,
+    auth: &Authorization,
+    extract_tx: RawExtractTransaction,
+    updates: &mut SmtUpdates,
+) -> Result<usize, Error> {
+    extract(issuer_id, auth, extract_tx, updates, |item| match item {
+        Item::Nft(nft) => check_extracted_nft_cell(code_hash, nft),
+        Item::Class(class) => check_extracted_class_cell(code_hash, class),
+        Item::Set(set) => check_extracted_set_cell(code_hash, set),
+    })
+}
+
+// put consumed nft, class or set cells back into the tree, every one of them must be
+// destroyed in the transaction, returns the number of inserted leaves.
+// The nft leaves belong to the owner if it's given, otherwise to the lock of the consumed cells.
+pub fn handle_insert(
+    code_hash: &[u8],
+    issuer_id: Option<&[u8; 20]>,
+    owner: Option<&[u8; 32]>,
+    insert_tx: RawInsertTransaction,
+    updates: &mut SmtUpdates,
+) -> Result<usize, Error> {
+    // the lock of the consumed cell has approved the insertion
+    insert(issuer_id, owner, insert_tx, updates, |typ, id| match typ {
+        NFT_ITEM => load_inserted_nft_cell(code_hash, id),
+        CLASS_ITEM => load_inserted_class_cell(code_hash, id).map(Item::Class),
+        _ => load_inserted_set_cell(code_hash, id).map(Item::Set),
+    })
+}
+
+// the nft cell must be unique, owned by the leaf owner and carry the same fields as the leaf
+fn check_extracted_nft_cell(code_hash: &[u8], nft: &Nft) -> Result<(), Error> {
+    let args = TypeArgs::from_nft(nft).to_vec();
+    if count_cells_by_type(Source::Output, code_hash, &args) != 1
+        || count_cells_by_type(Source::Input, code_hash, &args) != 0
+    {
+        return Err(Error::NFTCellsCountError);
+    }
+    let index = load_cell_index_by_type(Source::Output, code_hash, &args)
+        .ok_or(Error::NFTCellsCountError)?;
+
+    if load_cell_lock_hash(index, Source::Output)? != nft.owner {
+        return Err(Error::NFTOwnerNotMatch);
+    }
+
+    let data = load_cell_data(index, Source::Output)?;
+    if data.len() < 1 + NFT_DATA_MIN_LEN_IN_CELL || data[0] != NFT_CELL {
+        return Err(Error::NFTDataInvalid);
+    }
+    let cell_nft = Nft::from_data_cell(&data[1..])?;
+    if cell_nft.version != nft.version
+        || cell_nft.characteristic != nft.characteristic
+        || cell_nft.configure != nft.configure
+        || cell_nft.state != nft.state
+        || cell_nft.nonce != nft.nonce
+        || cell_nft.ext_info != nft.ext_info
+    {
+        return Err(Error::NFTDataInvalid);
+    }
+    Ok(())
+}
+
+// find the consumed nft cell whose leaf key is the nft_id, the owner of the leaf is
+// the lock hash of the cell
+fn load_inserted_nft_cell(code_hash: &[u8], nft_id: &Byte32) -> Result<Item, Error> {
+    for (index, type_opt) in QueryIter::new(load_cell_type, Source::Input).enumerate() {
+        let type_ = match type_opt {
+            Some(type_) if type_.code_hash().as_slice() == code_hash => type_,
+            _ => continue,
+        };
+        let data = load_cell_data(index, Source::Input)?;
+        if data.first() != Some(&NFT_CELL) {
+            continue;
+        }
+
+        let args: Bytes = type_.args().unpack();
+        let mut nft = Nft::from_data_cell(&data[1..])?;
+        TypeArgs::from_slice(&args)?.fill_nft(&mut nft)?;
+        if nft.to_key().as_slice() != nft_id.as_slice() {
+            continue;
+        }
+
+        if count_cells_by_type(Source::Output, code_hash, &args) != 0 {
+            return Err(Error::NFTCellsCountError);
+        }
+        nft.owner = load_cell_lock_hash(index, Source::Input)?;
+        return Ok(Item::Nft(nft));
+    }
+    Err(Error::NFTCellsCountError)
+}
+
+// the class cell must be unique, owned by the leaf owner and carry the same fields as the leaf
+fn check_extracted_class_cell(code_hash: &[u8], class: &Class) -> Result<(), Error> {
+    let args = TypeArgs::from_class(class).to_vec();
+    if count_cells_by_type(Source::Output, code_hash, &args) != 1
+        || count_cells_by_type(Source::Input, code_hash, &args) != 0
+    {
+        return Err(Error::ClassCellsCountError);
+    }
+    let index = load_cell_index_by_type(Source::Output, code_hash, &args)
+        .ok_or(Error::ClassCellsCountError)?;
+
+    if load_cell_lock_hash(index, Source::Output)? != class.owner {
+        return Err(Error::NFTOwnerNotMatch);
+    }
+
+    let data = load_cell_data(index, Source::Output)?;
+    if data.first() != Some(&CLASS_CELL) {
+        return Err(Error::ClassDataInvalid);
+    }
+    let cell_class = Class::from_data(&data[1..], true)?;
+    if cell_class.version != class.version
+        || cell_class.total != class.total
+        || cell_class.issued != class.issued
+        || cell_class.burned != class.burned
+        || cell_class.configure != class.configure
+        || cell_class.name != class.name
+        || cell_class.description != class.description
+        || cell_class.renderer != class.renderer
+        || cell_class.ext_info != class.ext_info
+    {
+        return Err(Error::ClassDataInvalid);
+    }
+    Ok(())
+}
+
+// find the consumed class cell whose leaf key is the class_id, the owner of the leaf is
+// the lock hash of the cell
+fn load_inserted_class_cell(code_hash: &[u8], class_id: &Byte32) -> Result<Class, Error> {
+    for (index, type_opt) in QueryIter::new(load_cell_type, Source::Input).enumerate() {
+        let type_ = match type_opt {
+            Some(type_) if type_.code_hash().as_slice() == code_hash => type_,
+            _ => continue,
+        };
+        let data = load_cell_data(index, Source::Input)?;
+        if data.first() != Some(&CLASS_CELL) {
+            continue;
+        }
+
+        let args: Bytes = type_.args().unpack();
+        let mut class = Class::from_data(&data[1..], true)?;
+        TypeArgs::from_slice(&args)?.fill_class(&mut class)?;
+        if class.to_key().as_slice() != class_id.as_slice() {
+            continue;
+        }
+
+        if count_cells_by_type(Source::Output, code_hash, &args) != 0 {
+            return Err(Error::ClassCellsCountError);
+        }
+        class.owner = load_cell_lock_hash(index, Source::Input)?;
+        return Ok(class);
+    }
+    Err(Error::ClassCellsCountError)
+}
+
+// the set cell must be unique, owned by the leaf owner and carry the same fields as the leaf
+fn check_extracted_set_cell(code_hash: &[u8], set: &NftSet) -> Result<(), Error> {
+    let args = TypeArgs::from_set(set).to_vec();
+    if count_cells_by_type(Source::Output, code_hash, &args) != 1
+        || count_cells_by_type(Source::Input, code_hash, &args) != 0
+    {
+        return Err(Error::NFTSetCellsCountError);
+    }
+    let index = load_cell_index_by_type(Source::Output, code_hash, &args)
+        .ok_or(Error::NFTSetCellsCountError)?;
+
+    if load_cell_lock_hash(index, Source::Output)? != set.owner {
+        return Err(Error::NFTOwnerNotMatch);
+    }
+
+    let data = load_cell_data(index, Source::Output)?;
+    if data.first() != Some(&NFT_SET_CELL) {
+        return Err(Error::NFTSetDataInvalid);
+    }
+    let cell_set = NftSet::from_data_cell(&data[1..])?;
+    if cell_set.version != set.version
+        || cell_set.member_count != set.member_count
+        || cell_set.name != set.name
+        || cell_set.description != set.description
+    {
+        return Err(Error::NFTSetDataInvalid);
+    }
+    Ok(())
+}
+
+// find the consumed nft set cell whose leaf key is the set_id, the owner of the leaf is
+// the lock hash of the cell
+fn load_inserted_set_cell(code_hash: &[u8], set_id: &Byte32) -> Result<NftSet, Error> {
+    for (index, type_opt) in QueryIter::new(load_cell_type, Source::Input).enumerate() {
+        let type_ = match type_opt {
+            Some(type_) if type_.code_hash().as_slice() == code_hash => type_,
+            _ => continue,
+        };
+        let data = load_cell_data(index, Source::Input)?;
+        if data.first() != Some(&NFT_SET_CELL) {
+            continue;
+        }
+
+        let args: Bytes = type_.args().unpack();
+        let mut set = NftSet::from_data_cell(&data[1..])?;
+        TypeArgs::from_slice(&args)?.fill_set(&mut set)?;
+        if set.to_key().as_slice() != set_id.as_slice() {
+            continue;
+        }
+
+        if count_cells_by_type(Source::Output, code_hash, &args) != 0 {
+            return Err(Error::NFTSetCellsCountError);
+        }
+        set.owner = load_cell_lock_hash(index, Source::Input)?;
+        return Ok(set);
+    }
+    Err(Error::NFTSetCellsCountError)
+}
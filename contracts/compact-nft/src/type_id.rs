@@ -4,15 +4,20 @@ use ckb_std::{
     high_level::{load_cell_type_hash, load_script_hash, QueryIter},
     syscalls::{load_cell, load_input, SysError},
 };
-use script_utils::misc::new_blake2b;
+use script_utils::{misc::new_blake2b, type_args::TypeArgs};
 
-use crate::{error::Error};
+use crate::error::Error;
 
 pub const TYPE_ID_SIZE: usize = 32;
 
 // type_id: usually the first 32-bytes of the current script.args
 // notice the type_id must be included in the script.args
-pub fn check_type_id(type_id: [u8; 32]) -> Result<(), Error> {
+// a shorter type_id is checked against the prefix of the expected one, e.g. the
+// issuer_id is the first 20 bytes of the type_id
+pub fn check_type_id(type_id: &[u8]) -> Result<(), Error> {
+    if type_id.is_empty() || type_id.len() > TYPE_ID_SIZE {
+        return Err(Error::TypeArgsInvalid);
+    }
     // check there is only one type id cell in each input/output group
     let has_second_input_type_id_cell = has_type_id_cell(1, Source::GroupInput);
     let has_second_output_type_id_cell = has_type_id_cell(1, Source::GroupOutput);
@@ -42,7 +47,7 @@ pub fn check_type_id(type_id: [u8; 32]) -> Result<(), Error> {
     hasher.update(&output_index.to_le_bytes());
     let mut expected_type_id = [0u8; 32];
     hasher.finalize(&mut expected_type_id);
-    if type_id != expected_type_id {
+    if type_id != &expected_type_id[..type_id.len()] {
         debug!(
             "type_id: {:?}, expected_type_id: {:?}",
             type_id, expected_type_id
@@ -52,6 +57,15 @@ pub fn check_type_id(type_id: [u8; 32]) -> Result<(), Error> {
     Ok(())
 }
 
+// the args of a single or multi owner cell are its full type_id, they must not be taken
+// for the args of an issuer, class, nft or nft set cell sharing the code_hash
+pub fn check_owner_type_id(args: &[u8]) -> Result<(), Error> {
+    if TypeArgs::from_slice(args).is_ok() {
+        return Err(Error::TypeArgsInvalid);
+    }
+    check_type_id(args)
+}
+
 fn has_type_id_cell(index: usize, source: Source) -> bool {
     let mut buf = [0u8; 0];
     match load_cell(&mut buf, 0, index, source) {
@@ -0,0 +1,351 @@
+use crate::error::Error;
+use aggregator::batch::LiveCell;
+use ckb_jsonrpc_types::{
+    BlockNumber, BlockView, CellWithStatus, JsonBytes, Status, Transaction, TransactionWithStatus,
+    Uint32,
+};
+use ckb_types::{
+    bytes::Bytes,
+    core::TransactionView,
+    packed::{self, Byte32, CellOutput, OutPoint, Script},
+    prelude::{Entity, Pack, Unpack},
+    H256,
+};
+use jsonrpc_core::{Output, Value};
+use reqwest::blocking::Client;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::json;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+// a request to the node fails if it's not answered in the time
+const NODE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxStatus {
+    // in the tx pool of the node, or not known by the node while the issuer cell is live
+    Pending,
+    Committed,
+    // the issuer cell is spent by another transaction, or the node refuses the transaction
+    Rejected,
+}
+
+/// The L1 chain the batches are sent to and the nft cells are indexed from. A clone is
+/// another client of the same chain, so the poller and the requests reach it apart.
+pub trait Chain: Send + Clone {
+    fn get_live_cell(&mut self, out_point: &OutPoint) -> Result<Option<LiveCell>, Error>;
+    /// The live cells whose type script has the code_hash and hash_type of the script and
+    /// args starting with its args
+    fn get_live_cells_by_type(&mut self, type_script: &Script) -> Result<Vec<LiveCell>, Error>;
+    fn get_tip_block_number(&mut self) -> Result<u64, Error>;
+    /// The transactions of the block at the number, None if the chain is not there yet
+    fn get_block_transactions(
+        &mut self,
+        number: u64,
+    ) -> Result<Option<Vec<TransactionView>>, Error>;
+    fn send_transaction(&mut self, tx: &TransactionView) -> Result<(), Error>;
+    /// The status of the sent batch, whose first input is the issuer cell
+    fn transaction_status(&mut self, tx: &TransactionView) -> Result<TxStatus, Error>;
+}
+
+fn match_type_prefix(type_script: &Script, cell: &LiveCell) -> bool {
+    match cell.output.type_().to_opt() {
+        Some(type_) => {
+            type_.code_hash() == type_script.code_hash()
+                && type_.hash_type() == type_script.hash_type()
+                && type_
+                    .args()
+                    .raw_data()
+                    .starts_with(&type_script.args().raw_data())
+        }
+        None => false,
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    cells: HashMap<OutPoint, LiveCell>,
+    committed: HashSet<Byte32>,
+    // the block at the number is blocks[number - 1]
+    blocks: Vec<TransactionView>,
+    created: u64,
+}
+
+/// An in-memory chain which commits every transaction spending live cells at once in a
+/// block of its own, the scripts of the transactions are not run. The clones share the
+/// chain.
+#[derive(Default, Clone)]
+pub struct MockChain {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockChain {
+    // the chain stays usable if a thread panicked with it
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Create a live cell outside of any transaction, e.g. an issuer cell to test with
+    pub fn create_cell(&mut self, output: CellOutput, data: Bytes) -> OutPoint {
+        let mut state = self.state();
+        state.created += 1;
+        let mut tx_hash = [0u8; 32];
+        tx_hash[..8].copy_from_slice(&state.created.to_le_bytes());
+        let out_point = OutPoint::new(tx_hash.pack(), 0);
+        state.cells.insert(
+            out_point.clone(),
+            LiveCell {
+                out_point: out_point.clone(),
+                output,
+                data,
+            },
+        );
+        out_point
+    }
+}
+
+impl Chain for MockChain {
+    fn get_live_cell(&mut self, out_point: &OutPoint) -> Result<Option<LiveCell>, Error> {
+        Ok(self.state().cells.get(out_point).cloned())
+    }
+
+    fn get_live_cells_by_type(&mut self, type_script: &Script) -> Result<Vec<LiveCell>, Error> {
+        Ok(self
+            .state()
+            .cells
+            .values()
+            .filter(|cell| match_type_prefix(type_script, cell))
+            .cloned()
+            .collect())
+    }
+
+    fn get_tip_block_number(&mut self) -> Result<u64, Error> {
+        Ok(self.state().blocks.len() as u64)
+    }
+
+    fn get_block_transactions(
+        &mut self,
+        number: u64,
+    ) -> Result<Option<Vec<TransactionView>>, Error> {
+        if number == 0 {
+            return Ok(Some(vec![]));
+        }
+        Ok(self
+            .state()
+            .blocks
+            .get((number - 1) as usize)
+            .map(|tx| vec![tx.clone()]))
+    }
+
+    fn send_transaction(&mut self, tx: &TransactionView) -> Result<(), Error> {
+        let mut state = self.state();
+        let out_points: Vec<OutPoint> = tx.input_pts_iter().collect();
+        if out_points
+            .iter()
+            .any(|out_point| !state.cells.contains_key(out_point))
+        {
+            return Err(Error::CellNotLive);
+        }
+        for out_point in out_points {
+            state.cells.remove(&out_point);
+        }
+        for (index, (output, data)) in tx.outputs_with_data_iter().enumerate() {
+            let out_point = OutPoint::new(tx.hash(), index as u32);
+            state.cells.insert(
+                out_point.clone(),
+                LiveCell {
+                    out_point,
+                    output,
+                    data,
+                },
+            );
+        }
+        state.committed.insert(tx.hash());
+        state.blocks.push(tx.clone());
+        Ok(())
+    }
+
+    fn transaction_status(&mut self, tx: &TransactionView) -> Result<TxStatus, Error> {
+        if self.state().committed.contains(&tx.hash()) {
+            Ok(TxStatus::Committed)
+        } else {
+            Ok(TxStatus::Rejected)
+        }
+    }
+}
+
+// the number of cells in a page of the indexer
+const INDEXER_PAGE_LIMIT: u32 = 100;
+
+#[derive(Deserialize)]
+struct IndexerCell {
+    output: ckb_jsonrpc_types::CellOutput,
+    output_data: JsonBytes,
+    out_point: ckb_jsonrpc_types::OutPoint,
+}
+
+#[derive(Deserialize)]
+struct IndexerCells {
+    objects: Vec<IndexerCell>,
+    last_cursor: JsonBytes,
+}
+
+/// A CKB node reached by its JSON-RPC, e.g. a local devnet, and the ckb-indexer of it.
+/// The batches are sent as they are built, so the issuer lock must not require a witness,
+/// e.g. the always success lock deployed on the devnet.
+#[derive(Clone)]
+pub struct NodeChain {
+    client: Client,
+    url: String,
+    indexer_url: String,
+    id: u64,
+}
+
+impl NodeChain {
+    /// The urls of the node RPC and the indexer RPC, e.g. http://127.0.0.1:8114 and
+    /// http://127.0.0.1:8116
+    pub fn new(url: &str, indexer_url: &str) -> Result<Self, Error> {
+        let client = Client::builder().timeout(NODE_TIMEOUT).build()?;
+        Ok(NodeChain {
+            client,
+            url: url.to_string(),
+            indexer_url: indexer_url.to_string(),
+            id: 0,
+        })
+    }
+
+    fn call<T: DeserializeOwned>(&mut self, method: &str, params: Value) -> Result<T, Error> {
+        let url = self.url.clone();
+        self.call_url(&url, method, params)
+    }
+
+    fn call_url<T: DeserializeOwned>(
+        &mut self,
+        url: &str,
+        method: &str,
+        params: Value,
+    ) -> Result<T, Error> {
+        self.id += 1;
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.id,
+            "method": method,
+            "params": params,
+        });
+        // the node answers errors in the body, so another status is a failure of the transport
+        let body = self
+            .client
+            .post(url)
+            .json(&request)
+            .send()?
+            .error_for_status()?
+            .bytes()?;
+        match serde_json::from_slice(&body)? {
+            Output::Success(success) => Ok(serde_json::from_value(success.result)?),
+            Output::Failure(failure) => Err(Error::Node(failure.error.message)),
+        }
+    }
+}
+
+impl Chain for NodeChain {
+    fn get_live_cell(&mut self, out_point: &OutPoint) -> Result<Option<LiveCell>, Error> {
+        let json_out_point: ckb_jsonrpc_types::OutPoint = out_point.clone().into();
+        let cell: CellWithStatus = self.call("get_live_cell", json!([json_out_point, true]))?;
+        if cell.status != "live" {
+            return Ok(None);
+        }
+        let info = cell
+            .cell
+            .ok_or_else(|| Error::Node("live cell without info".to_string()))?;
+        let data = info
+            .data
+            .ok_or_else(|| Error::Node("live cell without data".to_string()))?;
+        Ok(Some(LiveCell {
+            out_point: out_point.clone(),
+            output: info.output.into(),
+            data: data.content.into_bytes(),
+        }))
+    }
+
+    fn get_live_cells_by_type(&mut self, type_script: &Script) -> Result<Vec<LiveCell>, Error> {
+        let search_key = json!({
+            "script": ckb_jsonrpc_types::Script::from(type_script.clone()),
+            "script_type": "type",
+        });
+        let indexer_url = self.indexer_url.clone();
+        let mut cells = Vec::new();
+        let mut cursor: Option<JsonBytes> = None;
+        loop {
+            let page: IndexerCells = self.call_url(
+                &indexer_url,
+                "get_cells",
+                json!([search_key, "asc", Uint32::from(INDEXER_PAGE_LIMIT), cursor]),
+            )?;
+            let count = page.objects.len();
+            cells.extend(page.objects.into_iter().map(|cell| LiveCell {
+                out_point: cell.out_point.into(),
+                output: cell.output.into(),
+                data: cell.output_data.into_bytes(),
+            }));
+            if count < INDEXER_PAGE_LIMIT as usize {
+                return Ok(cells);
+            }
+            cursor = Some(page.last_cursor);
+        }
+    }
+
+    fn get_tip_block_number(&mut self) -> Result<u64, Error> {
+        let number: BlockNumber = self.call("get_tip_block_number", json!([]))?;
+        Ok(number.value())
+    }
+
+    fn get_block_transactions(
+        &mut self,
+        number: u64,
+    ) -> Result<Option<Vec<TransactionView>>, Error> {
+        let block: Option<BlockView> =
+            self.call("get_block_by_number", json!([BlockNumber::from(number)]))?;
+        Ok(block.map(|block| {
+            block
+                .transactions
+                .into_iter()
+                .map(|tx| packed::Transaction::from(tx.inner).into_view())
+                .collect()
+        }))
+    }
+
+    fn send_transaction(&mut self, tx: &TransactionView) -> Result<(), Error> {
+        let json_tx: Transaction = tx.data().into();
+        let tx_hash: H256 = self.call("send_transaction", json!([json_tx, "passthrough"]))?;
+        if tx_hash.as_bytes() != tx.hash().as_slice() {
+            return Err(Error::Node("transaction hash not match".to_string()));
+        }
+        Ok(())
+    }
+
+    fn transaction_status(&mut self, tx: &TransactionView) -> Result<TxStatus, Error> {
+        // the issuer cell is looked up first, so a commit in between is not taken as a spend
+        // by another transaction
+        let issuer_live = match tx.input_pts_iter().next() {
+            Some(out_point) => self.get_live_cell(&out_point)?.is_some(),
+            None => false,
+        };
+        let tx_hash: H256 = tx.hash().unpack();
+        let status: Option<TransactionWithStatus> =
+            self.call("get_transaction", json!([tx_hash]))?;
+        Ok(match status.map(|status| status.tx_status.status) {
+            Some(Status::Committed) => TxStatus::Committed,
+            Some(Status::Pending) | Some(Status::Proposed) => TxStatus::Pending,
+            // the node has not seen or has dropped the transaction, it's sent again
+            None if issuer_live => match self.send_transaction(tx) {
+                Ok(()) => TxStatus::Pending,
+                Err(Error::Node(_)) => TxStatus::Rejected,
+                Err(err) => return Err(err),
+            },
+            None => TxStatus::Rejected,
+        })
+    }
+}
@@ -0,0 +1,87 @@
+use std::{fmt, io};
+
+const SCRIPT_ERROR: i64 = -1;
+const AGGREGATOR_ERROR: i64 = -2;
+const SERVICE_ERROR: i64 = -3;
+
+/// Error
+#[derive(Debug)]
+pub enum Error {
+    Aggregator(aggregator::error::Error),
+    Io(io::Error),
+    Json(serde_json::Error),
+    Http(reqwest::Error),
+    // the node answered with an error or an unexpected result
+    Node(String),
+    // the transaction spends a cell which is not live on the chain
+    CellNotLive,
+    IssuerNotFound,
+    InvalidParams(String),
+}
+
+impl From<aggregator::error::Error> for Error {
+    fn from(err: aggregator::error::Error) -> Self {
+        Error::Aggregator(err)
+    }
+}
+
+impl From<script_utils::error::Error> for Error {
+    fn from(err: script_utils::error::Error) -> Self {
+        Error::Aggregator(err.into())
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Http(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Aggregator(err) => write!(f, "{}", err),
+            Error::Io(err) => write!(f, "io error: {}", err),
+            Error::Json(err) => write!(f, "json error: {}", err),
+            Error::Http(err) => write!(f, "http error: {}", err),
+            Error::Node(message) => write!(f, "node error: {}", message),
+            Error::CellNotLive => write!(f, "cell is not live"),
+            Error::IssuerNotFound => write!(f, "issuer not found"),
+            Error::InvalidParams(message) => write!(f, "invalid params: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The exit code of the contract is the data of a script error, so wallets can tell which
+/// rule is broken
+impl From<Error> for jsonrpc_core::Error {
+    fn from(err: Error) -> Self {
+        let (code, data) = match &err {
+            Error::Aggregator(aggregator::error::Error::Script(script_err)) => {
+                (SCRIPT_ERROR, Some((*script_err as i8).into()))
+            }
+            Error::Aggregator(_) => (AGGREGATOR_ERROR, None),
+            Error::InvalidParams(message) => return jsonrpc_core::Error::invalid_params(message),
+            _ => (SERVICE_ERROR, None),
+        };
+        jsonrpc_core::Error {
+            code: jsonrpc_core::ErrorCode::ServerError(code),
+            message: err.to_string(),
+            data,
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod chain;
+pub mod error;
+pub mod server;
+pub mod service;
+pub mod types;
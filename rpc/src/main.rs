@@ -0,0 +1,227 @@
+use ckb_types::{
+    bytes::Bytes,
+    core::{Capacity, DepType, ScriptHashType},
+    packed::{CellDep, CellOutput, OutPoint, Script},
+    prelude::{Builder, Pack},
+};
+use clap::{App, Arg, ArgMatches};
+use log::info;
+use rpc::{
+    chain::{Chain, MockChain, NodeChain},
+    error::Error,
+    server::{rpc_handler, serve, spawn_poller},
+    service::{Service, ServiceConfig},
+};
+use script_utils::{helpers::ISSUER_CELL, issuer::Issuer};
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+const MOCK_ISSUER_CAPACITY: u64 = 100_000 * 100_000_000;
+
+fn parse_hex(hex: &str) -> Result<Vec<u8>, Error> {
+    let hex = hex.trim_start_matches("0x");
+    if hex.len() % 2 != 0 {
+        return Err(Error::InvalidParams(format!("invalid hex: {}", hex)));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| {
+            u8::from_str_radix(&hex[index..(index + 2)], 16)
+                .map_err(|_| Error::InvalidParams(format!("invalid hex: {}", hex)))
+        })
+        .collect()
+}
+
+// <tx_hash>:<index>
+fn parse_out_point(value: &str) -> Result<OutPoint, Error> {
+    let invalid = || Error::InvalidParams(format!("invalid out point: {}", value));
+    let mut parts = value.split(':');
+    let tx_hash = parse_hex(parts.next().ok_or_else(invalid)?)?;
+    let index = parts
+        .next()
+        .and_then(|index| index.parse::<u32>().ok())
+        .ok_or_else(invalid)?;
+    if tx_hash.len() != 32 || parts.next().is_some() {
+        return Err(invalid());
+    }
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&tx_hash);
+    Ok(OutPoint::new(hash.pack(), index))
+}
+
+// <tx_hash>:<index>[:dep_group]
+fn parse_cell_dep(value: &str) -> Result<CellDep, Error> {
+    let (out_point, dep_type) = match value.strip_suffix(":dep_group") {
+        Some(out_point) => (out_point, DepType::DepGroup),
+        None => (value, DepType::Code),
+    };
+    Ok(CellDep::new_builder()
+        .out_point(parse_out_point(out_point)?)
+        .dep_type(dep_type.into())
+        .build())
+}
+
+// an issuer cell with an empty tree, the scripts are not run by the mock chain
+fn create_mock_issuer(chain: &mut MockChain, index: u32) -> Result<OutPoint, Error> {
+    let mut issuer_id = [0u8; 20];
+    issuer_id[..4].copy_from_slice(&index.to_le_bytes());
+    let type_ = Script::new_builder()
+        .hash_type(ScriptHashType::Type.into())
+        .args(issuer_id.to_vec().pack())
+        .build();
+    let output = CellOutput::new_builder()
+        .capacity(Capacity::shannons(MOCK_ISSUER_CAPACITY).pack())
+        .type_(Some(type_).pack())
+        .build();
+    let issuer = Issuer {
+        version: 0,
+        class_count: 0,
+        set_count: 0,
+        smt_root: [0u8; 32],
+        info: Vec::new(),
+    };
+    let mut data = vec![ISSUER_CELL];
+    data.extend_from_slice(&issuer.to_data()?);
+    Ok(chain.create_cell(output, Bytes::from(data)))
+}
+
+fn run<C: Chain + 'static>(
+    chain: C,
+    matches: &ArgMatches,
+    issuers: Vec<OutPoint>,
+) -> Result<(), Error> {
+    let cell_deps = matches
+        .values_of("cell-dep")
+        .into_iter()
+        .flatten()
+        .map(parse_cell_dep)
+        .collect::<Result<Vec<_>, _>>()?;
+    let parse_u64 = |name: &str| -> Result<u64, Error> {
+        let value = matches.value_of(name).unwrap_or_default();
+        value
+            .parse()
+            .map_err(|_| Error::InvalidParams(format!("invalid {}: {}", name, value)))
+    };
+    let config = ServiceConfig {
+        store_dir: PathBuf::from(matches.value_of("store-dir").unwrap_or_default()),
+        cell_deps,
+        fee: parse_u64("fee")?,
+    };
+    std::fs::create_dir_all(&config.store_dir)?;
+
+    let mut service = Service::new(chain, config);
+    for out_point in issuers {
+        let issuer_id = service.register_issuer(&out_point)?;
+        let hex: String = issuer_id
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        info!("serving issuer 0x{}", hex);
+    }
+
+    let service = Arc::new(Mutex::new(service));
+    spawn_poller(
+        Arc::clone(&service),
+        Duration::from_secs(parse_u64("poll-interval")?),
+    );
+    let listen = matches.value_of("listen").unwrap_or_default();
+    let addr: SocketAddr = listen
+        .parse()
+        .map_err(|_| Error::InvalidParams(format!("invalid listen: {}", listen)))?;
+    info!("listening on {}", addr);
+    serve(&addr, rpc_handler(&service))
+}
+
+fn main() -> Result<(), Error> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let matches = App::new("compact-nft-rpc")
+        .about("JSON-RPC service submitting and querying compact NFT operations")
+        .arg(
+            Arg::with_name("listen")
+                .long("listen")
+                .takes_value(true)
+                .default_value("127.0.0.1:8120"),
+        )
+        .arg(
+            Arg::with_name("node")
+                .long("node")
+                .takes_value(true)
+                .required_unless("mock")
+                .help("The RPC url of the CKB node, e.g. http://127.0.0.1:8114"),
+        )
+        .arg(
+            Arg::with_name("indexer")
+                .long("indexer")
+                .takes_value(true)
+                .required_unless("mock")
+                .help("The RPC url of the ckb-indexer of the node, e.g. http://127.0.0.1:8116"),
+        )
+        .arg(
+            Arg::with_name("mock")
+                .long("mock")
+                .takes_value(true)
+                .conflicts_with("node")
+                .help("Run on an in-memory chain with the number of empty issuer cells"),
+        )
+        .arg(
+            Arg::with_name("store-dir")
+                .long("store-dir")
+                .takes_value(true)
+                .default_value("store")
+                .help("The directory of the issuer trees"),
+        )
+        .arg(
+            Arg::with_name("issuer")
+                .long("issuer")
+                .takes_value(true)
+                .multiple(true)
+                .help("The out point of a live issuer cell, <tx_hash>:<index>"),
+        )
+        .arg(
+            Arg::with_name("cell-dep")
+                .long("cell-dep")
+                .takes_value(true)
+                .multiple(true)
+                .help("A cell dep of the batches, <tx_hash>:<index>[:dep_group]"),
+        )
+        .arg(
+            Arg::with_name("fee")
+                .long("fee")
+                .takes_value(true)
+                .default_value("100000")
+                .help("The fee of every batch in shannons, paid by the issuer cell"),
+        )
+        .arg(
+            Arg::with_name("poll-interval")
+                .long("poll-interval")
+                .takes_value(true)
+                .default_value("3")
+                .help("The seconds between sending and checking batches"),
+        )
+        .get_matches();
+
+    let mut issuers = matches
+        .values_of("issuer")
+        .into_iter()
+        .flatten()
+        .map(parse_out_point)
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(count) = matches.value_of("mock") {
+        let count: u32 = count
+            .parse()
+            .map_err(|_| Error::InvalidParams(format!("invalid mock: {}", count)))?;
+        let mut chain = MockChain::default();
+        for index in 0..count {
+            issuers.push(create_mock_issuer(&mut chain, index)?);
+        }
+        run(chain, &matches, issuers)
+    } else {
+        let url = matches.value_of("node").unwrap_or_default();
+        let indexer_url = matches.value_of("indexer").unwrap_or_default();
+        run(NodeChain::new(url, indexer_url)?, &matches, issuers)
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{
+    chain::Chain,
+    error::Error,
+    service::Service,
+    types::{ClassInfo, NftInfo, NftSubmission, OwnedNftInfo, SmtProof},
+};
+use ckb_jsonrpc_types::{Byte32, JsonBytes, Uint32};
+use jsonrpc_core::{IoHandler, Params, Value};
+use jsonrpc_http_server::ServerBuilder;
+use log::error;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+const MAX_BODY_LEN: usize = 16 * 1024 * 1024;
+
+fn add_method<C, T, R, F>(io: &mut IoHandler, service: &Arc<Mutex<Service<C>>>, name: &str, f: F)
+where
+    C: Chain + 'static,
+    T: DeserializeOwned,
+    R: Serialize,
+    F: Fn(&mut Service<C>, T) -> Result<R, Error> + Send + Sync + 'static,
+{
+    let service = Arc::clone(service);
+    io.add_method(name, move |params: Params| -> jsonrpc_core::Result<Value> {
+        let params: T = params.parse()?;
+        let mut service = service
+            .lock()
+            .map_err(|_| jsonrpc_core::Error::internal_error())?;
+        let result = f(&mut service, params)?;
+        serde_json::to_value(result).map_err(|_| jsonrpc_core::Error::internal_error())
+    });
+}
+
+/// The JSON-RPC methods of the service, every method takes its params by position:
+/// submit_transaction(issuer_id, NftSubmission) -> transaction
+/// get_nft(issuer_id, class_id, token_id) -> NftInfo | null
+/// get_class(issuer_id, class_id) -> ClassInfo | null
+/// get_proof(issuer_id, [key]) -> SmtProof
+/// list_nfts_by_owner(owner) -> [OwnedNftInfo]
+pub fn rpc_handler<C: Chain + 'static>(service: &Arc<Mutex<Service<C>>>) -> IoHandler {
+    let mut io = IoHandler::new();
+    add_method(
+        &mut io,
+        service,
+        "submit_transaction",
+        |service,
+         (issuer_id, submission): (JsonBytes, NftSubmission)|
+         -> Result<JsonBytes, Error> { service.submit_transaction(&issuer_id, submission) },
+    );
+    add_method(
+        &mut io,
+        service,
+        "get_nft",
+        |service,
+         (issuer_id, class_id, token_id): (JsonBytes, Uint32, Uint32)|
+         -> Result<Option<NftInfo>, Error> {
+            service.get_nft(&issuer_id, class_id.value(), token_id.value())
+        },
+    );
+    add_method(
+        &mut io,
+        service,
+        "get_class",
+        |service, (issuer_id, class_id): (JsonBytes, Uint32)| -> Result<Option<ClassInfo>, Error> {
+            service.get_class(&issuer_id, class_id.value())
+        },
+    );
+    add_method(
+        &mut io,
+        service,
+        "get_proof",
+        |service, (issuer_id, keys): (JsonBytes, Vec<Byte32>)| -> Result<SmtProof, Error> {
+            service.get_proof(&issuer_id, keys)
+        },
+    );
+    add_method(
+        &mut io,
+        service,
+        "list_nfts_by_owner",
+        |service, (owner,): (Byte32,)| -> Result<Vec<OwnedNftInfo>, Error> {
+            Ok(service.list_nfts_by_owner(&owner))
+        },
+    );
+    io
+}
+
+/// Poll the service every interval in a background thread, so the batches move on
+/// without requests. The service is only locked to start and finish a poll, the node is
+/// reached in between by a client of the poller.
+pub fn spawn_poller<C: Chain + 'static>(service: Arc<Mutex<Service<C>>>, interval: Duration) {
+    thread::spawn(move || {
+        let mut chain = match service.lock() {
+            Ok(service) => service.chain(),
+            Err(_) => return,
+        };
+        loop {
+            thread::sleep(interval);
+            let tasks = match service.lock() {
+                Ok(mut service) => service.start_poll(),
+                Err(_) => return,
+            };
+            let tasks = match tasks {
+                Ok(tasks) => tasks,
+                Err(err) => {
+                    error!("poll error: {}", err);
+                    continue;
+                }
+            };
+            let results = tasks.run(&mut chain);
+            let result = match service.lock() {
+                Ok(mut service) => service.finish_poll(results),
+                Err(_) => return,
+            };
+            if let Err(err) = result {
+                error!("poll error: {}", err);
+            }
+        }
+    });
+}
+
+/// Serve the JSON-RPC over HTTP on the address until the server is closed
+pub fn serve(addr: &SocketAddr, io: IoHandler) -> Result<(), Error> {
+    let server = ServerBuilder::new(io)
+        .max_request_body_size(MAX_BODY_LEN)
+        .start_http(addr)?;
+    server.wait();
+    Ok(())
+}
@@ -0,0 +1,423 @@
+use crate::{
+    chain::{Chain, TxStatus},
+    error::Error,
+    types::{ClassInfo, NftInfo, NftSubmission, OwnedNftInfo, SmtProof},
+};
+use aggregator::{
+    batch::{Aggregator, Submission},
+    indexer::OwnerIndex,
+    state::IssuerState,
+};
+use ckb_jsonrpc_types::{Byte32 as JsonByte32, JsonBytes};
+use ckb_types::{
+    core::TransactionView,
+    packed::{Byte32, CellDep, OutPoint, Script},
+    prelude::Entity,
+};
+use mol::NftTransaction;
+use script_utils::{class::Class, issuer::ISSUER_TYPE_ARGS_LEN, misc::NftValue, nft::Nft};
+use sparse_merkle_tree::{traits::Value, H256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
+pub struct ServiceConfig {
+    // the tree of every issuer is stored in <store_dir>/<issuer_id>.log
+    pub store_dir: PathBuf,
+    // the cell deps of the batches, e.g. the contract and the issuer lock
+    pub cell_deps: Vec<CellDep>,
+    // the fee of every batch, paid by the issuer cell
+    pub fee: u64,
+}
+
+struct IssuerEntry {
+    aggregator: Aggregator,
+    // the L1 transaction of the pending batch
+    sent: Option<TransactionView>,
+}
+
+/// The issuers served by the aggregators and the chain their batches are sent to
+pub struct Service<C> {
+    // the client of the requests, the poller has a clone of its own
+    chain: C,
+    config: ServiceConfig,
+    issuers: BTreeMap<[u8; 20], IssuerEntry>,
+    // the nfts of the issuers by owner, as of the scanned blocks
+    index: OwnerIndex,
+    // the block to scan next, set when the first issuer is registered
+    next_block: Option<u64>,
+    // the leaves changed by the sent batches, by the hash of their L1 transactions
+    batch_leaves: HashMap<Byte32, Vec<(H256, Vec<u8>)>>,
+}
+
+/// The node round-trips of a poll, they are run without the service so the requests
+/// are served meanwhile
+pub struct PollTasks {
+    // the first block to scan, None before any issuer is registered
+    next_block: Option<u64>,
+    // the L1 transactions of the sent batches to check
+    sent: Vec<([u8; 20], TransactionView)>,
+    // the L1 transactions of the new batches to send
+    new: Vec<([u8; 20], TransactionView)>,
+}
+
+/// The answers of the node to the PollTasks
+pub struct PollResults {
+    // the transactions of the scanned blocks from the next block on
+    blocks: Vec<Vec<TransactionView>>,
+    // the error which stopped the scan
+    scan_error: Option<Error>,
+    sent: Vec<([u8; 20], Byte32, Result<TxStatus, Error>)>,
+    new: Vec<([u8; 20], Byte32, Result<(), Error>)>,
+}
+
+impl PollTasks {
+    pub fn run<C: Chain>(self, chain: &mut C) -> PollResults {
+        let mut blocks = Vec::new();
+        let mut scan_error = None;
+        if let Some(number) = self.next_block {
+            loop {
+                match chain.get_block_transactions(number + blocks.len() as u64) {
+                    Ok(Some(txs)) => blocks.push(txs),
+                    Ok(None) => break,
+                    Err(err) => {
+                        scan_error = Some(err);
+                        break;
+                    }
+                }
+            }
+        }
+        let sent = self
+            .sent
+            .into_iter()
+            .map(|(issuer_id, tx)| (issuer_id, tx.hash(), chain.transaction_status(&tx)))
+            .collect();
+        let new = self
+            .new
+            .into_iter()
+            .map(|(issuer_id, tx)| (issuer_id, tx.hash(), chain.send_transaction(&tx)))
+            .collect();
+        PollResults {
+            blocks,
+            scan_error,
+            sent,
+            new,
+        }
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn parse_issuer_id(issuer_id: &JsonBytes) -> Result<[u8; 20], Error> {
+    if issuer_id.len() != ISSUER_TYPE_ARGS_LEN {
+        return Err(Error::InvalidParams(
+            "issuer_id must be 20 bytes".to_string(),
+        ));
+    }
+    let mut buf = [0u8; 20];
+    buf.copy_from_slice(issuer_id.as_bytes());
+    Ok(buf)
+}
+
+fn parse_nft_transaction(transaction: &JsonBytes) -> Result<NftTransaction, Error> {
+    NftTransaction::from_slice(transaction.as_bytes())
+        .map_err(|_| Error::InvalidParams("invalid NftTransaction".to_string()))
+}
+
+// the nft of the leaf if the leaf is a nft leaf, the key tells nft leaves from the others
+fn parse_nft_leaf(key: &H256, leaf: &[u8]) -> Option<NftInfo> {
+    let nft = Nft::from_data_leaf(leaf).ok()?;
+    if &nft.to_key() != key {
+        return None;
+    }
+    Some(NftInfo::new(&nft))
+}
+
+impl<C: Chain> Service<C> {
+    pub fn new(chain: C, config: ServiceConfig) -> Self {
+        Service {
+            chain,
+            config,
+            issuers: BTreeMap::new(),
+            index: OwnerIndex::default(),
+            next_block: None,
+            batch_leaves: HashMap::new(),
+        }
+    }
+
+    pub fn chain_mut(&mut self) -> &mut C {
+        &mut self.chain
+    }
+
+    /// Another client of the chain, to reach the node without locking the service
+    pub fn chain(&self) -> C {
+        self.chain.clone()
+    }
+
+    /// Serve the issuer whose cell is at the out_point, an issuer is registered only once
+    pub fn register_issuer(&mut self, out_point: &OutPoint) -> Result<[u8; 20], Error> {
+        let issuer_cell = self
+            .chain
+            .get_live_cell(out_point)?
+            .ok_or(Error::CellNotLive)?;
+        let args = issuer_cell
+            .output
+            .type_()
+            .to_opt()
+            .map(|type_| type_.args().raw_data())
+            .unwrap_or_default();
+        if args.len() != ISSUER_TYPE_ARGS_LEN {
+            return Err(Error::InvalidParams("not an issuer cell".to_string()));
+        }
+        let mut issuer_id = [0u8; 20];
+        issuer_id.copy_from_slice(&args);
+        if self.issuers.contains_key(&issuer_id) {
+            return Ok(issuer_id);
+        }
+        let issuer_type = issuer_cell.output.type_().to_opt().unwrap_or_default();
+
+        let path = self
+            .config
+            .store_dir
+            .join(format!("{}.log", to_hex(&issuer_id)));
+        let state = IssuerState::open(path, issuer_id)?;
+        let aggregator = Aggregator::new(state, issuer_cell, self.config.cell_deps.clone())?
+            .with_fee(self.config.fee);
+
+        // the nft cells live before are searched by the type of the issuer, whose args are
+        // the prefix of the args of its nft cells, the later ones are found by scanning the blocks
+        if self.next_block.is_none() {
+            self.next_block = Some(self.chain.get_tip_block_number()? + 1);
+        }
+        let cells = self.chain.get_live_cells_by_type(&issuer_type)?;
+        self.index.add_issuer(&issuer_type, aggregator.state());
+        self.index.apply_live_cells(&cells);
+        self.issuers.insert(
+            issuer_id,
+            IssuerEntry {
+                aggregator,
+                sent: None,
+            },
+        );
+        Ok(issuer_id)
+    }
+
+    fn aggregator(&self, issuer_id: &JsonBytes) -> Result<&Aggregator, Error> {
+        self.issuers
+            .get(&parse_issuer_id(issuer_id)?)
+            .map(|entry| &entry.aggregator)
+            .ok_or(Error::IssuerNotFound)
+    }
+
+    /// Accept the transaction into the next batch of the issuer and return it as accepted,
+    /// i.e. with its old values filled if it's unsigned
+    pub fn submit_transaction(
+        &mut self,
+        issuer_id: &JsonBytes,
+        submission: NftSubmission,
+    ) -> Result<JsonBytes, Error> {
+        let tx = parse_nft_transaction(&submission.transaction)?;
+        let chain = &mut self.chain;
+        let input_cells = submission
+            .input_cells
+            .into_iter()
+            .map(|out_point| {
+                chain
+                    .get_live_cell(&out_point.into())?
+                    .ok_or(Error::CellNotLive)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let output_locks = submission
+            .output_locks
+            .into_iter()
+            .map(Script::from)
+            .collect();
+
+        let entry = self
+            .issuers
+            .get_mut(&parse_issuer_id(issuer_id)?)
+            .ok_or(Error::IssuerNotFound)?;
+        entry.aggregator.submit(Submission {
+            tx,
+            output_locks,
+            input_cells,
+        })?;
+        let accepted = entry.aggregator.pool().last().expect("accepted submission");
+        Ok(JsonBytes::from_bytes(accepted.tx.as_bytes()))
+    }
+
+    /// The nft in the tree of the issuer, including the batch being sent
+    pub fn get_nft(
+        &self,
+        issuer_id: &JsonBytes,
+        class_id: u32,
+        token_id: u32,
+    ) -> Result<Option<NftInfo>, Error> {
+        let aggregator = self.aggregator(issuer_id)?;
+        let nft = Nft {
+            issuer_id: *aggregator.state().issuer_id(),
+            class_id,
+            token_id,
+            ..Default::default()
+        };
+        let key = nft.to_key();
+        let leaf = aggregator.state().leaf(&key)?;
+        Ok(parse_nft_leaf(&key, &leaf))
+    }
+
+    /// The class in the tree of the issuer, including the batch being sent
+    pub fn get_class(
+        &self,
+        issuer_id: &JsonBytes,
+        class_id: u32,
+    ) -> Result<Option<ClassInfo>, Error> {
+        let aggregator = self.aggregator(issuer_id)?;
+        let class = Class {
+            issuer_id: *aggregator.state().issuer_id(),
+            class_id,
+            ..Default::default()
+        };
+        let leaf = aggregator.state().leaf(&class.to_key())?;
+        if leaf.is_empty() {
+            return Ok(None);
+        }
+        let class = Class::from_data(&leaf, false)?;
+        Ok(Some(ClassInfo::new(&class, leaf)))
+    }
+
+    /// The proof of the leaves of the keys under the current root of the issuer tree
+    pub fn get_proof(
+        &self,
+        issuer_id: &JsonBytes,
+        keys: Vec<JsonByte32>,
+    ) -> Result<SmtProof, Error> {
+        if keys.is_empty() {
+            return Err(Error::InvalidParams("no keys".to_string()));
+        }
+        let state = self.aggregator(issuer_id)?.state();
+        let keys: Vec<H256> = keys.into_iter().map(|key| key.0.into()).collect();
+        let leaves = keys
+            .iter()
+            .map(|key| state.leaf(key))
+            .collect::<Result<Vec<_>, _>>()?;
+        let proof = state
+            .smt()
+            .merkle_proof(keys.clone())
+            .and_then(|proof| {
+                proof.compile(
+                    keys.iter()
+                        .zip(&leaves)
+                        .map(|(key, leaf)| (*key, NftValue(leaf.clone()).to_h256()))
+                        .collect(),
+                )
+            })
+            .map_err(aggregator::error::Error::from)?;
+
+        let mut root = [0u8; 32];
+        root.copy_from_slice(state.root().as_slice());
+        Ok(SmtProof {
+            root: JsonByte32::new(root),
+            leaves: leaves.into_iter().map(JsonBytes::from_vec).collect(),
+            proof: JsonBytes::from_vec(proof.0),
+        })
+    }
+
+    /// The nfts of the owner in the trees and the nft cells of all issuers, as of the
+    /// scanned blocks
+    pub fn list_nfts_by_owner(&self, owner: &JsonByte32) -> Vec<OwnedNftInfo> {
+        self.index
+            .nfts_of(&owner.0)
+            .map(OwnedNftInfo::new)
+            .collect()
+    }
+
+    /// Index the new blocks, then move the batch of every issuer on: commit or roll back the
+    /// sent batch by the status of its L1 transaction, or send the pool as a new batch
+    pub fn poll(&mut self) -> Result<(), Error> {
+        let tasks = self.start_poll()?;
+        let results = tasks.run(&mut self.chain);
+        self.finish_poll(results)
+    }
+
+    /// Build the pools of the issuers without a sent batch into new batches, and return
+    /// the node round-trips of the poll to run by PollTasks::run
+    pub fn start_poll(&mut self) -> Result<PollTasks, Error> {
+        let mut tasks = PollTasks {
+            next_block: self.next_block,
+            sent: Vec::new(),
+            new: Vec::new(),
+        };
+        for (issuer_id, entry) in self.issuers.iter_mut() {
+            if let Some(tx) = entry.sent.clone() {
+                tasks.sent.push((*issuer_id, tx));
+            } else if !entry.aggregator.pool().is_empty() {
+                let tx = entry.aggregator.build()?;
+                self.batch_leaves
+                    .insert(tx.hash(), entry.aggregator.pending_leaves().to_vec());
+                entry.sent = Some(tx.clone());
+                tasks.new.push((*issuer_id, tx));
+            }
+        }
+        Ok(tasks)
+    }
+
+    /// Apply the results of the PollTasks, returns the first error of the node
+    pub fn finish_poll(&mut self, results: PollResults) -> Result<(), Error> {
+        // the leaves of a batch are indexed with its transaction
+        for txs in results.blocks {
+            for tx in txs {
+                if let Some(leaves) = self.batch_leaves.remove(&tx.hash()) {
+                    self.index.apply_leaves(&leaves);
+                }
+                self.index.apply_transaction(&tx);
+            }
+            self.next_block = self.next_block.map(|number| number + 1);
+        }
+        let mut error = results.scan_error;
+
+        for (issuer_id, tx_hash, status) in results.sent {
+            let entry = self.issuers.get_mut(&issuer_id).expect("polled issuer");
+            match status {
+                Ok(TxStatus::Pending) => {}
+                Ok(TxStatus::Committed) => {
+                    // the issuer cell is the first output
+                    entry.aggregator.commit(OutPoint::new(tx_hash, 0))?;
+                    entry.sent = None;
+                }
+                Ok(TxStatus::Rejected) => {
+                    self.batch_leaves.remove(&tx_hash);
+                    entry.aggregator.rollback()?;
+                    entry.sent = None;
+                }
+                Err(err) => {
+                    error.get_or_insert(err);
+                }
+            }
+        }
+
+        for (issuer_id, tx_hash, sent) in results.new {
+            if let Err(err) = sent {
+                let entry = self.issuers.get_mut(&issuer_id).expect("polled issuer");
+                self.batch_leaves.remove(&tx_hash);
+                entry.aggregator.rollback()?;
+                entry.sent = None;
+                error.get_or_insert(err);
+            }
+        }
+
+        match error {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// The hash of the L1 transaction of the batch being sent
+    pub fn sent_transaction(&self, issuer_id: &JsonBytes) -> Result<Option<Byte32>, Error> {
+        let issuer_id = parse_issuer_id(issuer_id)?;
+        let entry = self.issuers.get(&issuer_id).ok_or(Error::IssuerNotFound)?;
+        Ok(entry.sent.as_ref().map(|tx| tx.hash()))
+    }
+}
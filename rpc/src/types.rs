@@ -0,0 +1,128 @@
+use aggregator::indexer::{NftLocation, OwnedNft};
+use ckb_jsonrpc_types::{Byte32, JsonBytes, OutPoint, Script, Uint32};
+use script_utils::{class::Class, nft::Nft};
+use serde::{Deserialize, Serialize};
+
+/// A NftTransaction in molecule encoding and the L1 cells it brings into the batch,
+/// see aggregator::batch::Submission
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NftSubmission {
+    pub transaction: JsonBytes,
+    #[serde(default)]
+    pub output_locks: Vec<Script>,
+    #[serde(default)]
+    pub input_cells: Vec<OutPoint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NftInfo {
+    pub issuer_id: JsonBytes,
+    pub class_id: Uint32,
+    pub token_id: Uint32,
+    pub characteristic: JsonBytes,
+    pub configure: Uint32,
+    pub state: Uint32,
+    pub nonce: Uint32,
+    pub owner: Byte32,
+    pub ext_info: JsonBytes,
+    // the key and value of the leaf in the issuer tree
+    pub key: Byte32,
+    pub leaf: JsonBytes,
+}
+
+impl NftInfo {
+    pub fn new(nft: &Nft) -> Self {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(nft.to_key().as_slice());
+        NftInfo {
+            issuer_id: JsonBytes::from_vec(nft.issuer_id.to_vec()),
+            class_id: nft.class_id.into(),
+            token_id: nft.token_id.into(),
+            characteristic: JsonBytes::from_vec(nft.characteristic.to_vec()),
+            configure: u32::from(nft.configure).into(),
+            state: u32::from(nft.state).into(),
+            nonce: nft.nonce.into(),
+            owner: Byte32::new(nft.owner),
+            ext_info: JsonBytes::from_vec(nft.ext_info.clone()),
+            key: Byte32::new(key),
+            leaf: JsonBytes::from_vec(nft.to_leaf_data()),
+        }
+    }
+}
+
+/// A nft of an owner, the cell is the out point of the nft cell or null if the nft is a
+/// leaf of the issuer tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OwnedNftInfo {
+    pub issuer_id: JsonBytes,
+    pub class_id: Uint32,
+    pub token_id: Uint32,
+    pub owner: Byte32,
+    pub cell: Option<OutPoint>,
+}
+
+impl OwnedNftInfo {
+    pub fn new(nft: &OwnedNft) -> Self {
+        let cell = match &nft.location {
+            NftLocation::Leaf(_) => None,
+            NftLocation::Cell(out_point) => Some(out_point.clone().into()),
+        };
+        OwnedNftInfo {
+            issuer_id: JsonBytes::from_vec(nft.id.issuer_id.to_vec()),
+            class_id: nft.id.class_id.into(),
+            token_id: nft.id.token_id.into(),
+            owner: Byte32::new(nft.owner),
+            cell,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassInfo {
+    pub issuer_id: JsonBytes,
+    pub class_id: Uint32,
+    pub total: Uint32,
+    pub issued: Uint32,
+    pub burned: Uint32,
+    pub configure: Uint32,
+    pub owner: Byte32,
+    pub name: JsonBytes,
+    pub description: JsonBytes,
+    pub renderer: JsonBytes,
+    pub ext_info: JsonBytes,
+    // the key and value of the leaf in the issuer tree
+    pub key: Byte32,
+    pub leaf: JsonBytes,
+}
+
+impl ClassInfo {
+    /// The class decoded from the leaf
+    pub fn new(class: &Class, leaf: Vec<u8>) -> Self {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(class.to_key().as_slice());
+        ClassInfo {
+            issuer_id: JsonBytes::from_vec(class.issuer_id.to_vec()),
+            class_id: class.class_id.into(),
+            total: class.total.into(),
+            issued: class.issued.into(),
+            burned: class.burned.into(),
+            configure: u32::from(class.configure).into(),
+            owner: Byte32::new(class.owner),
+            name: JsonBytes::from_vec(class.name.clone()),
+            description: JsonBytes::from_vec(class.description.clone()),
+            renderer: JsonBytes::from_vec(class.renderer.clone()),
+            ext_info: JsonBytes::from_vec(class.ext_info.clone()),
+            key: Byte32::new(key),
+            leaf: JsonBytes::from_vec(leaf),
+        }
+    }
+}
+
+/// The compiled merkle proof of the leaves of the keys under the root, an empty leaf
+/// means the key is absent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtProof {
+    pub root: Byte32,
+    pub leaves: Vec<JsonBytes>,
+    pub proof: JsonBytes,
+}
@@ -1,19 +1,19 @@
 use crate::error::Error;
-use crate::helpers::{parse_dyn_vec_len, u32_from_slice, DYN_MIN_LEN};
+use crate::helpers::{fit_dyn_vec_len, parse_dyn_vec_len, u32_from_slice, CLASS_LEAF, DYN_MIN_LEN};
 use crate::misc::new_blake2b;
 use alloc::vec::Vec;
 use core::result::Result;
 use sparse_merkle_tree::{traits::Value, H256};
 
 
-const FIXED_LEN: usize = 66;
+const FIXED_LEN: usize = 70;
 
-const CLASS_DATA_MIN_LEN: usize = 72;
+const CLASS_DATA_MIN_LEN: usize = 76;
 
-const FIXED_LEN_IN_CELL: usize = 10;
+const FIXED_LEN_IN_CELL: usize = 14;
 // in cell, owner will be in lock_script, issuer_id and class_id will be in type_args
 // FIXED_LEN_IN_CELL + DYN_MIN_LEN * 3
-const CLASS_DATA_MIN_LEN_IN_CELL: usize = 16;
+const CLASS_DATA_MIN_LEN_IN_CELL: usize = 20;
 
 pub const CLASS_TYPE_ARGS_LEN: usize = 24;
 
@@ -24,15 +24,19 @@ pub const CLASS_TYPE_ARGS_LEN: usize = 24;
 /// 3) class_id: u32,
 /// 4) total: u32
 /// 5) issued: u32
-/// 6) configure: u8
-/// 7) owner: [u8; 32],
-/// 8) name: <size: u16> + <content>
-/// 9) description: <size: u16> + <content>
-/// 10) renderer: <size: u16> + <content>
-/// 11) extinfo_data: <size: u16> + <content>
-/// The fields of 1), 2), 3), 4), 6), 8) and 9) cannot be changed after they are set and they cannot be
-/// missing. The fields of 5) and 10) can be changed and it cannot be missing.
-/// The filed of 11) can be changed and it also can be missing and it will not be validated.
+/// 6) burned: u32
+/// 7) configure: u8
+/// 8) owner: [u8; 32],
+/// 9) name: <size: u16> + <content>
+/// 10) description: <size: u16> + <content>
+/// 11) renderer: <size: u16> + <content>
+/// 12) extinfo_data: <size: u16> + <content>
+/// The fields of 1), 2), 3), 4), 7), 9) and 10) cannot be changed after they are set and they cannot be
+/// missing. The fields of 5), 6) and 11) can be changed and it cannot be missing, 11) can only be
+/// changed by the owner.
+/// 6) counts the nft cells of the class destroyed by their owners, it cannot be larger than 5).
+/// The filed of 12) can be missing, it can only be appended to if the configure allows ext info,
+/// otherwise it cannot be changed.
 #[derive(Debug, Clone, Default)]
 pub struct Class {
     pub version: u8,
@@ -40,10 +44,13 @@ pub struct Class {
     pub class_id: u32,
     pub total: u32,
     pub issued: u32,
+    pub burned: u32,
     pub configure: u8,
     pub owner: [u8; 32],
     pub name: Vec<u8>,
     pub description: Vec<u8>,
+    pub renderer: Vec<u8>,
+    pub ext_info: Vec<u8>,
 }
 
 impl Class {
@@ -60,38 +67,45 @@ impl Class {
 
             let total = u32_from_slice(&data[1..5]);
             let issued = u32_from_slice(&data[5..9]);
+            let burned = u32_from_slice(&data[9..13]);
 
             if total > 0 && issued > total {
                 return Err(Error::ClassTotalSmallerThanIssued);
             }
+            if burned > issued {
+                return Err(Error::ClassBurnedInvalid);
+            }
 
-            let configure: u8 = data[9];
+            let configure: u8 = data[13];
 
-            let name_len =
-                parse_dyn_vec_len(&data[FIXED_LEN_IN_CELL..(FIXED_LEN_IN_CELL + DYN_MIN_LEN)]);
+            let name_len = parse_dyn_vec_len(&data[FIXED_LEN_IN_CELL..])?;
             // DYN_MIN_LEN: the min length of description
             if data.len() < FIXED_LEN_IN_CELL + name_len + DYN_MIN_LEN {
                 return Err(Error::ClassDataInvalid);
             }
-            let name = data[FIXED_LEN_IN_CELL..(FIXED_LEN_IN_CELL + name_len)].to_vec();
+            let name =
+                data[(FIXED_LEN_IN_CELL + DYN_MIN_LEN)..(FIXED_LEN_IN_CELL + name_len)].to_vec();
 
             let description_index = FIXED_LEN_IN_CELL + name_len;
-            let description_len =
-                parse_dyn_vec_len(&data[description_index..(description_index + DYN_MIN_LEN)]);
+            let description_len = parse_dyn_vec_len(&data[description_index..])?;
             // DYN_MIN_LEN: the min length of renderer
             if data.len() < description_index + description_len + DYN_MIN_LEN {
                 return Err(Error::ClassDataInvalid);
             }
-            let description =
-                data[description_index..(description_index + description_len)].to_vec();
+            let description = data
+                [(description_index + DYN_MIN_LEN)..(description_index + description_len)]
+                .to_vec();
 
             let renderer_index = FIXED_LEN_IN_CELL + name_len + description_len;
-            let renderer_len =
-                parse_dyn_vec_len(&data[renderer_index..(renderer_index + DYN_MIN_LEN)]);
+            let renderer_len = parse_dyn_vec_len(&data[renderer_index..])?;
 
             if data.len() < renderer_index + renderer_len {
                 return Err(Error::ClassDataInvalid);
             }
+            let renderer =
+                data[(renderer_index + DYN_MIN_LEN)..(renderer_index + renderer_len)].to_vec();
+
+            let ext_info = parse_ext_info(&data[(renderer_index + renderer_len)..])?;
 
             return Ok(Class {
                 version,
@@ -99,10 +113,13 @@ impl Class {
                 class_id: 0,
                 total,
                 issued,
+                burned,
                 configure,
                 owner: [0u8; 32],
                 name,
                 description,
+                renderer,
+                ext_info,
             });
         }
 
@@ -118,46 +135,51 @@ impl Class {
         let mut issuer_id = [0u8; 20];
         issuer_id.copy_from_slice(&data[1..21]);
 
-        let mut u32_buf = [0u8; 4];
-
-        u32_buf.copy_from_slice(&data[21..25]);
-        let class_id = u32::from_le_bytes(u32_buf);
+        let class_id = u32_from_slice(&data[21..25]);
 
         let total = u32_from_slice(&data[25..29]);
         let issued = u32_from_slice(&data[29..33]);
+        let burned = u32_from_slice(&data[33..37]);
 
         if total > 0 && issued > total {
             return Err(Error::ClassTotalSmallerThanIssued);
         }
+        if burned > issued {
+            return Err(Error::ClassBurnedInvalid);
+        }
 
-        let configure: u8 = data[33];
+        let configure: u8 = data[37];
 
         let mut owner = [0u8; 32];
 
-        owner.copy_from_slice(&data[34..FIXED_LEN]);
+        owner.copy_from_slice(&data[38..FIXED_LEN]);
 
-        let name_len = parse_dyn_vec_len(&data[FIXED_LEN..(FIXED_LEN + DYN_MIN_LEN)]);
+        let name_len = parse_dyn_vec_len(&data[FIXED_LEN..])?;
         // DYN_MIN_LEN: the min length of description
         if data.len() < FIXED_LEN + name_len + DYN_MIN_LEN {
             return Err(Error::ClassDataInvalid);
         }
-        let name = data[FIXED_LEN..(FIXED_LEN + name_len)].to_vec();
+        let name = data[(FIXED_LEN + DYN_MIN_LEN)..(FIXED_LEN + name_len)].to_vec();
 
         let description_index = FIXED_LEN + name_len;
-        let description_len =
-            parse_dyn_vec_len(&data[description_index..(description_index + DYN_MIN_LEN)]);
+        let description_len = parse_dyn_vec_len(&data[description_index..])?;
         // DYN_MIN_LEN: the min length of renderer
         if data.len() < description_index + description_len + DYN_MIN_LEN {
             return Err(Error::ClassDataInvalid);
         }
-        let description = data[description_index..(description_index + description_len)].to_vec();
+        let description =
+            data[(description_index + DYN_MIN_LEN)..(description_index + description_len)].to_vec();
 
         let renderer_index = FIXED_LEN + name_len + description_len;
-        let renderer_len = parse_dyn_vec_len(&data[renderer_index..(renderer_index + DYN_MIN_LEN)]);
+        let renderer_len = parse_dyn_vec_len(&data[renderer_index..])?;
 
         if data.len() < renderer_index + renderer_len {
             return Err(Error::ClassDataInvalid);
         }
+        let renderer =
+            data[(renderer_index + DYN_MIN_LEN)..(renderer_index + renderer_len)].to_vec();
+
+        let ext_info = parse_ext_info(&data[(renderer_index + renderer_len)..])?;
 
         return Ok(Class {
             version,
@@ -165,16 +187,24 @@ impl Class {
             class_id,
             total,
             issued,
+            burned,
             configure,
             owner,
             name,
             description,
+            renderer,
+            ext_info,
         });
     }
 
+    /// The class configure is copied to its nfts, so the same bit allows ext info
+    pub fn allow_ext_info(&self) -> bool {
+        self.configure & 0b0000_0100 == 0b0000_0000
+    }
+
     pub fn immutable_equal(&self, other: &Class) -> bool {
         self.issuer_id == other.issuer_id
-            && self.class_id == self.class_id
+            && self.class_id == other.class_id
             && self.total == other.total
             && self.configure == other.configure
             && self.name == other.name
@@ -182,11 +212,104 @@ impl Class {
     }
 }
 
+// extinfo_data: <size: u16> + <content>, the whole field can be missing.
+// It's the last field, and an empty one is left out, so the data decodes to a class
+// which encodes back to the same bytes.
+fn parse_ext_info(data: &[u8]) -> Result<Vec<u8>, Error> {
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+    let ext_info_len = parse_dyn_vec_len(data)?;
+    if data.len() != ext_info_len || ext_info_len == DYN_MIN_LEN {
+        return Err(Error::ClassDataInvalid);
+    }
+    Ok(data[DYN_MIN_LEN..ext_info_len].to_vec())
+}
+
 impl Class {
     fn is_zero(&self) -> bool {
         return self.issuer_id == [0u8; 20] && self.total == 0 && self.name.len() == 0;
     }
 
+    /// Encode the class the way from_data decodes it, the cell type byte is not included
+    pub fn to_data(&self, in_cell: bool) -> Result<Vec<u8>, Error> {
+        if !in_cell {
+            return self.to_leaf_data();
+        }
+        self.check_dyn_vec_len()?;
+        let mut data = Vec::with_capacity(
+            CLASS_DATA_MIN_LEN_IN_CELL
+                + self.name.len()
+                + self.description.len()
+                + self.renderer.len(),
+        );
+        data.extend_from_slice(&self.version.to_be_bytes());
+        data.extend_from_slice(&self.total.to_be_bytes());
+        data.extend_from_slice(&self.issued.to_be_bytes());
+        data.extend_from_slice(&self.burned.to_be_bytes());
+        data.extend_from_slice(&self.configure.to_be_bytes());
+        data.extend_from_slice(&(self.name.len() as u16).to_be_bytes());
+        data.extend_from_slice(&self.name);
+        data.extend_from_slice(&(self.description.len() as u16).to_be_bytes());
+        data.extend_from_slice(&self.description);
+        self.extend_renderer_and_ext_info(&mut data);
+        Ok(data)
+    }
+
+    pub fn to_leaf_data(&self) -> Result<Vec<u8>, Error> {
+        self.check_dyn_vec_len()?;
+        let mut leaf_data =
+            Vec::with_capacity(CLASS_DATA_MIN_LEN + self.name.len() + self.description.len());
+        leaf_data.extend_from_slice(&self.version.to_be_bytes());
+        leaf_data.extend_from_slice(&self.issuer_id);
+        leaf_data.extend_from_slice(&self.class_id.to_be_bytes());
+        leaf_data.extend_from_slice(&self.total.to_be_bytes());
+        leaf_data.extend_from_slice(&self.issued.to_be_bytes());
+        leaf_data.extend_from_slice(&self.burned.to_be_bytes());
+        leaf_data.extend_from_slice(&self.configure.to_be_bytes());
+        leaf_data.extend_from_slice(&self.owner);
+        leaf_data.extend_from_slice(&(self.name.len() as u16).to_be_bytes());
+        leaf_data.extend_from_slice(&self.name);
+        leaf_data.extend_from_slice(&(self.description.len() as u16).to_be_bytes());
+        leaf_data.extend_from_slice(&self.description);
+        self.extend_renderer_and_ext_info(&mut leaf_data);
+        Ok(leaf_data)
+    }
+
+    fn check_dyn_vec_len(&self) -> Result<(), Error> {
+        let fields: [&[u8]; 4] = [
+            &self.name,
+            &self.description,
+            &self.renderer,
+            &self.ext_info,
+        ];
+        if !fit_dyn_vec_len(&fields) {
+            return Err(Error::ClassDataInvalid);
+        }
+        Ok(())
+    }
+
+    // the ext info is left out when it's empty, as it can be missing
+    fn extend_renderer_and_ext_info(&self, data: &mut Vec<u8>) {
+        data.extend_from_slice(&(self.renderer.len() as u16).to_be_bytes());
+        data.extend_from_slice(&self.renderer);
+        if !self.ext_info.is_empty() {
+            data.extend_from_slice(&(self.ext_info.len() as u16).to_be_bytes());
+            data.extend_from_slice(&self.ext_info);
+        }
+    }
+
+    /// The key of a class leaf only depends on issuer_id and class_id
+    pub fn to_key(&self) -> H256 {
+        let mut buf = [0u8; 32];
+        let mut hasher = new_blake2b();
+        hasher.update(&CLASS_LEAF.to_be_bytes());
+        hasher.update(&self.issuer_id);
+        hasher.update(&self.class_id.to_be_bytes());
+        hasher.finalize(&mut buf);
+        buf.into()
+    }
+
     pub fn to_h256(&self) -> H256 {
         if self.is_zero() {
             return H256::zero();
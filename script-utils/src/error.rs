@@ -1,6 +1,8 @@
+#[cfg(feature = "no-std")]
 use ckb_std::error::SysError;
 
 /// Error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(i8)]
 pub enum Error {
     IndexOutOfBound = 1,
@@ -41,8 +43,32 @@ pub enum Error {
     LockedNFTCannotTransfer,
     LockedNFTCannotAddExtInfo,
     LockedNFTCannotDestroy,
+    SMTProofVerifyFailed,
+    SMTLeafValueInvalid = 40,
+    NFTOwnerNotMatch,
+    NFTKeyNotMatch,
+    TransactionLenNotMatch,
+    OwnerNotAuthorized,
+    SignatureInvalid = 45,
+    NFTNonceInvalid = 47,
+    SingleOwnerDataInvalid,
+    SingleOwnerLockNotSame,
+    SingleOwnerCellCannotDestroyed = 50,
+    TransactionNotSupported,
+    MultiOwnerDataInvalid,
+    MultiOwnerCellCannotDestroyed,
+    NFTSetDataInvalid,
+    NftTransactionsInvalid = 55,
+    TransactionDataInvalid,
+    ClassRendererCannotModify,
+    ClassExtInfoLenError,
+    ClassExtInfoCannotModify,
+    NFTSetCellsCountError = 60,
+    NFTSetCellCannotDestroyed,
+    ClassBurnedInvalid,
 }
 
+#[cfg(feature = "no-std")]
 impl From<SysError> for Error {
     fn from(err: SysError) -> Self {
         use SysError::*;
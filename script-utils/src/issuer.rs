@@ -1,4 +1,8 @@
-use crate::{error::Error, helpers::{DYN_MIN_LEN, parse_dyn_vec_len, u32_from_slice}};
+use crate::{
+    error::Error,
+    helpers::{fit_dyn_vec_len, parse_dyn_vec_len, u32_from_slice, DYN_MIN_LEN},
+};
+use alloc::vec::Vec;
 use core::result::Result;
 
 const FIXED_LEN: usize = 41;
@@ -11,13 +15,16 @@ pub const ISSUER_TYPE_ARGS_LEN: usize = 20;
 /// 1) version: u8
 /// 2) class_count: u32
 /// 3) set_count: u32
-/// 4) info: <size: u16> + <content>
+/// 4) smt_root: [u8; 32]
+/// 5) info: <size: u16> + <content>
+/// The field of 5) is the last one, no bytes can follow it.
 #[derive(Debug, Clone)]
 pub struct Issuer {
     pub version: u8,
     pub class_count: u32,
     pub set_count: u32,
     pub smt_root: [u8; 32],
+    pub info: Vec<u8>,
 }
 
 impl Issuer {
@@ -37,16 +44,33 @@ impl Issuer {
         let mut smt_root = [0u8; 32];
         smt_root.copy_from_slice(&data[9..FIXED_LEN]);
 
-        let info_len = parse_dyn_vec_len(&data[FIXED_LEN..(FIXED_LEN + DYN_MIN_LEN)]);
-        if data.len() < info_len + FIXED_LEN {
+        let info_len = parse_dyn_vec_len(&data[FIXED_LEN..])?;
+        if data.len() != info_len + FIXED_LEN {
             return Err(Error::IssuerDataInvalid);
         }
+        let info = data[(FIXED_LEN + DYN_MIN_LEN)..(FIXED_LEN + info_len)].to_vec();
 
         Ok(Issuer {
             version,
             class_count,
             set_count,
             smt_root,
+            info,
         })
     }
+
+    /// Encode the issuer the way from_data decodes it, the cell type byte is not included
+    pub fn to_data(&self) -> Result<Vec<u8>, Error> {
+        if !fit_dyn_vec_len(&[&self.info[..]]) {
+            return Err(Error::IssuerDataInvalid);
+        }
+        let mut data = Vec::with_capacity(ISSUER_DATA_MIN_LEN + self.info.len());
+        data.extend_from_slice(&self.version.to_be_bytes());
+        data.extend_from_slice(&self.class_count.to_be_bytes());
+        data.extend_from_slice(&self.set_count.to_be_bytes());
+        data.extend_from_slice(&self.smt_root);
+        data.extend_from_slice(&(self.info.len() as u16).to_be_bytes());
+        data.extend_from_slice(&self.info);
+        Ok(data)
+    }
 }
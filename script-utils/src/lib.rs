@@ -1,10 +1,17 @@
-#![no_std]
+#![cfg_attr(not(feature = "std"), no_std)]
 extern crate alloc;
 
 pub mod class;
 pub mod error;
 pub mod issuer;
 pub mod nft;
+pub mod nft_set;
 pub mod helpers;
 pub mod misc;
+pub mod multi_owner;
+pub mod signature;
+pub mod single_owner;
+pub mod transition;
+pub mod type_args;
+pub mod validator;
 
@@ -55,6 +55,10 @@ pub fn new_smt(pairs: Vec<(H256, NftValue)>) -> SMT {
 
 impl Value for NftValue {
     fn to_h256(&self) -> H256 {
+        // an empty leaf means the key is absent from the tree
+        if self.0.is_empty() {
+            return H256::zero();
+        }
         let mut buf = [0u8; 32];
         let mut hasher = new_blake2b();
         hasher.update(&self.0);
@@ -0,0 +1,33 @@
+use crate::error::Error;
+use core::result::Result;
+
+const MULTI_OWNER_DATA_LEN: usize = 33;
+
+/// Multi owner cell data structure
+/// Every nft leaf in the SMT carries its own owner.
+/// This structure contains the following information:
+/// 1) version: u8
+/// 2) smt_root: [u8; 32]
+#[derive(Debug, Clone)]
+pub struct MultiOwner {
+    pub version: u8,
+    pub smt_root: [u8; 32],
+}
+
+impl MultiOwner {
+    pub fn from_data(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != MULTI_OWNER_DATA_LEN {
+            return Err(Error::MultiOwnerDataInvalid);
+        }
+
+        let version: u8 = data[0];
+        if version != 0 {
+            return Err(Error::VersionInvalid);
+        }
+
+        let mut smt_root = [0u8; 32];
+        smt_root.copy_from_slice(&data[1..MULTI_OWNER_DATA_LEN]);
+
+        Ok(MultiOwner { version, smt_root })
+    }
+}
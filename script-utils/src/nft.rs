@@ -1,4 +1,8 @@
-use crate::{error::Error, helpers::u32_from_slice, misc::new_blake2b};
+use crate::{
+    error::Error,
+    helpers::{u32_from_slice, NFT_LEAF},
+    misc::new_blake2b,
+};
 use alloc::vec::Vec;
 use core::result::Result;
 use sparse_merkle_tree::{traits::Value, H256};
@@ -21,8 +25,8 @@ pub const NFT_TYPE_ARGS_LEN: usize = 28;
 /// 7) state: u8
 /// 8) nonce: u32
 /// 9) owner:[u8;32]
-/// 10) extinfo_data: <size: u16> + <vartext>
-/// The filed of 10) can be changed and it also can be missing and it will not be validated.
+/// 10) ext_info: the rest of the data
+/// The filed of 10) can be appended to if the nft allows it and it also can be missing.
 #[derive(Debug, Clone, Default)]
 pub struct Nft {
     pub version: u8,
@@ -34,6 +38,7 @@ pub struct Nft {
     pub state: u8,
     pub nonce: u32,
     pub owner: [u8; 32],
+    pub ext_info: Vec<u8>,
 }
 
 impl Nft {
@@ -65,6 +70,7 @@ impl Nft {
             state,
             nonce,
             owner: [0u8; 32],
+            ext_info: data[NFT_DATA_MIN_LEN_IN_CELL..].to_vec(),
         });
     }
 
@@ -106,6 +112,7 @@ impl Nft {
             state,
             nonce,
             owner,
+            ext_info: data[NFT_DATA_MIN_LEN..].to_vec(),
         });
     }
 
@@ -157,7 +164,7 @@ impl Nft {
 
     pub fn immutable_equal(&self, other: &Nft) -> bool {
         self.issuer_id == other.issuer_id
-            && self.class_id == self.class_id
+            && self.class_id == other.class_id
             && self.token_id == other.token_id
             && self.configure == other.configure
             && self.characteristic == other.characteristic
@@ -169,32 +176,44 @@ impl Nft {
         return self.issuer_id == [0u8; 20] && self.owner == [0u8; 32];
     }
 
-    fn to_leaf_data(&self) -> Vec<u8> {
-        let mut leaf_data = Vec::with_capacity(NFT_DATA_MIN_LEN);
-        leaf_data.extend_from_slice(&self.version.to_le_bytes());
+    /// Encode the nft the way from_data decodes it, the cell type byte is not included
+    pub fn to_data(&self, in_cell: bool) -> Vec<u8> {
+        if !in_cell {
+            return self.to_leaf_data();
+        }
+        let mut data = Vec::with_capacity(NFT_DATA_MIN_LEN_IN_CELL + self.ext_info.len());
+        data.extend_from_slice(&self.version.to_be_bytes());
+        data.extend_from_slice(&self.characteristic);
+        data.extend_from_slice(&self.configure.to_be_bytes());
+        data.extend_from_slice(&self.state.to_be_bytes());
+        data.extend_from_slice(&self.nonce.to_be_bytes());
+        data.extend_from_slice(&self.ext_info);
+        data
+    }
+
+    pub fn to_leaf_data(&self) -> Vec<u8> {
+        let mut leaf_data = Vec::with_capacity(NFT_DATA_MIN_LEN + self.ext_info.len());
+        leaf_data.extend_from_slice(&self.version.to_be_bytes());
         leaf_data.extend_from_slice(&self.issuer_id);
-        leaf_data.extend_from_slice(&self.class_id.to_le_bytes());
-        leaf_data.extend_from_slice(&self.token_id.to_le_bytes());
+        leaf_data.extend_from_slice(&self.class_id.to_be_bytes());
+        leaf_data.extend_from_slice(&self.token_id.to_be_bytes());
         leaf_data.extend_from_slice(&self.characteristic);
-        leaf_data.extend_from_slice(&self.configure.to_le_bytes());
-        leaf_data.extend_from_slice(&self.state.to_le_bytes());
-        leaf_data.extend_from_slice(&self.nonce.to_le_bytes());
+        leaf_data.extend_from_slice(&self.configure.to_be_bytes());
+        leaf_data.extend_from_slice(&self.state.to_be_bytes());
+        leaf_data.extend_from_slice(&self.nonce.to_be_bytes());
         leaf_data.extend_from_slice(&self.owner);
+        leaf_data.extend_from_slice(&self.ext_info);
         leaf_data
     }
 
+    /// The key of a nft leaf only depends on issuer_id, class_id and token_id
     pub fn to_key(&self) -> H256 {
-        if self.is_zero() {
-            return H256::zero();
-        }
         let mut buf = [0u8; 32];
         let mut hasher = new_blake2b();
-        hasher.update(&self.version.to_le_bytes());
+        hasher.update(&NFT_LEAF.to_be_bytes());
         hasher.update(&self.issuer_id);
-        hasher.update(&self.class_id.to_le_bytes());
-        hasher.update(&self.token_id.to_le_bytes());
-        hasher.update(&self.characteristic);
-        hasher.update(&self.configure.to_le_bytes());
+        hasher.update(&self.class_id.to_be_bytes());
+        hasher.update(&self.token_id.to_be_bytes());
         hasher.finalize(&mut buf);
         buf.into()
     }
@@ -0,0 +1,192 @@
+use crate::{
+    error::Error,
+    helpers::{fit_dyn_vec_len, parse_dyn_vec_len, u32_from_slice, DYN_MIN_LEN, NFT_SET_LEAF},
+    misc::new_blake2b,
+};
+use alloc::vec::Vec;
+use core::result::Result;
+use sparse_merkle_tree::H256;
+
+const FIXED_LEN: usize = 61;
+// FIXED_LEN + DYN_MIN_LEN * 2
+const NFT_SET_DATA_MIN_LEN: usize = 65;
+
+const FIXED_LEN_IN_CELL: usize = 5;
+// in cell, owner will be in lock_script, issuer_id and set_id will be in type_args
+// FIXED_LEN_IN_CELL + DYN_MIN_LEN * 2
+const NFT_SET_DATA_MIN_LEN_IN_CELL: usize = 9;
+
+// issuer_id + NFT_SET_CELL + set_id
+pub const NFT_SET_TYPE_ARGS_LEN: usize = 25;
+
+/// NFT set leaf data structure
+/// A set groups nfts of the same issuer across classes, e.g. bundles or editions.
+/// This structure contains the following information:
+/// 1) version: u8
+/// 2) issuer_id: [u8; 20],
+/// 3) set_id: u32,
+/// 4) owner: [u8; 32],
+/// 5) member_count: u32
+/// 6) name: <size: u16> + <content>
+/// 7) description: <size: u16> + <content>
+/// Every member has its own leaf in the SMT whose value is the key of the member nft,
+/// so the membership of an nft can be proved by the merkle proof of the member leaf.
+/// The set leaf can be extracted into a nft set cell holding 1), 5), 6) and 7), the member
+/// leaves stay in the SMT.
+#[derive(Debug, Clone, Default)]
+pub struct NftSet {
+    pub version: u8,
+    pub issuer_id: [u8; 20],
+    pub set_id: u32,
+    pub owner: [u8; 32],
+    pub member_count: u32,
+    pub name: Vec<u8>,
+    pub description: Vec<u8>,
+}
+
+impl NftSet {
+    pub fn from_data_cell(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < NFT_SET_DATA_MIN_LEN_IN_CELL {
+            return Err(Error::NFTSetDataInvalid);
+        }
+
+        let version: u8 = data[0];
+        if version != 0 {
+            return Err(Error::VersionInvalid);
+        }
+
+        let member_count = u32_from_slice(&data[1..FIXED_LEN_IN_CELL]);
+        let (name, description) = parse_name_and_description(&data[FIXED_LEN_IN_CELL..])?;
+
+        Ok(NftSet {
+            version,
+            issuer_id: [0u8; 20],
+            set_id: 0,
+            owner: [0u8; 32],
+            member_count,
+            name,
+            description,
+        })
+    }
+
+    pub fn from_data_leaf(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < NFT_SET_DATA_MIN_LEN {
+            return Err(Error::NFTSetDataInvalid);
+        }
+
+        let version: u8 = data[0];
+        if version != 0 {
+            return Err(Error::VersionInvalid);
+        }
+
+        let mut issuer_id = [0u8; 20];
+        issuer_id.copy_from_slice(&data[1..21]);
+
+        let set_id = u32_from_slice(&data[21..25]);
+
+        let mut owner = [0u8; 32];
+        owner.copy_from_slice(&data[25..57]);
+
+        let member_count = u32_from_slice(&data[57..FIXED_LEN]);
+        let (name, description) = parse_name_and_description(&data[FIXED_LEN..])?;
+
+        Ok(NftSet {
+            version,
+            issuer_id,
+            set_id,
+            owner,
+            member_count,
+            name,
+            description,
+        })
+    }
+
+    pub fn from_data(data: &[u8], in_cell: bool) -> Result<Self, Error> {
+        if in_cell {
+            Self::from_data_cell(data)
+        } else {
+            Self::from_data_leaf(data)
+        }
+    }
+
+    /// Encode the set the way from_data decodes it, the cell type byte is not included
+    pub fn to_data(&self, in_cell: bool) -> Result<Vec<u8>, Error> {
+        if !in_cell {
+            return self.to_leaf_data();
+        }
+        self.check_dyn_vec_len()?;
+        let mut data = Vec::with_capacity(
+            NFT_SET_DATA_MIN_LEN_IN_CELL + self.name.len() + self.description.len(),
+        );
+        data.extend_from_slice(&self.version.to_be_bytes());
+        data.extend_from_slice(&self.member_count.to_be_bytes());
+        data.extend_from_slice(&(self.name.len() as u16).to_be_bytes());
+        data.extend_from_slice(&self.name);
+        data.extend_from_slice(&(self.description.len() as u16).to_be_bytes());
+        data.extend_from_slice(&self.description);
+        Ok(data)
+    }
+
+    pub fn to_leaf_data(&self) -> Result<Vec<u8>, Error> {
+        self.check_dyn_vec_len()?;
+        let mut leaf_data =
+            Vec::with_capacity(NFT_SET_DATA_MIN_LEN + self.name.len() + self.description.len());
+        leaf_data.extend_from_slice(&self.version.to_be_bytes());
+        leaf_data.extend_from_slice(&self.issuer_id);
+        leaf_data.extend_from_slice(&self.set_id.to_be_bytes());
+        leaf_data.extend_from_slice(&self.owner);
+        leaf_data.extend_from_slice(&self.member_count.to_be_bytes());
+        leaf_data.extend_from_slice(&(self.name.len() as u16).to_be_bytes());
+        leaf_data.extend_from_slice(&self.name);
+        leaf_data.extend_from_slice(&(self.description.len() as u16).to_be_bytes());
+        leaf_data.extend_from_slice(&self.description);
+        Ok(leaf_data)
+    }
+
+    fn check_dyn_vec_len(&self) -> Result<(), Error> {
+        if !fit_dyn_vec_len(&[&self.name[..], &self.description[..]]) {
+            return Err(Error::NFTSetDataInvalid);
+        }
+        Ok(())
+    }
+
+    /// The key of a set leaf only depends on issuer_id and set_id
+    pub fn to_key(&self) -> H256 {
+        let mut buf = [0u8; 32];
+        let mut hasher = new_blake2b();
+        hasher.update(&NFT_SET_LEAF.to_be_bytes());
+        hasher.update(&self.issuer_id);
+        hasher.update(&self.set_id.to_be_bytes());
+        hasher.finalize(&mut buf);
+        buf.into()
+    }
+
+    /// The key of the member leaf of an nft, the value of the leaf is the nft key
+    pub fn to_member_key(&self, nft_key: &[u8; 32]) -> H256 {
+        let mut buf = [0u8; 32];
+        let mut hasher = new_blake2b();
+        hasher.update(&NFT_SET_LEAF.to_be_bytes());
+        hasher.update(&self.issuer_id);
+        hasher.update(&self.set_id.to_be_bytes());
+        hasher.update(nft_key);
+        hasher.finalize(&mut buf);
+        buf.into()
+    }
+}
+
+// name: <size: u16> + <content>, description: <size: u16> + <content>
+fn parse_name_and_description(data: &[u8]) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    let name_len = parse_dyn_vec_len(data)?;
+    // DYN_MIN_LEN: the min length of description
+    if data.len() < name_len + DYN_MIN_LEN {
+        return Err(Error::NFTSetDataInvalid);
+    }
+    let name = data[DYN_MIN_LEN..name_len].to_vec();
+
+    let description_len = parse_dyn_vec_len(&data[name_len..])?;
+    if data.len() < name_len + description_len {
+        return Err(Error::NFTSetDataInvalid);
+    }
+    let description = data[(name_len + DYN_MIN_LEN)..(name_len + description_len)].to_vec();
+    Ok((name, description))
+}
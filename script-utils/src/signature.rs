@@ -0,0 +1,89 @@
+use crate::{error::Error, misc::new_blake2b};
+use alloc::vec;
+use secp256k1::{
+    recovery::{RecoverableSignature, RecoveryId},
+    Message, Secp256k1,
+};
+
+/// compact signature(64 bytes) + recovery id(1 byte)
+pub const SIGNATURE_SIZE: usize = 65;
+
+pub const BLAKE160_SIZE: usize = 20;
+
+/// code_hash of the default secp256k1_blake160_sighash_all lock, referenced by type
+pub const SECP256K1_BLAKE160_CODE_HASH: [u8; 32] = [
+    0x9b, 0xd7, 0xe0, 0x6f, 0x3e, 0xcf, 0x4b, 0xe0, 0xf2, 0xfc, 0xd2, 0x18, 0x8b, 0x23, 0xf1, 0xb9,
+    0xfc, 0xc8, 0x8e, 0x5d, 0x4b, 0x65, 0xa8, 0x63, 0x7b, 0x17, 0x72, 0x3b, 0xbd, 0xa3, 0xcc, 0xe8,
+];
+const HASH_TYPE_TYPE: u8 = 1;
+
+/// Separates the messages signed for compact nft transactions from any other message
+pub const SIGNATURE_DOMAIN: &[u8] = b"compact-mNFT";
+
+/// The message signed by the owner of the leaves touched by a NftTransaction:
+/// blake2b(SIGNATURE_DOMAIN || tree_id || typ || transaction),
+/// tree_id is the type args of the cell holding the SMT, e.g. the issuer_id of an issuer cell.
+/// No chain id is needed: the tree_id comes from the type id of the cell, which hashes the
+/// first input of the transaction creating it, so a tree_id only exists on the chain the cell
+/// is created on and a signature can't be replayed on another chain.
+pub fn signing_message(tree_id: &[u8], typ: u8, transaction: &[u8]) -> [u8; 32] {
+    let mut message = [0u8; 32];
+    let mut hasher = new_blake2b();
+    hasher.update(SIGNATURE_DOMAIN);
+    hasher.update(tree_id);
+    hasher.update(&[typ]);
+    hasher.update(transaction);
+    hasher.finalize(&mut message);
+    message
+}
+
+/// The lock hash of the secp256k1_blake160_sighash_all lock with the blake160 as args
+pub fn blake160_lock_hash(blake160: &[u8; BLAKE160_SIZE]) -> [u8; 32] {
+    // molecule serialization of Script { code_hash, hash_type, args }
+    const HEADER_SIZE: u32 = 16;
+    const ARGS_OFFSET: u32 = HEADER_SIZE + 32 + 1;
+    const TOTAL_SIZE: u32 = ARGS_OFFSET + 4 + BLAKE160_SIZE as u32;
+
+    let mut hash = [0u8; 32];
+    let mut hasher = new_blake2b();
+    hasher.update(&TOTAL_SIZE.to_le_bytes());
+    hasher.update(&HEADER_SIZE.to_le_bytes());
+    hasher.update(&(HEADER_SIZE + 32).to_le_bytes());
+    hasher.update(&ARGS_OFFSET.to_le_bytes());
+    hasher.update(&SECP256K1_BLAKE160_CODE_HASH);
+    hasher.update(&[HASH_TYPE_TYPE]);
+    hasher.update(&(BLAKE160_SIZE as u32).to_le_bytes());
+    hasher.update(blake160);
+    hasher.finalize(&mut hash);
+    hash
+}
+
+/// Recover the signer of the message and return the blake160 of its compressed pubkey
+pub fn recover_blake160(
+    message: &[u8; 32],
+    signature: &[u8],
+) -> Result<[u8; BLAKE160_SIZE], Error> {
+    if signature.len() != SIGNATURE_SIZE {
+        return Err(Error::SignatureInvalid);
+    }
+    let recovery_id =
+        RecoveryId::from_i32(signature[64] as i32).map_err(|_| Error::SignatureInvalid)?;
+    let signature = RecoverableSignature::from_compact(&signature[..64], recovery_id)
+        .map_err(|_| Error::SignatureInvalid)?;
+    let message = Message::from_slice(message).map_err(|_| Error::SignatureInvalid)?;
+
+    let mut buf = vec![0u8; Secp256k1::preallocate_verification_size()];
+    let secp =
+        Secp256k1::preallocated_verification_only(&mut buf).map_err(|_| Error::SignatureInvalid)?;
+    let pubkey = secp
+        .recover(&message, &signature)
+        .map_err(|_| Error::SignatureInvalid)?;
+
+    let mut pubkey_hash = [0u8; 32];
+    let mut hasher = new_blake2b();
+    hasher.update(&pubkey.serialize());
+    hasher.finalize(&mut pubkey_hash);
+    let mut blake160 = [0u8; BLAKE160_SIZE];
+    blake160.copy_from_slice(&pubkey_hash[..BLAKE160_SIZE]);
+    Ok(blake160)
+}
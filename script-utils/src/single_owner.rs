@@ -0,0 +1,33 @@
+use crate::error::Error;
+use core::result::Result;
+
+const SINGLE_OWNER_DATA_LEN: usize = 33;
+
+/// Single owner cell data structure
+/// All the nfts in the SMT belong to the lock of the cell.
+/// This structure contains the following information:
+/// 1) version: u8
+/// 2) smt_root: [u8; 32]
+#[derive(Debug, Clone)]
+pub struct SingleOwner {
+    pub version: u8,
+    pub smt_root: [u8; 32],
+}
+
+impl SingleOwner {
+    pub fn from_data(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != SINGLE_OWNER_DATA_LEN {
+            return Err(Error::SingleOwnerDataInvalid);
+        }
+
+        let version: u8 = data[0];
+        if version != 0 {
+            return Err(Error::VersionInvalid);
+        }
+
+        let mut smt_root = [0u8; 32];
+        smt_root.copy_from_slice(&data[1..SINGLE_OWNER_DATA_LEN]);
+
+        Ok(SingleOwner { version, smt_root })
+    }
+}
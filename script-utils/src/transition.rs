@@ -0,0 +1,479 @@
+use crate::{
+    class::Class,
+    error::Error,
+    helpers::{CLASS_ITEM, NFT_ITEM, SET_ITEM},
+    nft::Nft,
+    nft_set::NftSet,
+    signature::{blake160_lock_hash, recover_blake160, signing_message},
+    validator::{
+        validate_immutable_nft_fields, validate_nft_claim, validate_nft_ext_info,
+        validate_nft_lock, validate_nft_transferable,
+    },
+};
+use alloc::vec::Vec;
+use core::result::Result;
+use mol::{
+    ckb_types::{
+        packed::{Byte, Byte32},
+        prelude::{Entity, Unpack},
+    },
+    NftTransaction, RawCreateSetTransaction, RawDistributeTransaction, RawExtractTransaction,
+    RawInsertTransaction, RawIssueTransaction, RawTransferTransaction, RawUpdateTransaction,
+};
+use sparse_merkle_tree::H256;
+
+// The leaf transitions of the NftTransactions are shared by the contract, which checks
+// them against the SMT proof, and the aggregator, which builds the new tree from them.
+// The cells created and consumed by extract and insert transactions are checked by the
+// contract alone.
+
+/// The leaf transitions of a batch of NftTransactions
+pub trait LeafUpdates {
+    type Error: From<Error>;
+
+    /// Replace the old leaf of the key by the new one, the old leaf must be the one left
+    /// by the transactions applied before. An empty leaf means the key is absent.
+    fn update(&mut self, key: H256, old_leaf: &[u8], new_leaf: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// The owners of the leaves touched by a NftTransaction authorize it
+pub trait Authorize {
+    fn check(&self, owner: &[u8; 32]) -> Result<(), Error>;
+}
+
+/// A leaf moved between the tree and a cell by extract and insert transactions
+#[derive(Debug, Clone)]
+pub enum Item {
+    Nft(Nft),
+    Class(Class),
+    Set(NftSet),
+}
+
+impl Item {
+    pub fn key(&self) -> H256 {
+        match self {
+            Item::Nft(nft) => nft.to_key(),
+            Item::Class(class) => class.to_key(),
+            Item::Set(set) => set.to_key(),
+        }
+    }
+
+    pub fn owner(&self) -> [u8; 32] {
+        match self {
+            Item::Nft(nft) => nft.owner,
+            Item::Class(class) => class.owner,
+            Item::Set(set) => set.owner,
+        }
+    }
+
+    pub fn to_leaf_data(&self) -> Result<Vec<u8>, Error> {
+        match self {
+            Item::Nft(nft) => Ok(nft.to_leaf_data()),
+            Item::Class(class) => class.to_leaf_data(),
+            Item::Set(set) => set.to_leaf_data(),
+        }
+    }
+
+    fn check_issuer_id(&self, issuer_id: Option<&[u8; 20]>) -> Result<(), Error> {
+        match self {
+            Item::Nft(nft) if !check_issuer_id(issuer_id, &nft.issuer_id) => {
+                Err(Error::NFTDataInvalid)
+            }
+            Item::Class(class) if !check_issuer_id(issuer_id, &class.issuer_id) => {
+                Err(Error::ClassDataInvalid)
+            }
+            Item::Set(set) if !check_issuer_id(issuer_id, &set.issuer_id) => {
+                Err(Error::NFTSetDataInvalid)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// decode the raw transaction of the NftTransaction according to its typ
+pub fn parse_raw_transaction<T: Entity>(tx: &NftTransaction) -> Result<T, Error> {
+    T::from_compatible_slice(&tx.transaction().raw_data())
+        .map_err(|_| Error::TransactionDataInvalid)
+}
+
+/// The lock hash of the signer of the NftTransaction, if it's signed
+pub fn recover_signer(tree_id: &[u8], tx: &NftTransaction) -> Result<Option<[u8; 32]>, Error> {
+    let signature = tx.signature().raw_data();
+    if signature.is_empty() {
+        return Ok(None);
+    }
+    let message = signing_message(
+        tree_id,
+        <Byte as Into<u8>>::into(tx.typ()),
+        &tx.transaction().raw_data(),
+    );
+    let blake160 = recover_blake160(&message, &signature)?;
+    Ok(Some(blake160_lock_hash(&blake160)))
+}
+
+fn parse_byte32(slice: &[u8]) -> [u8; 32] {
+    let mut buf = [0u8; 32];
+    buf.copy_from_slice(slice);
+    buf
+}
+
+// leaves of an issuer tree must belong to the issuer
+fn check_issuer_id(issuer_id: Option<&[u8; 20]>, leaf_issuer_id: &[u8; 20]) -> bool {
+    issuer_id.map_or(true, |issuer_id| issuer_id == leaf_issuer_id)
+}
+
+// class and set leaves only live in issuer trees
+fn check_item_typ(issuer_id: Option<&[u8; 20]>, typ: u8) -> Result<(), Error> {
+    match typ {
+        NFT_ITEM => Ok(()),
+        CLASS_ITEM | SET_ITEM if issuer_id.is_some() => Ok(()),
+        _ => Err(Error::NFTDataInvalid),
+    }
+}
+
+/// Decode the leaf of the typ, the leaf must belong to the issuer if it's given
+pub fn parse_item(typ: u8, issuer_id: Option<&[u8; 20]>, leaf: &[u8]) -> Result<Item, Error> {
+    let item = match typ {
+        NFT_ITEM => Item::Nft(Nft::from_data_leaf(leaf)?),
+        CLASS_ITEM => Item::Class(Class::from_data(leaf, false)?),
+        SET_ITEM => Item::Set(NftSet::from_data_leaf(leaf)?),
+        _ => return Err(Error::NFTDataInvalid),
+    };
+    item.check_issuer_id(issuer_id)?;
+    Ok(item)
+}
+
+/// Create a new class leaf which takes the class_id, the key must not exist in the
+/// issuer tree before
+pub fn issue<U: LeafUpdates>(
+    issuer_id: &[u8; 20],
+    class_id: u32,
+    issue_tx: RawIssueTransaction,
+    updates: &mut U,
+) -> Result<(), U::Error> {
+    let name = issue_tx.name().raw_data();
+    let description = issue_tx.description().raw_data();
+
+    let class = Class {
+        version: 0,
+        issuer_id: *issuer_id,
+        class_id,
+        total: issue_tx.total().unpack(),
+        issued: 0,
+        burned: 0,
+        configure: issue_tx.configure().into(),
+        owner: parse_byte32(issue_tx.to().as_slice()),
+        name: name.to_vec(),
+        description: description.to_vec(),
+        renderer: Vec::new(),
+        ext_info: Vec::new(),
+    };
+    updates.update(class.to_key(), &[], &class.to_leaf_data()?)
+}
+
+/// Mint nft leaves from a class leaf, token_ids continue from the old issued
+pub fn distribute<U: LeafUpdates, A: Authorize>(
+    issuer_id: &[u8; 20],
+    auth: &A,
+    distribute_tx: RawDistributeTransaction,
+    updates: &mut U,
+) -> Result<(), U::Error> {
+    let class_old_value = distribute_tx.class_old_value().raw_data();
+    let old_class = Class::from_data(&class_old_value, false)?;
+    if &old_class.issuer_id != issuer_id {
+        return Err(Error::ClassDataInvalid.into());
+    }
+    // only the class owner can distribute
+    auth.check(&old_class.owner)?;
+
+    let targets = distribute_tx.to();
+    let mut new_class = old_class.clone();
+    new_class.issued = old_class
+        .issued
+        .checked_add(targets.len() as u32)
+        .ok_or(Error::ClassIssuedInvalid)?;
+    if new_class.total > 0 && new_class.issued > new_class.total {
+        return Err(Error::ClassTotalSmallerThanIssued.into());
+    }
+    updates.update(
+        old_class.to_key(),
+        &class_old_value,
+        &new_class.to_leaf_data()?,
+    )?;
+
+    for (index, target) in targets.into_iter().enumerate() {
+        let nft = Nft {
+            version: 0,
+            issuer_id: *issuer_id,
+            class_id: old_class.class_id,
+            token_id: old_class.issued + index as u32,
+            characteristic: [0u8; 8],
+            configure: old_class.configure,
+            state: 0,
+            nonce: 0,
+            owner: parse_byte32(target.as_slice()),
+            ext_info: Vec::new(),
+        };
+        updates.update(nft.to_key(), &[], &nft.to_leaf_data())?;
+    }
+
+    Ok(())
+}
+
+/// Hand nft or class leaves to other owners, the leaves must belong to the issuer if
+/// it's given
+pub fn transfer<U: LeafUpdates, A: Authorize>(
+    issuer_id: Option<&[u8; 20]>,
+    auth: &A,
+    transfer_tx: RawTransferTransaction,
+    updates: &mut U,
+) -> Result<(), U::Error> {
+    let nft_ids = transfer_tx.nft_ids();
+    let nft_old_values = transfer_tx.nft_old_values();
+    let targets = transfer_tx.to();
+    if nft_ids.len() != nft_old_values.len() || nft_ids.len() != targets.len() {
+        return Err(Error::TransactionLenNotMatch.into());
+    }
+
+    let typ: u8 = transfer_tx.typ().into();
+    let items = nft_ids
+        .into_iter()
+        .zip(nft_old_values.into_iter())
+        .zip(targets.into_iter());
+    for ((nft_id, old_value), target) in items {
+        let old_leaf = old_value.raw_data();
+        let to = parse_byte32(target.as_slice());
+        match typ {
+            NFT_ITEM => transfer_nft(issuer_id, auth, &nft_id, &old_leaf, to, updates)?,
+            CLASS_ITEM => transfer_class(issuer_id, auth, &nft_id, &old_leaf, to, updates)?,
+            _ => return Err(Error::NFTDataInvalid.into()),
+        }
+    }
+
+    Ok(())
+}
+
+fn transfer_nft<U: LeafUpdates, A: Authorize>(
+    issuer_id: Option<&[u8; 20]>,
+    auth: &A,
+    nft_id: &Byte32,
+    old_leaf: &[u8],
+    to: [u8; 32],
+    updates: &mut U,
+) -> Result<(), U::Error> {
+    let old_nft = Nft::from_data_leaf(old_leaf)?;
+    if !check_issuer_id(issuer_id, &old_nft.issuer_id) {
+        return Err(Error::NFTDataInvalid.into());
+    }
+    let key = old_nft.to_key();
+    if key.as_slice() != nft_id.as_slice() {
+        return Err(Error::NFTKeyNotMatch.into());
+    }
+    auth.check(&old_nft.owner)?;
+    validate_nft_transferable(&old_nft)?;
+
+    let mut new_nft = old_nft.clone();
+    new_nft.owner = to;
+    new_nft.nonce = old_nft.nonce.checked_add(1).ok_or(Error::NFTDataInvalid)?;
+
+    // ext info of the leaf is kept as it is
+    updates.update(key, old_leaf, &new_nft.to_leaf_data())
+}
+
+fn transfer_class<U: LeafUpdates, A: Authorize>(
+    issuer_id: Option<&[u8; 20]>,
+    auth: &A,
+    class_id: &Byte32,
+    old_leaf: &[u8],
+    to: [u8; 32],
+    updates: &mut U,
+) -> Result<(), U::Error> {
+    let old_class = Class::from_data(old_leaf, false)?;
+    if !check_issuer_id(issuer_id, &old_class.issuer_id) {
+        return Err(Error::ClassDataInvalid.into());
+    }
+    let key = old_class.to_key();
+    if key.as_slice() != class_id.as_slice() {
+        return Err(Error::NFTKeyNotMatch.into());
+    }
+    auth.check(&old_class.owner)?;
+
+    let mut new_class = old_class.clone();
+    new_class.owner = to;
+    updates.update(key, old_leaf, &new_class.to_leaf_data()?)
+}
+
+/// Update the state and ext info of nft leaves by the rules of nft cells
+pub fn update_nfts<U: LeafUpdates, A: Authorize>(
+    issuer_id: Option<&[u8; 20]>,
+    auth: &A,
+    update_tx: RawUpdateTransaction,
+    updates: &mut U,
+) -> Result<(), U::Error> {
+    let nft_ids = update_tx.nft_ids();
+    let nft_old_values = update_tx.nft_old_values();
+    let states = update_tx.state().raw_data();
+    let ext_datas = update_tx.ext_datas();
+    if nft_ids.len() != nft_old_values.len()
+        || nft_ids.len() != states.len()
+        || nft_ids.len() != ext_datas.len()
+    {
+        return Err(Error::TransactionLenNotMatch.into());
+    }
+
+    let typ: u8 = update_tx.typ().into();
+    if typ != NFT_ITEM {
+        return Err(Error::NFTDataInvalid.into());
+    }
+
+    let items = nft_ids
+        .into_iter()
+        .zip(nft_old_values.into_iter())
+        .zip(states.iter())
+        .zip(ext_datas.into_iter());
+    for (((nft_id, old_value), state), ext_data) in items {
+        let old_leaf = old_value.raw_data();
+        let old_nft = Nft::from_data_leaf(&old_leaf)?;
+        if !check_issuer_id(issuer_id, &old_nft.issuer_id) {
+            return Err(Error::NFTDataInvalid.into());
+        }
+        let key = old_nft.to_key();
+        if key.as_slice() != nft_id.as_slice() {
+            return Err(Error::NFTKeyNotMatch.into());
+        }
+        auth.check(&old_nft.owner)?;
+
+        // only state and ext info can be updated
+        let mut new_nft = old_nft.clone();
+        new_nft.state = *state;
+        new_nft.ext_info = ext_data.raw_data().to_vec();
+        let nfts = (old_nft, new_nft);
+        validate_immutable_nft_fields(&nfts)?;
+        validate_nft_claim(&nfts)?;
+        validate_nft_lock(&nfts)?;
+        validate_nft_ext_info(&nfts)?;
+        updates.update(key, &old_leaf, &nfts.1.to_leaf_data())?;
+    }
+
+    Ok(())
+}
+
+/// Remove nft, class or set leaves from the tree, every one of them becomes a cell of
+/// the L1 transaction which is checked by check_item. Returns the number of extracted leaves.
+pub fn extract<U, A, F>(
+    issuer_id: Option<&[u8; 20]>,
+    auth: &A,
+    extract_tx: RawExtractTransaction,
+    updates: &mut U,
+    mut check_item: F,
+) -> Result<usize, U::Error>
+where
+    U: LeafUpdates,
+    A: Authorize,
+    F: FnMut(&Item) -> Result<(), U::Error>,
+{
+    let nft_ids = extract_tx.nft_ids();
+    let nft_old_values = extract_tx.nft_old_values();
+    if nft_ids.len() != nft_old_values.len() {
+        return Err(Error::TransactionLenNotMatch.into());
+    }
+
+    let typ: u8 = extract_tx.typ().into();
+    check_item_typ(issuer_id, typ)?;
+
+    let count = nft_ids.len();
+    for (nft_id, old_value) in nft_ids.into_iter().zip(nft_old_values.into_iter()) {
+        let old_leaf = old_value.raw_data();
+        let item = parse_item(typ, issuer_id, &old_leaf)?;
+        let key = item.key();
+        if key.as_slice() != nft_id.as_slice() {
+            return Err(Error::NFTKeyNotMatch.into());
+        }
+        auth.check(&item.owner())?;
+
+        check_item(&item)?;
+        updates.update(key, &old_leaf, &[])?;
+    }
+
+    Ok(count)
+}
+
+/// Put the leaves of consumed nft, class or set cells back into the tree, the item of a key
+/// is loaded by load_item and the lock of its cell has approved the insertion.
+/// The nft leaves belong to the owner if it's given, otherwise to the lock of the cells.
+/// Returns the number of inserted leaves.
+pub fn insert<U, F>(
+    issuer_id: Option<&[u8; 20]>,
+    owner: Option<&[u8; 32]>,
+    insert_tx: RawInsertTransaction,
+    updates: &mut U,
+    mut load_item: F,
+) -> Result<usize, U::Error>
+where
+    U: LeafUpdates,
+    F: FnMut(u8, &Byte32) -> Result<Item, U::Error>,
+{
+    let typ: u8 = insert_tx.typ().into();
+    check_item_typ(issuer_id, typ)?;
+
+    let nft_ids = insert_tx.nft_ids();
+    let count = nft_ids.len();
+    for nft_id in nft_ids.into_iter() {
+        let mut item = load_item(typ, &nft_id)?;
+        if let (Item::Nft(nft), Some(owner)) = (&mut item, owner) {
+            nft.owner = *owner;
+        }
+        item.check_issuer_id(issuer_id)?;
+        updates.update(item.key(), &[], &item.to_leaf_data()?)?;
+    }
+
+    Ok(count)
+}
+
+/// Create a new set leaf which takes the set_id and a member leaf for every member nft,
+/// the keys must not exist in the issuer tree before. Every member must be a nft leaf of
+/// the issuer, its old value is kept as it is so the proof covers it.
+pub fn create_set<U: LeafUpdates>(
+    issuer_id: &[u8; 20],
+    set_id: u32,
+    create_set_tx: RawCreateSetTransaction,
+    updates: &mut U,
+) -> Result<(), U::Error> {
+    let name = create_set_tx.name().raw_data();
+    let description = create_set_tx.description().raw_data();
+
+    let members = create_set_tx.members();
+    let member_old_values = create_set_tx.member_old_values();
+    if members.len() != member_old_values.len() {
+        return Err(Error::TransactionLenNotMatch.into());
+    }
+    let set = NftSet {
+        version: 0,
+        issuer_id: *issuer_id,
+        set_id,
+        owner: parse_byte32(create_set_tx.to().as_slice()),
+        member_count: members.len() as u32,
+        name: name.to_vec(),
+        description: description.to_vec(),
+    };
+    updates.update(set.to_key(), &[], &set.to_leaf_data()?)?;
+
+    // a member can't be added twice, its member leaf would already exist
+    for (member, old_value) in members.into_iter().zip(member_old_values.into_iter()) {
+        let old_leaf = old_value.raw_data();
+        let nft = Nft::from_data_leaf(&old_leaf)?;
+        if &nft.issuer_id != issuer_id {
+            return Err(Error::NFTDataInvalid.into());
+        }
+        let key = nft.to_key();
+        if key.as_slice() != member.as_slice() {
+            return Err(Error::NFTKeyNotMatch.into());
+        }
+        updates.update(key, &old_leaf, &old_leaf)?;
+
+        let nft_key = parse_byte32(member.as_slice());
+        updates.update(set.to_member_key(&nft_key), &[], &nft_key)?;
+    }
+
+    Ok(())
+}
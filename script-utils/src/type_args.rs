@@ -0,0 +1,170 @@
+use crate::{
+    class::{Class, CLASS_TYPE_ARGS_LEN},
+    error::Error,
+    helpers::{u32_from_slice, NFT_SET_CELL},
+    issuer::ISSUER_TYPE_ARGS_LEN,
+    nft::{Nft, NFT_TYPE_ARGS_LEN},
+    nft_set::{NftSet, NFT_SET_TYPE_ARGS_LEN},
+};
+use alloc::vec::Vec;
+use core::result::Result;
+
+/// Type args of the issuer, class, nft and nft set cells, the ids are big-endian
+/// 1) issuer: issuer_id: [u8; 20], the first 20 bytes of the type_id
+/// 2) class: issuer_id: [u8; 20] + class_id: u32
+/// 3) nft: issuer_id: [u8; 20] + class_id: u32 + token_id: u32
+/// 4) nft set: issuer_id: [u8; 20] + NFT_SET_CELL: u8 + set_id: u32, the cell type byte
+///    keeps the args of a set apart from the args of a class
+/// The ids in the cell data are taken from the type args, so a cell is bound to its
+/// type script and two cells can't claim the same class or token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeArgs {
+    Issuer {
+        issuer_id: [u8; 20],
+    },
+    Class {
+        issuer_id: [u8; 20],
+        class_id: u32,
+    },
+    Nft {
+        issuer_id: [u8; 20],
+        class_id: u32,
+        token_id: u32,
+    },
+    NftSet {
+        issuer_id: [u8; 20],
+        set_id: u32,
+    },
+}
+
+impl TypeArgs {
+    pub fn from_slice(args: &[u8]) -> Result<Self, Error> {
+        let mut issuer_id = [0u8; 20];
+        match args.len() {
+            ISSUER_TYPE_ARGS_LEN => {
+                issuer_id.copy_from_slice(args);
+                Ok(TypeArgs::Issuer { issuer_id })
+            }
+            CLASS_TYPE_ARGS_LEN => {
+                issuer_id.copy_from_slice(&args[..20]);
+                Ok(TypeArgs::Class {
+                    issuer_id,
+                    class_id: u32_from_slice(&args[20..24]),
+                })
+            }
+            NFT_TYPE_ARGS_LEN => {
+                issuer_id.copy_from_slice(&args[..20]);
+                Ok(TypeArgs::Nft {
+                    issuer_id,
+                    class_id: u32_from_slice(&args[20..24]),
+                    token_id: u32_from_slice(&args[24..28]),
+                })
+            }
+            NFT_SET_TYPE_ARGS_LEN if args[20] == NFT_SET_CELL => {
+                issuer_id.copy_from_slice(&args[..20]);
+                Ok(TypeArgs::NftSet {
+                    issuer_id,
+                    set_id: u32_from_slice(&args[21..25]),
+                })
+            }
+            _ => Err(Error::TypeArgsInvalid),
+        }
+    }
+
+    pub fn from_class(class: &Class) -> Self {
+        TypeArgs::Class {
+            issuer_id: class.issuer_id,
+            class_id: class.class_id,
+        }
+    }
+
+    pub fn from_nft(nft: &Nft) -> Self {
+        TypeArgs::Nft {
+            issuer_id: nft.issuer_id,
+            class_id: nft.class_id,
+            token_id: nft.token_id,
+        }
+    }
+
+    pub fn from_set(set: &NftSet) -> Self {
+        TypeArgs::NftSet {
+            issuer_id: set.issuer_id,
+            set_id: set.set_id,
+        }
+    }
+
+    pub fn issuer_id(&self) -> &[u8; 20] {
+        match self {
+            TypeArgs::Issuer { issuer_id }
+            | TypeArgs::Class { issuer_id, .. }
+            | TypeArgs::Nft { issuer_id, .. }
+            | TypeArgs::NftSet { issuer_id, .. } => issuer_id,
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut args = Vec::with_capacity(NFT_TYPE_ARGS_LEN);
+        args.extend_from_slice(self.issuer_id());
+        match self {
+            TypeArgs::Issuer { .. } => {}
+            TypeArgs::Class { class_id, .. } => {
+                args.extend_from_slice(&class_id.to_be_bytes());
+            }
+            TypeArgs::Nft {
+                class_id, token_id, ..
+            } => {
+                args.extend_from_slice(&class_id.to_be_bytes());
+                args.extend_from_slice(&token_id.to_be_bytes());
+            }
+            TypeArgs::NftSet { set_id, .. } => {
+                args.push(NFT_SET_CELL);
+                args.extend_from_slice(&set_id.to_be_bytes());
+            }
+        }
+        args
+    }
+
+    /// Fill the ids of a class decoded from a class cell
+    pub fn fill_class(&self, class: &mut Class) -> Result<(), Error> {
+        match self {
+            TypeArgs::Class {
+                issuer_id,
+                class_id,
+            } => {
+                class.issuer_id = *issuer_id;
+                class.class_id = *class_id;
+                Ok(())
+            }
+            _ => Err(Error::TypeArgsInvalid),
+        }
+    }
+
+    /// Fill the ids of an nft decoded from an nft cell
+    pub fn fill_nft(&self, nft: &mut Nft) -> Result<(), Error> {
+        match self {
+            TypeArgs::Nft {
+                issuer_id,
+                class_id,
+                token_id,
+            } => {
+                nft.issuer_id = *issuer_id;
+                nft.class_id = *class_id;
+                nft.token_id = *token_id;
+                Ok(())
+            }
+            _ => Err(Error::TypeArgsInvalid),
+        }
+    }
+
+    /// Fill the ids of a nft set decoded from a nft set cell
+    pub fn fill_set(&self, set: &mut NftSet) -> Result<(), Error> {
+        match self {
+            TypeArgs::NftSet { issuer_id, set_id } => {
+                set.issuer_id = *issuer_id;
+                set.set_id = *set_id;
+                Ok(())
+            }
+            _ => Err(Error::TypeArgsInvalid),
+        }
+    }
+}
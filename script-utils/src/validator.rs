@@ -0,0 +1,139 @@
+use crate::{class::Class, error::Error, nft::Nft};
+use core::result::Result;
+
+// The rules are shared by nft cells and nft leaves, so they only work on the
+// decoded nfts.
+type Nfts = (Nft, Nft);
+type Classes = (Class, Class);
+
+pub fn validate_immutable_nft_fields((input_nft, output_nft): &Nfts) -> Result<(), Error> {
+    if input_nft.characteristic != output_nft.characteristic {
+        return Err(Error::NFTCharacteristicNotSame);
+    }
+    if input_nft.configure != output_nft.configure {
+        return Err(Error::NFTConfigureNotSame);
+    }
+    Ok(())
+}
+
+pub fn validate_nft_claim((input_nft, output_nft): &Nfts) -> Result<(), Error> {
+    match (input_nft.is_claimed(), output_nft.is_claimed()) {
+        (false, true) => {
+            if input_nft.is_locked() {
+                return Err(Error::LockedNFTCannotClaim);
+            }
+            if !input_nft.allow_claim() {
+                return Err(Error::NFTDisallowClaimed);
+            }
+            Ok(())
+        }
+        (true, false) => Err(Error::NFTClaimedToUnclaimedError),
+        _ => Ok(()),
+    }
+}
+
+pub fn validate_nft_lock((input_nft, output_nft): &Nfts) -> Result<(), Error> {
+    match (input_nft.is_locked(), output_nft.is_locked()) {
+        (false, true) => {
+            if !input_nft.allow_lock() {
+                return Err(Error::NFTDisallowLocked);
+            }
+            Ok(())
+        }
+        (true, false) => Err(Error::NFTLockedToUnlockedError),
+        _ => Ok(()),
+    }
+}
+
+// transferred: the owner of the nft is changed, the nonce counts the transfers
+pub fn validate_nft_transfer(
+    (input_nft, output_nft): &Nfts,
+    transferred: bool,
+) -> Result<(), Error> {
+    if transferred {
+        validate_nft_transferable(input_nft)?;
+        if input_nft.nonce.checked_add(1) != Some(output_nft.nonce) {
+            return Err(Error::NFTNonceInvalid);
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_nft_transferable(input_nft: &Nft) -> Result<(), Error> {
+    if input_nft.is_locked() {
+        return Err(Error::LockedNFTCannotTransfer);
+    }
+    if !input_nft.is_claimed() && !input_nft.allow_transfer_before_claim() {
+        return Err(Error::NFTCannotTransferBeforeClaim);
+    }
+    if input_nft.is_claimed() && !input_nft.allow_transfer_after_claim() {
+        return Err(Error::NFTCannotTransferAfterClaim);
+    }
+    Ok(())
+}
+
+pub fn validate_nft_destroyable(input_nft: &Nft) -> Result<(), Error> {
+    if input_nft.is_locked() {
+        return Err(Error::LockedNFTCannotDestroy);
+    }
+    if !input_nft.is_claimed() && !input_nft.allow_destroying_before_claim() {
+        return Err(Error::NFTCannotDestroyBeforeClaim);
+    }
+    if input_nft.is_claimed() && !input_nft.allow_destroying_after_claim() {
+        return Err(Error::NFTCannotDestroyAfterClaim);
+    }
+    Ok(())
+}
+
+pub fn validate_nft_ext_info((input_nft, output_nft): &Nfts) -> Result<(), Error> {
+    let (input_ext_info, output_ext_info) = (&input_nft.ext_info, &output_nft.ext_info);
+    if input_nft.allow_ext_info() {
+        let input_len = input_ext_info.len();
+        let output_len = output_ext_info.len();
+        if input_len > output_len {
+            return Err(Error::NFTExtInfoLenError);
+        }
+        if input_ext_info[..] != output_ext_info[..input_len] {
+            return Err(Error::NFTExtInfoCannotModify);
+        }
+        if input_nft.is_locked() && input_len != output_len {
+            return Err(Error::LockedNFTCannotAddExtInfo);
+        }
+        Ok(())
+    } else {
+        if input_ext_info != output_ext_info {
+            return Err(Error::NFTExtInfoCannotModify);
+        }
+        Ok(())
+    }
+}
+
+// owner_kept: the class is still held by the same owner after the update
+pub fn validate_class_renderer(
+    (input_class, output_class): &Classes,
+    owner_kept: bool,
+) -> Result<(), Error> {
+    if input_class.renderer != output_class.renderer && !owner_kept {
+        return Err(Error::ClassRendererCannotModify);
+    }
+    Ok(())
+}
+
+pub fn validate_class_ext_info((input_class, output_class): &Classes) -> Result<(), Error> {
+    let input_ext_info = &input_class.ext_info;
+    let output_ext_info = &output_class.ext_info;
+    if input_class.allow_ext_info() {
+        if input_ext_info.len() > output_ext_info.len() {
+            return Err(Error::ClassExtInfoLenError);
+        }
+        if input_ext_info[..] != output_ext_info[..input_ext_info.len()] {
+            return Err(Error::ClassExtInfoCannotModify);
+        }
+        Ok(())
+    } else {
+        if input_ext_info != output_ext_info {
+            return Err(Error::ClassExtInfoCannotModify);
+        }
+        Ok(())
+    }
+}
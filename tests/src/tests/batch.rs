@@ -0,0 +1,225 @@
+use super::misc::*;
+use super::*;
+use aggregator::{
+    batch::{Aggregator, LiveCell, Submission},
+    error::Error,
+    state::IssuerState,
+};
+use ckb_crypto::secp::{Generator, Message, Privkey};
+use ckb_types::{packed::*, prelude::*};
+use mol::{RawDistributeTransaction, RawExtractTransaction, RawTransferTransaction, Targets};
+use script_utils::{
+    error::Error as ScriptError,
+    helpers::{
+        DISTRIBUTE_TRANSACTION, EXTRACT_TRANSACTION, ISSUE_TRANSACTION, NFT_ITEM,
+        TRANSFER_TRANSACTION,
+    },
+    signature::blake160_lock_hash,
+};
+
+// send the batch of the pool and commit it with the new issuer cell
+fn send_batch(ctx: &mut TestContext, aggregator: &mut Aggregator) {
+    let tx = aggregator.build().expect("build batch");
+    let tx = ctx.complete_tx(tx.as_advanced_builder());
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+
+    let issuer_out_point = ctx.context.create_cell(
+        tx.outputs().get(0).unwrap(),
+        tx.outputs_data().get(0).unwrap().raw_data(),
+    );
+    aggregator.commit(issuer_out_point).unwrap();
+}
+
+fn issue_submission(issuer: &IssuerCell) -> Submission {
+    let issue_tx = issue_transaction(lock_hash(&issuer.lock));
+    Submission::new(nft_transaction(ISSUE_TRANSACTION, issue_tx.as_slice(), &[]))
+}
+
+// the class_old_value only carries the ids of the class, the aggregator fills the leaf
+fn distribute_submission(issuer: &IssuerCell, targets: Vec<[u8; 32]>) -> Submission {
+    let class = new_class(&issuer.issuer_id, 0, lock_hash(&issuer.lock));
+    let distribute_tx = RawDistributeTransaction::new_builder()
+        .class_old_value(class.to_leaf_data().unwrap().pack())
+        .to(Targets::new_builder()
+            .set(targets.iter().map(|target| target.pack()).collect())
+            .build())
+        .build();
+    Submission::new(nft_transaction(
+        DISTRIBUTE_TRANSACTION,
+        distribute_tx.as_slice(),
+        &[],
+    ))
+}
+
+fn secp_owner() -> (Privkey, [u8; 32]) {
+    let (privkey, pubkey) = Generator::random_keypair();
+    let mut blake160 = [0u8; 20];
+    blake160.copy_from_slice(&ckb_hash::blake2b_256(pubkey.serialize())[..20]);
+    (privkey, blake160_lock_hash(&blake160))
+}
+
+#[test]
+fn test_aggregator_issue_and_distribute_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let (mut aggregator, path) = new_aggregator(&mut ctx, &issuer);
+
+    // the issuer lock owns the class and is in the inputs, no signature is needed
+    aggregator.submit(issue_submission(&issuer)).unwrap();
+    let targets = vec![[1u8; 32], [2u8; 32], [3u8; 32]];
+    aggregator
+        .submit(distribute_submission(&issuer, targets))
+        .unwrap();
+    send_batch(&mut ctx, &mut aggregator);
+    assert_eq!(aggregator.state().leaves().count(), 4);
+    assert!(aggregator.pool().is_empty());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_aggregator_duplicate_transaction_error() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let (mut aggregator, path) = new_aggregator(&mut ctx, &issuer);
+
+    aggregator.submit(issue_submission(&issuer)).unwrap();
+    let result = aggregator.submit(issue_submission(&issuer));
+    assert!(matches!(result, Err(Error::DuplicateTransaction)));
+    assert_eq!(aggregator.pool().len(), 1);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_aggregator_signed_transfer_and_extract_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let (mut aggregator, path) = new_aggregator(&mut ctx, &issuer);
+    let (privkey, owner) = secp_owner();
+    aggregator.submit(issue_submission(&issuer)).unwrap();
+    aggregator
+        .submit(distribute_submission(&issuer, vec![owner]))
+        .unwrap();
+    send_batch(&mut ctx, &mut aggregator);
+
+    // the owner signs the transfer after the old values are filled
+    let nft = new_nft(&issuer.issuer_id, 0, 0, owner);
+    let transfer_tx = RawTransferTransaction::new_builder()
+        .typ(Byte::new(NFT_ITEM))
+        .nft_ids(nft_ids(vec![nft.to_key()]))
+        .to(Targets::new_builder()
+            .set(vec![lock_hash(&issuer.lock).pack()])
+            .build())
+        .build();
+    let (tx, message) = aggregator
+        .prepare(&nft_transaction(
+            TRANSFER_TRANSACTION,
+            transfer_tx.as_slice(),
+            &[],
+        ))
+        .unwrap();
+    let signature = privkey
+        .sign_recoverable(&Message::from(message))
+        .unwrap()
+        .serialize();
+    let tx = tx.as_builder().signature(signature.pack()).build();
+    aggregator.submit(Submission::new(tx)).unwrap();
+
+    // the issuer lock extracts the transferred nft into a cell in the same batch
+    let extract_tx = RawExtractTransaction::new_builder()
+        .typ(Byte::new(NFT_ITEM))
+        .nft_ids(nft_ids(vec![nft.to_key()]))
+        .build();
+    let mut submission = Submission::new(nft_transaction(
+        EXTRACT_TRANSACTION,
+        extract_tx.as_slice(),
+        &[],
+    ));
+    submission.output_locks = vec![issuer.lock.clone()];
+    aggregator.submit(submission).unwrap();
+    send_batch(&mut ctx, &mut aggregator);
+    assert!(aggregator.state().leaf(&nft.to_key()).unwrap().is_empty());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_aggregator_transfer_without_authorization_error() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let (mut aggregator, path) = new_aggregator(&mut ctx, &issuer);
+    let (_, owner) = secp_owner();
+    aggregator.submit(issue_submission(&issuer)).unwrap();
+    aggregator
+        .submit(distribute_submission(&issuer, vec![owner]))
+        .unwrap();
+
+    let nft = new_nft(&issuer.issuer_id, 0, 0, owner);
+    let transfer_tx = RawTransferTransaction::new_builder()
+        .typ(Byte::new(NFT_ITEM))
+        .nft_ids(nft_ids(vec![nft.to_key()]))
+        .to(Targets::new_builder().set(vec![[1u8; 32].pack()]).build())
+        .build();
+    let result = aggregator.submit(Submission::new(nft_transaction(
+        TRANSFER_TRANSACTION,
+        transfer_tx.as_slice(),
+        &[],
+    )));
+    assert!(matches!(
+        result,
+        Err(Error::Script(ScriptError::OwnerNotAuthorized))
+    ));
+    assert_eq!(aggregator.pool().len(), 2);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_aggregator_rollback_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let (mut aggregator, path) = new_aggregator(&mut ctx, &issuer);
+    aggregator.submit(issue_submission(&issuer)).unwrap();
+    aggregator.build().unwrap();
+
+    // submissions accepted while the batch is pending follow it
+    aggregator
+        .submit(distribute_submission(&issuer, vec![[1u8; 32]]))
+        .unwrap();
+
+    // the L1 transaction of the batch failed, both are sent in the next one
+    let dropped = aggregator.rollback().unwrap();
+    assert!(dropped.is_empty());
+    assert_eq!(aggregator.pool().len(), 2);
+    send_batch(&mut ctx, &mut aggregator);
+    assert_eq!(aggregator.state().leaves().count(), 2);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_aggregator_recover_committed_batch_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let (mut aggregator, path) = new_aggregator(&mut ctx, &issuer);
+    aggregator.submit(issue_submission(&issuer)).unwrap();
+    let tx = aggregator.build().unwrap();
+    // the L1 transaction is committed, but the process stops before the batch is persisted
+    drop(aggregator);
+
+    let output = tx.outputs().get(0).unwrap();
+    let data = tx.outputs_data().get(0).unwrap().raw_data();
+    let issuer_cell = LiveCell {
+        out_point: ctx.context.create_cell(output.clone(), data.clone()),
+        output,
+        data,
+    };
+    let state = IssuerState::open(&path, issuer.issuer_id).unwrap();
+    let aggregator = Aggregator::new(state, issuer_cell, vec![]).unwrap();
+    assert_eq!(aggregator.state().issuer().class_count, 1);
+    assert_eq!(aggregator.state().leaves().count(), 1);
+
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,668 @@
+use super::misc::*;
+use super::*;
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    bytes::Bytes,
+    core::{TransactionBuilder, TransactionView},
+    packed::*,
+    prelude::*,
+};
+use mol::{
+    RawDistributeTransaction, RawExtractTransaction, RawInsertTransaction, RawTransferTransaction,
+    Targets,
+};
+use script_utils::{
+    class::Class,
+    error::Error,
+    helpers::{
+        CLASS_ITEM, DISTRIBUTE_TRANSACTION, EXTRACT_TRANSACTION, INSERT_TRANSACTION,
+        TRANSFER_TRANSACTION,
+    },
+    nft::Nft,
+    nft_set::NftSet,
+};
+
+// an issuer with one class leaf owned by the owner lock
+fn issuer_with_class(ctx: &mut TestContext, owner_lock: &Script) -> (IssuerCell, Class) {
+    let mut issuer = IssuerCell::new(ctx, 1, 0, vec![]);
+    let class = new_class(&issuer.issuer_id, 0, lock_hash(owner_lock));
+    issuer.smt = new_smt(vec![(class.to_key(), class.to_leaf_data().unwrap())]);
+    (issuer, class)
+}
+
+// extract the class leaf into a class cell with the class lock, the owner cell is put
+// into the inputs to authorize the extraction if it's given
+fn extract_class_tx(
+    ctx: &mut TestContext,
+    issuer: &mut IssuerCell,
+    class: &Class,
+    class_lock: Script,
+    owner_lock: Option<Script>,
+) -> TransactionView {
+    let mut inputs = vec![issuer.input(ctx)];
+    if let Some(owner_lock) = owner_lock {
+        inputs.push(ctx.create_input(owner_lock, None, Bytes::new()));
+    }
+    let leaf = class.to_leaf_data().unwrap();
+    let proof = update_smt(&mut issuer.smt, vec![(class.to_key(), vec![])]);
+    let extract_tx = RawExtractTransaction::new_builder()
+        .typ(Byte::new(CLASS_ITEM))
+        .nft_ids(nft_ids(vec![class.to_key()]))
+        .nft_old_values(nft_values(vec![leaf]))
+        .build();
+    let witness = smt_witness(
+        vec![nft_transaction(
+            EXTRACT_TRANSACTION,
+            extract_tx.as_slice(),
+            &[],
+        )],
+        &proof,
+    );
+
+    let class_type = ctx.type_script(class_type_args(class));
+    ctx.complete_tx(
+        TransactionBuilder::default()
+            .inputs(inputs)
+            .output(issuer.output())
+            .output_data(issuer.data().pack())
+            .output(cell_output(class_lock, Some(class_type)))
+            .output_data(class_cell_data(class).pack())
+            .witness(witness.pack()),
+    )
+}
+
+// insert the class leaf back into the issuer tree, the class cell is consumed if it's given
+fn insert_class_tx(
+    ctx: &mut TestContext,
+    issuer: &mut IssuerCell,
+    class: &Class,
+    class_lock: Option<Script>,
+) -> TransactionView {
+    let mut inputs = vec![issuer.input(ctx)];
+    if let Some(class_lock) = class_lock {
+        let class_type = ctx.type_script(class_type_args(class));
+        inputs.push(ctx.create_input(class_lock, Some(class_type), class_cell_data(class)));
+    }
+    let proof = update_smt(
+        &mut issuer.smt,
+        vec![(class.to_key(), class.to_leaf_data().unwrap())],
+    );
+    let insert_tx = RawInsertTransaction::new_builder()
+        .typ(Byte::new(CLASS_ITEM))
+        .nft_ids(nft_ids(vec![class.to_key()]))
+        .build();
+    let witness = smt_witness(
+        vec![nft_transaction(
+            INSERT_TRANSACTION,
+            insert_tx.as_slice(),
+            &[],
+        )],
+        &proof,
+    );
+
+    ctx.complete_tx(
+        TransactionBuilder::default()
+            .inputs(inputs)
+            .output(issuer.output())
+            .output_data(issuer.data().pack())
+            .witness(witness.pack()),
+    )
+}
+
+#[test]
+fn test_extract_class_cell_success() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, class) = issuer_with_class(&mut ctx, &owner_lock);
+    let tx = extract_class_tx(
+        &mut ctx,
+        &mut issuer,
+        &class,
+        owner_lock.clone(),
+        Some(owner_lock),
+    );
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_extract_class_cell_with_other_lock_error() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let other_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, class) = issuer_with_class(&mut ctx, &owner_lock);
+    let tx = extract_class_tx(&mut ctx, &mut issuer, &class, other_lock, Some(owner_lock));
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::NFTOwnerNotMatch as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_extract_class_cell_without_authorization_error() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, class) = issuer_with_class(&mut ctx, &owner_lock);
+    let tx = extract_class_tx(&mut ctx, &mut issuer, &class, owner_lock, None);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::OwnerNotAuthorized as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_extract_class_cell_not_issued_error() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, _) = issuer_with_class(&mut ctx, &owner_lock);
+    // the issuer has issued one class, so the class_id 1 is not taken yet
+    let class = new_class(&issuer.issuer_id, 1, lock_hash(&owner_lock));
+    issuer.smt = new_smt(vec![(class.to_key(), class.to_leaf_data().unwrap())]);
+    let tx = extract_class_tx(
+        &mut ctx,
+        &mut issuer,
+        &class,
+        owner_lock.clone(),
+        Some(owner_lock),
+    );
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::ClassIdIncreaseError as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_insert_class_cell_success() {
+    let mut ctx = TestContext::new();
+    let class_lock = ctx.lock_script(random_20bytes());
+    let mut issuer = IssuerCell::new(&mut ctx, 1, 0, vec![]);
+    let class = new_class(&issuer.issuer_id, 0, lock_hash(&class_lock));
+    let tx = insert_class_tx(&mut ctx, &mut issuer, &class, Some(class_lock));
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_insert_class_cell_without_class_cell_error() {
+    let mut ctx = TestContext::new();
+    let class_lock = ctx.lock_script(random_20bytes());
+    let mut issuer = IssuerCell::new(&mut ctx, 1, 0, vec![]);
+    let class = new_class(&issuer.issuer_id, 0, lock_hash(&class_lock));
+    let tx = insert_class_tx(&mut ctx, &mut issuer, &class, None);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::ClassCellsCountError as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+// distribute nft cells from a class cell, the class cell records the new issued and the
+// nft cells are changed by modify
+fn distribute_from_class_cell_tx(
+    ctx: &mut TestContext,
+    class: &Class,
+    count: u32,
+    modify: &dyn Fn(&mut Nft),
+) -> TransactionView {
+    let class_lock = ctx.lock_script(random_20bytes());
+    let class_type = ctx.type_script(class_type_args(class));
+    let input = ctx.create_input(
+        class_lock.clone(),
+        Some(class_type.clone()),
+        class_cell_data(class),
+    );
+
+    let mut output_class = class.clone();
+    output_class.issued += count;
+    let mut builder = TransactionBuilder::default()
+        .input(input)
+        .output(cell_output(class_lock, Some(class_type)))
+        .output_data(class_cell_data(&output_class).pack());
+
+    let mut targets = Vec::new();
+    for token_id in class.issued..output_class.issued {
+        let target_lock = ctx.lock_script(random_20bytes());
+        targets.push(lock_hash(&target_lock).pack());
+        let mut nft = new_nft(
+            &class.issuer_id,
+            class.class_id,
+            token_id,
+            lock_hash(&target_lock),
+        );
+        modify(&mut nft);
+        let nft_type = ctx.type_script(nft_type_args(&nft));
+        builder = builder
+            .output(cell_output(target_lock, Some(nft_type)))
+            .output_data(nft_cell_data(&nft).pack());
+    }
+
+    let distribute_tx = RawDistributeTransaction::new_builder()
+        .to(Targets::new_builder().set(targets).build())
+        .build();
+    let witness = smt_witness(
+        vec![nft_transaction(
+            DISTRIBUTE_TRANSACTION,
+            distribute_tx.as_slice(),
+            &[],
+        )],
+        &[],
+    );
+    ctx.complete_tx(builder.witness(witness.pack()))
+}
+
+#[test]
+fn test_distribute_from_class_cell_success() {
+    let mut ctx = TestContext::new();
+    let class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    let tx = distribute_from_class_cell_tx(&mut ctx, &class, 2, &|_| {});
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_distribute_from_class_cell_over_total_error() {
+    let mut ctx = TestContext::new();
+    let mut class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    class.total = 1;
+    let tx = distribute_from_class_cell_tx(&mut ctx, &class, 2, &|_| {});
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::ClassTotalSmallerThanIssued as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_distribute_from_class_cell_with_token_id_error() {
+    let mut ctx = TestContext::new();
+    let class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    let tx = distribute_from_class_cell_tx(&mut ctx, &class, 1, &|nft| nft.token_id += 1);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::NFTTokenIdIncreaseError as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_distribute_from_class_cell_with_configure_error() {
+    let mut ctx = TestContext::new();
+    let class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    let tx = distribute_from_class_cell_tx(&mut ctx, &class, 1, &|nft| nft.configure = 0b0000_0001);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::NFTAndClassConfigureNotSame as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_distribute_from_class_cell_with_claimed_nft_error() {
+    let mut ctx = TestContext::new();
+    let class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    let tx = distribute_from_class_cell_tx(&mut ctx, &class, 2, &|nft| nft.state = 1);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::NFTDataInvalid as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_distribute_from_class_cell_with_characteristic_error() {
+    let mut ctx = TestContext::new();
+    let class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    let tx =
+        distribute_from_class_cell_tx(&mut ctx, &class, 1, &|nft| nft.characteristic = [1u8; 8]);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::NFTDataInvalid as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_distribute_from_class_cell_with_ext_info_error() {
+    let mut ctx = TestContext::new();
+    let class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    let tx =
+        distribute_from_class_cell_tx(&mut ctx, &class, 1, &|nft| nft.ext_info = b"ext".to_vec());
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::NFTDataInvalid as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+// a class cell has no tree, so the transactions of a tree are refused along with it
+#[test]
+fn test_update_class_cell_with_transfer_error() {
+    let mut ctx = TestContext::new();
+    let class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    let class_lock = ctx.lock_script(random_20bytes());
+    let class_type = ctx.type_script(class_type_args(&class));
+    let input = ctx.create_input(
+        class_lock.clone(),
+        Some(class_type.clone()),
+        class_cell_data(&class),
+    );
+    let transfer_tx = RawTransferTransaction::default();
+    let witness = smt_witness(
+        vec![nft_transaction(
+            TRANSFER_TRANSACTION,
+            transfer_tx.as_slice(),
+            &[],
+        )],
+        &[],
+    );
+    let tx = ctx.complete_tx(
+        TransactionBuilder::default()
+            .input(input)
+            .output(cell_output(class_lock, Some(class_type)))
+            .output_data(class_cell_data(&class).pack())
+            .witness(witness.pack()),
+    );
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::TransactionNotSupported as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+// update a class cell without any nft transaction, the class cell is handed to a new lock
+// if transferred is set
+fn update_class_cell_tx(
+    ctx: &mut TestContext,
+    input_class: &Class,
+    output_class: &Class,
+    transferred: bool,
+) -> TransactionView {
+    let input_lock = ctx.lock_script(random_20bytes());
+    let output_lock = if transferred {
+        ctx.lock_script(random_20bytes())
+    } else {
+        input_lock.clone()
+    };
+    let class_type = ctx.type_script(class_type_args(input_class));
+    let input = ctx.create_input(
+        input_lock,
+        Some(class_type.clone()),
+        class_cell_data(input_class),
+    );
+    let witness = smt_witness(vec![], &[]);
+    ctx.complete_tx(
+        TransactionBuilder::default()
+            .input(input)
+            .output(cell_output(output_lock, Some(class_type)))
+            .output_data(class_cell_data(output_class).pack())
+            .witness(witness.pack()),
+    )
+}
+
+#[test]
+fn test_update_class_cell_burned_without_burn_error() {
+    let mut ctx = TestContext::new();
+    let mut input_class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    input_class.issued = 1;
+    let mut output_class = input_class.clone();
+    output_class.burned = 1;
+    let tx = update_class_cell_tx(&mut ctx, &input_class, &output_class, false);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::ClassBurnedInvalid as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_update_class_cell_issued_without_distribute_error() {
+    let mut ctx = TestContext::new();
+    let input_class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    let mut output_class = input_class.clone();
+    output_class.issued = 1;
+    let tx = update_class_cell_tx(&mut ctx, &input_class, &output_class, false);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::ClassIssuedInvalid as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_modify_class_cell_name_error() {
+    let mut ctx = TestContext::new();
+    let input_class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    let mut output_class = input_class.clone();
+    output_class.name = b"another class".to_vec();
+    let tx = update_class_cell_tx(&mut ctx, &input_class, &output_class, false);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::ClassImmutableFieldsNotSame as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_update_class_cell_with_unaccounted_set_cell_error() {
+    let mut ctx = TestContext::new();
+    let class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    let class_lock = ctx.lock_script(random_20bytes());
+    let class_type = ctx.type_script(class_type_args(&class));
+    let input = ctx.create_input(
+        class_lock.clone(),
+        Some(class_type.clone()),
+        class_cell_data(&class),
+    );
+    // a class cell can't create set cells, only an issuer cell extracts them
+    let set = NftSet {
+        version: 0,
+        issuer_id: class.issuer_id,
+        set_id: 0,
+        owner: lock_hash(&class_lock),
+        member_count: 0,
+        name: b"set".to_vec(),
+        description: Vec::new(),
+    };
+    let set_type = ctx.type_script(set_type_args(&set));
+    let witness = smt_witness(vec![], &[]);
+    let tx = ctx.complete_tx(
+        TransactionBuilder::default()
+            .input(input)
+            .output(cell_output(class_lock.clone(), Some(class_type)))
+            .output_data(class_cell_data(&class).pack())
+            .output(cell_output(class_lock, Some(set_type)))
+            .output_data(set_cell_data(&set).pack())
+            .witness(witness.pack()),
+    );
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 1;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::ClassDataInvalid as i8)
+            .output_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_update_class_cell_renderer_success() {
+    let mut ctx = TestContext::new();
+    let input_class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    let mut output_class = input_class.clone();
+    output_class.renderer = b"https://example.com/render".to_vec();
+    let tx = update_class_cell_tx(&mut ctx, &input_class, &output_class, false);
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_update_class_cell_renderer_with_transfer_error() {
+    let mut ctx = TestContext::new();
+    let input_class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    let mut output_class = input_class.clone();
+    output_class.renderer = b"https://example.com/render".to_vec();
+    let tx = update_class_cell_tx(&mut ctx, &input_class, &output_class, true);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::ClassRendererCannotModify as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_append_class_cell_ext_info_success() {
+    let mut ctx = TestContext::new();
+    let mut input_class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    input_class.ext_info = b"hello".to_vec();
+    let mut output_class = input_class.clone();
+    output_class.ext_info = b"hello world".to_vec();
+    let tx = update_class_cell_tx(&mut ctx, &input_class, &output_class, false);
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_modify_class_cell_ext_info_error() {
+    let mut ctx = TestContext::new();
+    let mut input_class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    input_class.ext_info = b"hello".to_vec();
+    let mut output_class = input_class.clone();
+    output_class.ext_info = b"world".to_vec();
+    let tx = update_class_cell_tx(&mut ctx, &input_class, &output_class, false);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::ClassExtInfoCannotModify as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_shrink_class_cell_ext_info_error() {
+    let mut ctx = TestContext::new();
+    let mut input_class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    input_class.ext_info = b"hello".to_vec();
+    let mut output_class = input_class.clone();
+    output_class.ext_info = b"he".to_vec();
+    let tx = update_class_cell_tx(&mut ctx, &input_class, &output_class, false);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::ClassExtInfoLenError as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_append_class_cell_ext_info_with_disallowed_configure_error() {
+    let mut ctx = TestContext::new();
+    let mut input_class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    input_class.configure = 0b0000_0100;
+    let mut output_class = input_class.clone();
+    output_class.ext_info = b"hello".to_vec();
+    let tx = update_class_cell_tx(&mut ctx, &input_class, &output_class, false);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::ClassExtInfoCannotModify as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+fn destroy_class_cell_tx(ctx: &mut TestContext, class: &Class) -> TransactionView {
+    let lock = ctx.lock_script(random_20bytes());
+    let class_type = ctx.type_script(class_type_args(class));
+    let input = ctx.create_input(lock.clone(), Some(class_type), class_cell_data(class));
+    ctx.complete_tx(
+        TransactionBuilder::default()
+            .input(input)
+            .output(cell_output(lock, None))
+            .output_data(Bytes::new().pack()),
+    )
+}
+
+#[test]
+fn test_destroy_class_cell_success() {
+    let mut ctx = TestContext::new();
+    let class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    let tx = destroy_class_cell_tx(&mut ctx, &class);
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_destroy_class_cell_with_issued_error() {
+    let mut ctx = TestContext::new();
+    let mut class = new_class(&[1u8; 20], 0, [0u8; 32]);
+    class.issued = 1;
+    let tx = destroy_class_cell_tx(&mut ctx, &class);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::ClassCellCannotDestroyed as i8)
+            .input_type_script(script_cell_index)
+    );
+}
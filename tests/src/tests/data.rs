@@ -0,0 +1,203 @@
+use super::misc::*;
+use super::*;
+use script_utils::{class::Class, error::Error, issuer::Issuer, nft::Nft, nft_set::NftSet};
+
+fn random_class() -> Class {
+    let mut issuer_id = [0u8; 20];
+    issuer_id.copy_from_slice(&random_20bytes());
+    let mut class = new_class(&issuer_id, 3, [5u8; 32]);
+    class.issued = 7;
+    class.burned = 2;
+    class.configure = 0b0001_0001;
+    class.renderer = b"https://example.com/render".to_vec();
+    class.ext_info = b"ext info".to_vec();
+    class
+}
+
+fn random_nft() -> Nft {
+    let mut issuer_id = [0u8; 20];
+    issuer_id.copy_from_slice(&random_20bytes());
+    let mut nft = new_nft(&issuer_id, 3, 9, [5u8; 32]);
+    nft.characteristic = [7u8; 8];
+    nft.configure = 0b0000_0100;
+    nft.state = 0b0000_0001;
+    nft.nonce = 2;
+    nft.ext_info = b"ext info".to_vec();
+    nft
+}
+
+#[test]
+fn test_issuer_data_round_trip() {
+    let issuer = Issuer {
+        version: 0,
+        class_count: 2,
+        set_count: 1,
+        smt_root: [9u8; 32],
+        info: b"issuer info".to_vec(),
+    };
+    let data = issuer.to_data().unwrap();
+    let decoded = Issuer::from_data(&data).unwrap();
+    assert_eq!(decoded.class_count, issuer.class_count);
+    assert_eq!(decoded.set_count, issuer.set_count);
+    assert_eq!(decoded.smt_root, issuer.smt_root);
+    assert_eq!(decoded.info, issuer.info);
+    assert_eq!(decoded.to_data().unwrap(), data);
+}
+
+#[test]
+fn test_issuer_data_with_trailing_bytes_error() {
+    let issuer = Issuer {
+        version: 0,
+        class_count: 2,
+        set_count: 1,
+        smt_root: [9u8; 32],
+        info: b"issuer info".to_vec(),
+    };
+    let mut data = issuer.to_data().unwrap();
+    data.push(0);
+    assert!(Issuer::from_data(&data).is_err());
+}
+
+#[test]
+fn test_class_data_round_trip() {
+    let class = random_class();
+
+    let leaf = class.to_data(false).unwrap();
+    let decoded = Class::from_data(&leaf, false).unwrap();
+    assert_eq!(decoded.to_data(false).unwrap(), leaf);
+    assert_eq!(decoded.owner, class.owner);
+
+    // the ids and owner are not in the cell data
+    let data = class.to_data(true).unwrap();
+    let decoded = Class::from_data(&data, true).unwrap();
+    assert_eq!(decoded.to_data(true).unwrap(), data);
+    assert_eq!(decoded.name, class.name);
+    assert_eq!(decoded.description, class.description);
+    assert_eq!(decoded.renderer, class.renderer);
+    assert_eq!(decoded.ext_info, class.ext_info);
+    assert_eq!(decoded.issued, class.issued);
+    assert_eq!(decoded.burned, class.burned);
+}
+
+#[test]
+fn test_class_data_without_ext_info() {
+    let mut class = random_class();
+    class.ext_info = Vec::new();
+
+    // the ext info is left out of the data instead of being encoded as an empty field
+    let data = class.to_data(true).unwrap();
+    let decoded = Class::from_data(&data, true).unwrap();
+    assert!(decoded.ext_info.is_empty());
+    assert_eq!(decoded.to_data(true).unwrap(), data);
+
+    // an empty ext info field present in the data can't be encoded back
+    let mut data = class.to_data(true).unwrap();
+    data.extend_from_slice(&0u16.to_be_bytes());
+    assert!(Class::from_data(&data, true).is_err());
+}
+
+#[test]
+fn test_class_data_with_trailing_bytes_error() {
+    let class = random_class();
+
+    let mut leaf = class.to_data(false).unwrap();
+    leaf.push(0);
+    assert!(Class::from_data(&leaf, false).is_err());
+
+    let mut data = class.to_data(true).unwrap();
+    data.push(0);
+    assert!(Class::from_data(&data, true).is_err());
+}
+
+#[test]
+fn test_class_data_version_error() {
+    let class = random_class();
+
+    let mut data = class.to_data(true).unwrap();
+    data[0] = 1;
+    assert_eq!(
+        Class::from_data(&data, true).unwrap_err(),
+        Error::VersionInvalid
+    );
+}
+
+#[test]
+fn test_class_burned_over_issued_error() {
+    let mut class = random_class();
+    class.burned = class.issued + 1;
+
+    let leaf = class.to_data(false).unwrap();
+    assert_eq!(
+        Class::from_data(&leaf, false).unwrap_err(),
+        Error::ClassBurnedInvalid
+    );
+    let data = class.to_data(true).unwrap();
+    assert_eq!(
+        Class::from_data(&data, true).unwrap_err(),
+        Error::ClassBurnedInvalid
+    );
+}
+
+#[test]
+fn test_oversize_field_error() {
+    let oversize = vec![0u8; u16::MAX as usize + 1];
+
+    let issuer = Issuer {
+        version: 0,
+        class_count: 0,
+        set_count: 0,
+        smt_root: [0u8; 32],
+        info: oversize.clone(),
+    };
+    assert_eq!(issuer.to_data().unwrap_err(), Error::IssuerDataInvalid);
+
+    let mut class = random_class();
+    class.description = oversize.clone();
+    assert_eq!(class.to_data(false).unwrap_err(), Error::ClassDataInvalid);
+    assert_eq!(class.to_data(true).unwrap_err(), Error::ClassDataInvalid);
+
+    let set = NftSet {
+        version: 0,
+        issuer_id: [1u8; 20],
+        set_id: 0,
+        owner: [5u8; 32],
+        member_count: 0,
+        name: oversize,
+        description: Vec::new(),
+    };
+    assert_eq!(set.to_data(false).unwrap_err(), Error::NFTSetDataInvalid);
+    assert_eq!(set.to_data(true).unwrap_err(), Error::NFTSetDataInvalid);
+}
+
+#[test]
+fn test_nft_data_round_trip() {
+    let nft = random_nft();
+
+    let leaf = nft.to_data(false);
+    let decoded = Nft::from_data(&leaf, false).unwrap();
+    assert_eq!(decoded.to_data(false), leaf);
+    assert_eq!(decoded.to_key(), nft.to_key());
+
+    // the ids and owner are not in the cell data
+    let data = nft.to_data(true);
+    let decoded = Nft::from_data(&data, true).unwrap();
+    assert_eq!(decoded.to_data(true), data);
+    assert_eq!(decoded.nonce, nft.nonce);
+    assert_eq!(decoded.ext_info, nft.ext_info);
+}
+
+#[test]
+fn test_nft_data_without_ext_info() {
+    let mut nft = random_nft();
+    nft.ext_info = Vec::new();
+
+    let leaf = nft.to_data(false);
+    let decoded = Nft::from_data(&leaf, false).unwrap();
+    assert!(decoded.ext_info.is_empty());
+    assert_eq!(decoded.to_data(false), leaf);
+
+    let data = nft.to_data(true);
+    let decoded = Nft::from_data(&data, true).unwrap();
+    assert!(decoded.ext_info.is_empty());
+    assert_eq!(decoded.to_data(true), data);
+}
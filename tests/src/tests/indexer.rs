@@ -0,0 +1,120 @@
+use super::misc::*;
+use super::*;
+use aggregator::{
+    batch::{Aggregator, Submission},
+    indexer::{NftId, NftLocation, OwnerIndex},
+};
+use ckb_types::{
+    core::{TransactionBuilder, TransactionView},
+    packed::*,
+    prelude::*,
+};
+use mol::{NftTransaction, RawExtractTransaction};
+use script_utils::helpers::{EXTRACT_TRANSACTION, NFT_ITEM};
+
+// build the batch of the pool, index it as observed on L1 and commit it
+fn commit_batch(
+    ctx: &mut TestContext,
+    aggregator: &mut Aggregator,
+    index: &mut OwnerIndex,
+) -> TransactionView {
+    let tx = aggregator.build().expect("build batch");
+    index.apply_leaves(aggregator.pending_leaves());
+    index.apply_transaction(&tx);
+    let issuer_out_point = ctx.context.create_cell(
+        tx.outputs().get(0).unwrap(),
+        tx.outputs_data().get(0).unwrap().raw_data(),
+    );
+    aggregator.commit(issuer_out_point).unwrap();
+    tx
+}
+
+fn submit_all(aggregator: &mut Aggregator, txs: Vec<NftTransaction>) {
+    for tx in txs {
+        aggregator.submit(Submission::new(tx)).unwrap();
+    }
+}
+
+fn nft_id(issuer: &IssuerCell, token_id: u32) -> NftId {
+    NftId {
+        issuer_id: issuer.issuer_id,
+        class_id: 0,
+        token_id,
+    }
+}
+
+#[test]
+fn test_owner_index_leaves_and_cells_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let (mut aggregator, path) = new_aggregator(&mut ctx, &issuer);
+    let mut index = OwnerIndex::default();
+    index.add_issuer(&issuer.type_, aggregator.state());
+
+    let issuer_owner = lock_hash(&issuer.lock);
+    submit_all(
+        &mut aggregator,
+        issue_and_distribute(&issuer, vec![issuer_owner, issuer_owner, [1u8; 32]]),
+    );
+    commit_batch(&mut ctx, &mut aggregator, &mut index);
+    assert_eq!(index.nfts_of(&issuer_owner).count(), 2);
+    assert_eq!(index.nfts_of(&[1u8; 32]).count(), 1);
+
+    // the extracted nft moves from its leaf to a cell locked by the owner
+    let nft = new_nft(&issuer.issuer_id, 0, 1, issuer_owner);
+    let extract_tx = RawExtractTransaction::new_builder()
+        .typ(Byte::new(NFT_ITEM))
+        .nft_ids(nft_ids(vec![nft.to_key()]))
+        .build();
+    let mut submission = Submission::new(nft_transaction(
+        EXTRACT_TRANSACTION,
+        extract_tx.as_slice(),
+        &[],
+    ));
+    submission.output_locks = vec![issuer.lock.clone()];
+    aggregator.submit(submission).unwrap();
+    let tx = commit_batch(&mut ctx, &mut aggregator, &mut index);
+    let cell = OutPoint::new(tx.hash(), 1);
+    assert_eq!(
+        index.get(&nft_id(&issuer, 1)).unwrap().location,
+        NftLocation::Cell(cell.clone())
+    );
+    assert_eq!(
+        index.get(&nft_id(&issuer, 0)).unwrap().location,
+        NftLocation::Leaf(new_nft(&issuer.issuer_id, 0, 0, issuer_owner).to_key())
+    );
+    assert_eq!(index.nfts_of(&issuer_owner).count(), 2);
+
+    // the nft cell is consumed by a transaction destroying it
+    let destroy_tx = TransactionBuilder::default()
+        .input(CellInput::new(cell, 0))
+        .build();
+    index.apply_transaction(&destroy_tx);
+    assert!(index.get(&nft_id(&issuer, 1)).is_none());
+    assert_eq!(index.nfts_of(&issuer_owner).count(), 1);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_owner_index_add_issuer_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let (mut aggregator, path) = new_aggregator(&mut ctx, &issuer);
+    let mut index = OwnerIndex::default();
+    submit_all(
+        &mut aggregator,
+        issue_and_distribute(&issuer, vec![[1u8; 32], [2u8; 32]]),
+    );
+    commit_batch(&mut ctx, &mut aggregator, &mut index);
+
+    // an issuer added after its batches is indexed from its tree
+    let mut index = OwnerIndex::default();
+    index.add_issuer(&issuer.type_, aggregator.state());
+    let nfts: Vec<_> = index.nfts_of(&[2u8; 32]).collect();
+    assert_eq!(nfts.len(), 1);
+    assert_eq!(nfts[0].id, nft_id(&issuer, 1));
+    assert!(index.nfts_of(&lock_hash(&issuer.lock)).next().is_none());
+
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,230 @@
+use super::misc::*;
+use super::*;
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    bytes::Bytes,
+    core::{TransactionBuilder, TransactionView},
+    packed::*,
+    prelude::*,
+};
+use script_utils::{error::Error, helpers::ISSUE_TRANSACTION};
+use sparse_merkle_tree::H256;
+
+fn create_issuer_tx(
+    ctx: &mut TestContext,
+    issuer_id: Option<Bytes>,
+    class_count: u32,
+) -> TransactionView {
+    let lock_script = ctx.lock_script(random_20bytes());
+    let input_out_point = ctx
+        .context
+        .create_cell(cell_output(lock_script.clone(), None), Bytes::new());
+    let input = CellInput::new_builder()
+        .previous_output(input_out_point.clone())
+        .build();
+
+    // the issuer_id is the first 20 bytes of the type id
+    let issuer_id =
+        issuer_id.unwrap_or_else(|| Bytes::from(calculate_type_id(input_out_point)[..20].to_vec()));
+    let issuer_type_script = ctx.type_script(issuer_id.to_vec());
+
+    let outputs = vec![cell_output(lock_script, Some(issuer_type_script))];
+    let outputs_data = vec![issuer_data(class_count, 0, &H256::zero())];
+
+    ctx.complete_tx(
+        TransactionBuilder::default()
+            .input(input)
+            .outputs(outputs)
+            .outputs_data(outputs_data.pack()),
+    )
+}
+
+// issue a class from an empty issuer, the class_count and root of the output are
+// given by the caller
+fn issue_class_tx(
+    ctx: &mut TestContext,
+    issuer: &mut IssuerCell,
+    tx_data: &[u8],
+    output_class_count: u32,
+) -> TransactionView {
+    let owner = lock_hash(&issuer.lock);
+    let input = issuer.input(ctx);
+    let class = new_class(&issuer.issuer_id, 0, owner);
+    let proof = update_smt(
+        &mut issuer.smt,
+        vec![(class.to_key(), class.to_leaf_data().unwrap())],
+    );
+    issuer.class_count = output_class_count;
+
+    let witness = smt_witness(
+        vec![nft_transaction(ISSUE_TRANSACTION, tx_data, &[])],
+        &proof,
+    );
+    ctx.complete_tx(
+        TransactionBuilder::default()
+            .input(input)
+            .output(issuer.output())
+            .output_data(issuer.data().pack())
+            .witness(witness.pack()),
+    )
+}
+
+#[test]
+fn test_create_issuer_cell_success() {
+    let mut ctx = TestContext::new();
+    let tx = create_issuer_tx(&mut ctx, None, 0);
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_create_issuer_cell_with_wrong_type_args_error() {
+    let mut ctx = TestContext::new();
+    let tx = create_issuer_tx(&mut ctx, Some(random_20bytes()), 0);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::TypeArgsInvalid as i8)
+            .output_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_create_issuer_cell_with_class_count_error() {
+    let mut ctx = TestContext::new();
+    let tx = create_issuer_tx(&mut ctx, None, 1);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::IssuerClassCountError as i8)
+            .output_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_update_issuer_cell_with_issue_success() {
+    let mut ctx = TestContext::new();
+    let mut issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let owner = lock_hash(&issuer.lock);
+    let issue_tx = issue_transaction(owner);
+    let tx = issue_class_tx(&mut ctx, &mut issuer, issue_tx.as_slice(), 1);
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_update_issuer_cell_with_class_count_error() {
+    let mut ctx = TestContext::new();
+    let mut issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let owner = lock_hash(&issuer.lock);
+    let issue_tx = issue_transaction(owner);
+    let tx = issue_class_tx(&mut ctx, &mut issuer, issue_tx.as_slice(), 0);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::IssuerClassCountError as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_update_issuer_cell_with_wrong_root_error() {
+    let mut ctx = TestContext::new();
+    let mut issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    // the issued class has another owner than the one put into the tree
+    let issue_tx = issue_transaction([1u8; 32]);
+    let tx = issue_class_tx(&mut ctx, &mut issuer, issue_tx.as_slice(), 1);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::SMTProofVerifyFailed as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_update_issuer_cell_with_invalid_transaction_error() {
+    let mut ctx = TestContext::new();
+    let mut issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let tx = issue_class_tx(&mut ctx, &mut issuer, &[0u8; 10], 1);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::TransactionDataInvalid as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_update_issuer_cell_with_invalid_witness_error() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let input = issuer.input(&mut ctx);
+    // the lock of the witness must hold the nft transactions
+    let witness = WitnessArgs::new_builder()
+        .lock(Some(Bytes::from(vec![1u8, 2, 3])).pack())
+        .build();
+    let tx = ctx.complete_tx(
+        TransactionBuilder::default()
+            .input(input)
+            .output(issuer.output())
+            .output_data(issuer.data().pack())
+            .witness(witness.as_bytes().pack()),
+    );
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::NftTransactionsInvalid as i8)
+            .input_type_script(script_cell_index)
+    );
+}
+
+fn destroy_issuer_tx(ctx: &mut TestContext, issuer: &IssuerCell) -> TransactionView {
+    let input = issuer.input(ctx);
+    ctx.complete_tx(
+        TransactionBuilder::default()
+            .input(input)
+            .output(cell_output(issuer.lock.clone(), None))
+            .output_data(Bytes::new().pack()),
+    )
+}
+
+#[test]
+fn test_destroy_issuer_cell_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let tx = destroy_issuer_tx(&mut ctx, &issuer);
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_destroy_issuer_cell_with_classes_error() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 1, 0, vec![]);
+    let tx = destroy_issuer_tx(&mut ctx, &issuer);
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::IssuerCellCannotDestroyed as i8)
+            .input_type_script(script_cell_index)
+    );
+}
@@ -1,44 +1,370 @@
 // tools, functions, utilities, etc
 
-use ckb_hash::{Blake2b, Blake2bBuilder};
-use sparse_merkle_tree::default_store::DefaultStore;
-use sparse_merkle_tree::traits::{Hasher, Value};
-use sparse_merkle_tree::{SparseMerkleTree, H256};
+use aggregator::{
+    batch::{Aggregator, LiveCell},
+    state::IssuerState,
+};
+use ckb_testtool::{builtin::ALWAYS_SUCCESS, context::Context};
+use ckb_types::{
+    bytes::Bytes,
+    core::{Cycle, TransactionBuilder, TransactionView},
+    packed::*,
+    prelude::*,
+};
+use mol::{
+    NftIds, NftTransaction, NftTransactionVec, NftValues, RawDistributeTransaction,
+    RawIssueTransaction, SmtProof, SmtUpdateAction, Targets,
+};
+use script_utils::{
+    class::Class,
+    helpers::{
+        CLASS_CELL, DISTRIBUTE_TRANSACTION, ISSUER_CELL, ISSUE_TRANSACTION, NFT_CELL, NFT_SET_CELL,
+    },
+    issuer::Issuer,
+    misc::{NftValue, SMT},
+    nft::Nft,
+    nft_set::NftSet,
+    type_args::TypeArgs,
+};
+use sparse_merkle_tree::traits::Value;
+use sparse_merkle_tree::H256;
+use std::path::{Path, PathBuf};
 
+use crate::Loader;
 
-pub const BLAKE2B_KEY: &[u8] = &[];
-pub const BLAKE2B_LEN: usize = 32;
-pub const PERSONALIZATION: &[u8] = b"ckb-default-hash";
+// the capacity of the issuer cells of the aggregators, which pays the extracted cells
+pub const ISSUER_CAPACITY: u64 = 1_000 * 100_000_000;
 
-pub struct CKBBlake2bHasher(Blake2b);
+// the tree shares the hasher and leaf encoding with the contract
+pub fn new_smt(pairs: Vec<(H256, Vec<u8>)>) -> SMT {
+    let mut smt = SMT::default();
+    for (key, value) in pairs {
+        smt.update(key, NftValue(value)).unwrap();
+    }
+    smt
+}
 
-impl Default for CKBBlake2bHasher {
-    fn default() -> Self {
-        let blake2b = Blake2bBuilder::new(BLAKE2B_LEN)
-            .personal(PERSONALIZATION)
-            .key(BLAKE2B_KEY)
-            .build();
-        CKBBlake2bHasher(blake2b)
+/// Apply the leaf updates to the smt and return the compiled proof of the touched keys,
+/// an empty leaf removes the key from the tree
+pub fn update_smt(smt: &mut SMT, updates: Vec<(H256, Vec<u8>)>) -> Vec<u8> {
+    let keys: Vec<H256> = updates.iter().map(|(key, _)| *key).collect();
+    let leaves: Vec<(H256, H256)> = keys
+        .iter()
+        .map(|key| (*key, smt.get(key).unwrap().to_h256()))
+        .collect();
+    let proof = smt.merkle_proof(keys).unwrap().compile(leaves).unwrap().0;
+    for (key, value) in updates {
+        smt.update(key, NftValue(value)).unwrap();
     }
+    proof
+}
+
+pub struct TestContext {
+    pub context: Context,
+    contract: OutPoint,
+    always_success: OutPoint,
 }
 
-impl Hasher for CKBBlake2bHasher {
-    fn write_h256(&mut self, h: &H256) {
-        self.0.update(h.as_slice());
+impl TestContext {
+    pub fn new() -> Self {
+        let mut context = Context::default();
+        let contract_bin: Bytes = Loader::default().load_binary("compact-nft");
+        let contract = context.deploy_cell(contract_bin);
+        let always_success = context.deploy_cell(ALWAYS_SUCCESS.clone());
+        TestContext {
+            context,
+            contract,
+            always_success,
+        }
+    }
+
+    pub fn lock_script(&mut self, args: Bytes) -> Script {
+        self.context
+            .build_script(&self.always_success, args)
+            .expect("script")
     }
-    fn finish(self) -> H256 {
-        let mut hash = [0u8; 32];
-        self.0.finalize(&mut hash);
-        hash.into()
+
+    pub fn type_script(&mut self, args: Vec<u8>) -> Script {
+        self.context
+            .build_script(&self.contract, Bytes::from(args))
+            .expect("script")
+    }
+
+    pub fn create_input(&mut self, lock: Script, type_: Option<Script>, data: Bytes) -> CellInput {
+        let out_point = self.context.create_cell(cell_output(lock, type_), data);
+        CellInput::new_builder().previous_output(out_point).build()
+    }
+
+    pub fn complete_tx(&mut self, builder: TransactionBuilder) -> TransactionView {
+        self.context.complete_tx(builder.build())
+    }
+
+    pub fn verify_tx(&self, tx: &TransactionView) -> Result<Cycle, ckb_error::Error> {
+        self.context.verify_tx(tx, super::MAX_CYCLES)
     }
 }
 
-pub type SMT = SparseMerkleTree<CKBBlake2bHasher, Vec<u8>, DefaultStore<Vec<u8>>>;
+pub fn cell_output(lock: Script, type_: Option<Script>) -> CellOutput {
+    CellOutput::new_builder()
+        .capacity(1000u64.pack())
+        .lock(lock)
+        .type_(type_.pack())
+        .build()
+}
 
-pub fn new_smt(pairs: Vec<(H256, Vec<u8>)>) -> SMT {
-    let mut smt = SMT::default();
-    for (key, value) in pairs {
-        smt.update(key, value).unwrap();
+pub fn lock_hash(lock: &Script) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(lock.calc_script_hash().as_slice());
+    hash
+}
+
+// cell type + issuer data in cell
+pub fn issuer_data(class_count: u32, set_count: u32, root: &H256) -> Bytes {
+    let mut smt_root = [0u8; 32];
+    smt_root.copy_from_slice(root.as_slice());
+    let issuer = Issuer {
+        version: 0,
+        class_count,
+        set_count,
+        smt_root,
+        info: Vec::new(),
+    };
+    let mut data = vec![ISSUER_CELL];
+    data.extend_from_slice(&issuer.to_data().unwrap());
+    Bytes::from(data)
+}
+
+// the issuer_id, class_id and owner of a class cell are in its type args and lock
+pub fn class_cell_data(class: &Class) -> Bytes {
+    let mut data = vec![CLASS_CELL];
+    data.extend_from_slice(&class.to_data(true).unwrap());
+    Bytes::from(data)
+}
+
+// the ids and owner of a nft cell are in its type args and lock
+pub fn nft_cell_data(nft: &Nft) -> Bytes {
+    let mut data = vec![NFT_CELL];
+    data.extend_from_slice(&nft.to_data(true));
+    Bytes::from(data)
+}
+
+pub fn set_cell_data(set: &NftSet) -> Bytes {
+    let mut data = vec![NFT_SET_CELL];
+    data.extend_from_slice(&set.to_data(true).unwrap());
+    Bytes::from(data)
+}
+
+pub fn class_type_args(class: &Class) -> Vec<u8> {
+    let mut args = class.issuer_id.to_vec();
+    args.extend_from_slice(&class.class_id.to_be_bytes());
+    args
+}
+
+pub fn nft_type_args(nft: &Nft) -> Vec<u8> {
+    let mut args = nft.issuer_id.to_vec();
+    args.extend_from_slice(&nft.class_id.to_be_bytes());
+    args.extend_from_slice(&nft.token_id.to_be_bytes());
+    args
+}
+
+pub fn set_type_args(set: &NftSet) -> Vec<u8> {
+    TypeArgs::from_set(set).to_vec()
+}
+
+pub fn nft_transaction(typ: u8, transaction: &[u8], signature: &[u8]) -> NftTransaction {
+    NftTransaction::new_builder()
+        .typ(Byte::new(typ))
+        .transaction(transaction.pack())
+        .signature(signature.pack())
+        .build()
+}
+
+// the class issued by it is the one built by new_class
+pub fn issue_transaction(owner: [u8; 32]) -> RawIssueTransaction {
+    RawIssueTransaction::new_builder()
+        .total(100u32.pack())
+        .configure(Byte::new(0))
+        .name(b"class".pack())
+        .description(b"a class of nfts".pack())
+        .to(owner.pack())
+        .build()
+}
+
+// NftTransactionVec is placed in the lock and SmtUpdateAction in the input_type
+pub fn smt_witness(txs: Vec<NftTransaction>, proof: &[u8]) -> Bytes {
+    let txs = NftTransactionVec::new_builder().set(txs).build();
+    let action = SmtUpdateAction::new_builder()
+        .proof(
+            SmtProof::new_builder()
+                .set(proof.iter().map(|byte| Byte::new(*byte)).collect())
+                .build(),
+        )
+        .build();
+    WitnessArgs::new_builder()
+        .lock(Some(txs.as_bytes()).pack())
+        .input_type(Some(action.as_bytes()).pack())
+        .build()
+        .as_bytes()
+}
+
+/// An issuer cell and the tree it commits to, the fields are updated by the tests
+/// to build the output issuer cell
+pub struct IssuerCell {
+    pub issuer_id: [u8; 20],
+    pub lock: Script,
+    pub type_: Script,
+    pub class_count: u32,
+    pub set_count: u32,
+    pub smt: SMT,
+}
+
+impl IssuerCell {
+    pub fn new(
+        ctx: &mut TestContext,
+        class_count: u32,
+        set_count: u32,
+        leaves: Vec<(H256, Vec<u8>)>,
+    ) -> Self {
+        let mut issuer_id = [0u8; 20];
+        issuer_id.copy_from_slice(&super::random_20bytes());
+        let lock = ctx.lock_script(super::random_20bytes());
+        let type_ = ctx.type_script(issuer_id.to_vec());
+        IssuerCell {
+            issuer_id,
+            lock,
+            type_,
+            class_count,
+            set_count,
+            smt: new_smt(leaves),
+        }
     }
-    smt
+
+    pub fn data(&self) -> Bytes {
+        issuer_data(self.class_count, self.set_count, self.smt.root())
+    }
+
+    pub fn output(&self) -> CellOutput {
+        cell_output(self.lock.clone(), Some(self.type_.clone()))
+    }
+
+    // consume the issuer cell in its current state
+    pub fn input(&self, ctx: &mut TestContext) -> CellInput {
+        ctx.create_input(self.lock.clone(), Some(self.type_.clone()), self.data())
+    }
+}
+
+pub fn new_class(issuer_id: &[u8; 20], class_id: u32, owner: [u8; 32]) -> Class {
+    Class {
+        version: 0,
+        issuer_id: *issuer_id,
+        class_id,
+        total: 100,
+        issued: 0,
+        burned: 0,
+        configure: 0,
+        owner,
+        name: b"class".to_vec(),
+        description: b"a class of nfts".to_vec(),
+        renderer: Vec::new(),
+        ext_info: Vec::new(),
+    }
+}
+
+pub fn new_nft(issuer_id: &[u8; 20], class_id: u32, token_id: u32, owner: [u8; 32]) -> Nft {
+    Nft {
+        version: 0,
+        issuer_id: *issuer_id,
+        class_id,
+        token_id,
+        characteristic: [0u8; 8],
+        configure: 0,
+        state: 0,
+        nonce: 0,
+        owner,
+        ext_info: Vec::new(),
+    }
+}
+
+pub fn nft_ids(keys: Vec<H256>) -> NftIds {
+    NftIds::new_builder()
+        .set(
+            keys.iter()
+                .map(|key| Byte32::from_slice(key.as_slice()).unwrap())
+                .collect(),
+        )
+        .build()
+}
+
+pub fn nft_values(leaves: Vec<Vec<u8>>) -> NftValues {
+    NftValues::new_builder()
+        .set(leaves.iter().map(|leaf| leaf.pack()).collect())
+        .build()
+}
+
+fn random_name() -> String {
+    super::random_20bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// a new file for the store of an issuer state
+pub fn store_path() -> PathBuf {
+    std::env::temp_dir().join(format!("issuer-{}.log", random_name()))
+}
+
+/// A new directory for the stores of a service, removed with its content when dropped
+pub struct StoreDir(PathBuf);
+
+impl StoreDir {
+    pub fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("stores-{}", random_name()));
+        std::fs::create_dir_all(&path).unwrap();
+        StoreDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for StoreDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+// an aggregator of the issuer cell with an empty store, and the path of the store
+pub fn new_aggregator(ctx: &mut TestContext, issuer: &IssuerCell) -> (Aggregator, PathBuf) {
+    let path = store_path();
+    let state = IssuerState::open(&path, issuer.issuer_id).unwrap();
+    let output = issuer
+        .output()
+        .as_builder()
+        .capacity(ISSUER_CAPACITY.pack())
+        .build();
+    let issuer_cell = LiveCell {
+        out_point: ctx.context.create_cell(output.clone(), issuer.data()),
+        output,
+        data: issuer.data(),
+    };
+    let aggregator = Aggregator::new(state, issuer_cell, vec![]).unwrap();
+    (aggregator, path)
+}
+
+// issue a class owned by the issuer lock and distribute it to the targets, the
+// class_old_value only carries the ids of the class, the aggregator fills the leaf
+pub fn issue_and_distribute(issuer: &IssuerCell, targets: Vec<[u8; 32]>) -> Vec<NftTransaction> {
+    let issue_tx = issue_transaction(lock_hash(&issuer.lock));
+    let class = new_class(&issuer.issuer_id, 0, lock_hash(&issuer.lock));
+    let distribute_tx = RawDistributeTransaction::new_builder()
+        .class_old_value(class.to_leaf_data().unwrap().pack())
+        .to(Targets::new_builder()
+            .set(targets.iter().map(|target| target.pack()).collect())
+            .build())
+        .build();
+    vec![
+        nft_transaction(ISSUE_TRANSACTION, issue_tx.as_slice(), &[]),
+        nft_transaction(DISTRIBUTE_TRANSACTION, distribute_tx.as_slice(), &[]),
+    ]
 }
@@ -3,7 +3,17 @@ use ckb_testtool::bytes::Bytes;
 use ckb_types::prelude::{Builder, Entity};
 use rand::{thread_rng, Rng};
 
+mod batch;
+mod class;
+mod data;
+mod indexer;
+mod issuer;
 mod misc;
+mod nft;
+mod owner;
+mod service;
+mod state;
+mod transaction;
 
 const MAX_CYCLES: u64 = 1_000_000_000;
 
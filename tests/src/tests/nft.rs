@@ -0,0 +1,367 @@
+use super::misc::*;
+use super::*;
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    bytes::Bytes,
+    core::{TransactionBuilder, TransactionView},
+    prelude::*,
+};
+use script_utils::{error::Error, nft::Nft};
+
+const CLAIMED: u8 = 0b0000_0001;
+const LOCKED: u8 = 0b0000_0010;
+
+fn random_nft(state: u8, configure: u8) -> Nft {
+    let mut issuer_id = [0u8; 20];
+    issuer_id.copy_from_slice(&random_20bytes());
+    let mut nft = new_nft(&issuer_id, 0, 0, [0u8; 32]);
+    nft.characteristic = [7u8; 8];
+    nft.state = state;
+    nft.configure = configure;
+    nft
+}
+
+// update a standalone nft cell, the cell is transferred to a new lock if transferred is set
+fn update_nft_tx(
+    ctx: &mut TestContext,
+    input_nft: &Nft,
+    output_nft: &Nft,
+    transferred: bool,
+) -> TransactionView {
+    let input_lock = ctx.lock_script(random_20bytes());
+    let output_lock = if transferred {
+        ctx.lock_script(random_20bytes())
+    } else {
+        input_lock.clone()
+    };
+    let nft_type = ctx.type_script(nft_type_args(input_nft));
+    let input = ctx.create_input(input_lock, Some(nft_type.clone()), nft_cell_data(input_nft));
+    ctx.complete_tx(
+        TransactionBuilder::default()
+            .input(input)
+            .output(cell_output(output_lock, Some(nft_type)))
+            .output_data(nft_cell_data(output_nft).pack()),
+    )
+}
+
+// destroy a standalone nft cell, the class cell of the nft adds burned to its burned count
+// unless there is no class cell
+fn destroy_nft_tx(ctx: &mut TestContext, nft: &Nft, burned: Option<u32>) -> TransactionView {
+    let lock = ctx.lock_script(random_20bytes());
+    let nft_type = ctx.type_script(nft_type_args(nft));
+    let input = ctx.create_input(lock.clone(), Some(nft_type), nft_cell_data(nft));
+    let mut builder = TransactionBuilder::default()
+        .input(input)
+        .output(cell_output(lock, None))
+        .output_data(Bytes::new().pack());
+
+    if let Some(burned) = burned {
+        let mut class = new_class(&nft.issuer_id, nft.class_id, [0u8; 32]);
+        class.issued = nft.token_id + 1;
+        let class_lock = ctx.lock_script(random_20bytes());
+        let class_type = ctx.type_script(class_type_args(&class));
+        let class_input = ctx.create_input(
+            class_lock.clone(),
+            Some(class_type.clone()),
+            class_cell_data(&class),
+        );
+        let mut output_class = class.clone();
+        output_class.burned += burned;
+        builder = builder
+            .input(class_input)
+            .output(cell_output(class_lock, Some(class_type)))
+            .output_data(class_cell_data(&output_class).pack())
+            .witness(Bytes::new().pack())
+            .witness(smt_witness(vec![], &[]).pack());
+    }
+    ctx.complete_tx(builder)
+}
+
+fn assert_nft_error(ctx: &TestContext, tx: &TransactionView, error: i8) {
+    let err = ctx.verify_tx(tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(error).input_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_modify_nft_cell_characteristic_error() {
+    let mut ctx = TestContext::new();
+    let input_nft = random_nft(0, 0);
+    let mut output_nft = input_nft.clone();
+    output_nft.characteristic = [8u8; 8];
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, false);
+
+    assert_nft_error(&ctx, &tx, Error::NFTCharacteristicNotSame as i8);
+}
+
+#[test]
+fn test_modify_nft_cell_configure_error() {
+    let mut ctx = TestContext::new();
+    let input_nft = random_nft(0, 0);
+    let mut output_nft = input_nft.clone();
+    output_nft.configure = 0b0000_0001;
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, false);
+
+    assert_nft_error(&ctx, &tx, Error::NFTConfigureNotSame as i8);
+}
+
+#[test]
+fn test_claim_nft_cell_success() {
+    let mut ctx = TestContext::new();
+    let input_nft = random_nft(0, 0);
+    let mut output_nft = input_nft.clone();
+    output_nft.state = CLAIMED;
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, false);
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_claim_nft_cell_with_disallowed_configure_error() {
+    let mut ctx = TestContext::new();
+    let input_nft = random_nft(0, 0b0000_0001);
+    let mut output_nft = input_nft.clone();
+    output_nft.state = CLAIMED;
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, false);
+
+    assert_nft_error(&ctx, &tx, Error::NFTDisallowClaimed as i8);
+}
+
+#[test]
+fn test_claim_locked_nft_cell_error() {
+    let mut ctx = TestContext::new();
+    let input_nft = random_nft(LOCKED, 0);
+    let mut output_nft = input_nft.clone();
+    output_nft.state = LOCKED | CLAIMED;
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, false);
+
+    assert_nft_error(&ctx, &tx, Error::LockedNFTCannotClaim as i8);
+}
+
+#[test]
+fn test_unclaim_nft_cell_error() {
+    let mut ctx = TestContext::new();
+    let input_nft = random_nft(CLAIMED, 0);
+    let mut output_nft = input_nft.clone();
+    output_nft.state = 0;
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, false);
+
+    assert_nft_error(&ctx, &tx, Error::NFTClaimedToUnclaimedError as i8);
+}
+
+#[test]
+fn test_lock_nft_cell_success() {
+    let mut ctx = TestContext::new();
+    let input_nft = random_nft(0, 0);
+    let mut output_nft = input_nft.clone();
+    output_nft.state = LOCKED;
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, false);
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_lock_nft_cell_with_disallowed_configure_error() {
+    let mut ctx = TestContext::new();
+    let input_nft = random_nft(0, 0b0000_0010);
+    let mut output_nft = input_nft.clone();
+    output_nft.state = LOCKED;
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, false);
+
+    assert_nft_error(&ctx, &tx, Error::NFTDisallowLocked as i8);
+}
+
+#[test]
+fn test_unlock_nft_cell_error() {
+    let mut ctx = TestContext::new();
+    let input_nft = random_nft(LOCKED, 0);
+    let mut output_nft = input_nft.clone();
+    output_nft.state = 0;
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, false);
+
+    assert_nft_error(&ctx, &tx, Error::NFTLockedToUnlockedError as i8);
+}
+
+#[test]
+fn test_transfer_nft_cell_success() {
+    let mut ctx = TestContext::new();
+    let input_nft = random_nft(0, 0);
+    let mut output_nft = input_nft.clone();
+    output_nft.nonce = 1;
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, true);
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_transfer_nft_cell_without_nonce_error() {
+    let mut ctx = TestContext::new();
+    let input_nft = random_nft(0, 0);
+    let output_nft = input_nft.clone();
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, true);
+
+    assert_nft_error(&ctx, &tx, Error::NFTNonceInvalid as i8);
+}
+
+#[test]
+fn test_transfer_locked_nft_cell_error() {
+    let mut ctx = TestContext::new();
+    let input_nft = random_nft(LOCKED, 0);
+    let mut output_nft = input_nft.clone();
+    output_nft.nonce = 1;
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, true);
+
+    assert_nft_error(&ctx, &tx, Error::LockedNFTCannotTransfer as i8);
+}
+
+#[test]
+fn test_transfer_nft_cell_before_claim_with_disallowed_configure_error() {
+    let mut ctx = TestContext::new();
+    let input_nft = random_nft(0, 0b0001_0000);
+    let mut output_nft = input_nft.clone();
+    output_nft.nonce = 1;
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, true);
+
+    assert_nft_error(&ctx, &tx, Error::NFTCannotTransferBeforeClaim as i8);
+}
+
+#[test]
+fn test_transfer_nft_cell_after_claim_with_disallowed_configure_error() {
+    let mut ctx = TestContext::new();
+    let input_nft = random_nft(CLAIMED, 0b0010_0000);
+    let mut output_nft = input_nft.clone();
+    output_nft.nonce = 1;
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, true);
+
+    assert_nft_error(&ctx, &tx, Error::NFTCannotTransferAfterClaim as i8);
+}
+
+#[test]
+fn test_add_nft_cell_ext_info_success() {
+    let mut ctx = TestContext::new();
+    let mut input_nft = random_nft(0, 0);
+    input_nft.ext_info = b"hello".to_vec();
+    let mut output_nft = input_nft.clone();
+    output_nft.ext_info = b"hello world".to_vec();
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, false);
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_modify_nft_cell_ext_info_error() {
+    let mut ctx = TestContext::new();
+    let mut input_nft = random_nft(0, 0);
+    input_nft.ext_info = b"hello".to_vec();
+    let mut output_nft = input_nft.clone();
+    output_nft.ext_info = b"world".to_vec();
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, false);
+
+    assert_nft_error(&ctx, &tx, Error::NFTExtInfoCannotModify as i8);
+}
+
+#[test]
+fn test_shrink_nft_cell_ext_info_error() {
+    let mut ctx = TestContext::new();
+    let mut input_nft = random_nft(0, 0);
+    input_nft.ext_info = b"hello".to_vec();
+    let mut output_nft = input_nft.clone();
+    output_nft.ext_info = b"he".to_vec();
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, false);
+
+    assert_nft_error(&ctx, &tx, Error::NFTExtInfoLenError as i8);
+}
+
+#[test]
+fn test_add_locked_nft_cell_ext_info_error() {
+    let mut ctx = TestContext::new();
+    let mut input_nft = random_nft(LOCKED, 0);
+    input_nft.ext_info = b"hello".to_vec();
+    let mut output_nft = input_nft.clone();
+    output_nft.ext_info = b"hello world".to_vec();
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, false);
+
+    assert_nft_error(&ctx, &tx, Error::LockedNFTCannotAddExtInfo as i8);
+}
+
+#[test]
+fn test_modify_nft_cell_ext_info_with_disallowed_configure_error() {
+    let mut ctx = TestContext::new();
+    let mut input_nft = random_nft(0, 0b0000_0100);
+    input_nft.ext_info = b"hello".to_vec();
+    let mut output_nft = input_nft.clone();
+    output_nft.ext_info = b"world".to_vec();
+    let tx = update_nft_tx(&mut ctx, &input_nft, &output_nft, false);
+
+    assert_nft_error(&ctx, &tx, Error::NFTExtInfoCannotModify as i8);
+}
+
+#[test]
+fn test_destroy_nft_cell_success() {
+    let mut ctx = TestContext::new();
+    let nft = random_nft(CLAIMED, 0);
+    let tx = destroy_nft_tx(&mut ctx, &nft, Some(1));
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_destroy_locked_nft_cell_error() {
+    let mut ctx = TestContext::new();
+    let nft = random_nft(LOCKED, 0);
+    let tx = destroy_nft_tx(&mut ctx, &nft, Some(1));
+
+    assert_nft_error(&ctx, &tx, Error::LockedNFTCannotDestroy as i8);
+}
+
+#[test]
+fn test_destroy_nft_cell_before_claim_with_disallowed_configure_error() {
+    let mut ctx = TestContext::new();
+    let nft = random_nft(0, 0b0100_0000);
+    let tx = destroy_nft_tx(&mut ctx, &nft, Some(1));
+
+    assert_nft_error(&ctx, &tx, Error::NFTCannotDestroyBeforeClaim as i8);
+}
+
+#[test]
+fn test_destroy_nft_cell_after_claim_with_disallowed_configure_error() {
+    let mut ctx = TestContext::new();
+    let nft = random_nft(CLAIMED, 0b1000_0000);
+    let tx = destroy_nft_tx(&mut ctx, &nft, Some(1));
+
+    assert_nft_error(&ctx, &tx, Error::NFTCannotDestroyAfterClaim as i8);
+}
+
+#[test]
+fn test_destroy_nft_cell_without_class_cell_error() {
+    let mut ctx = TestContext::new();
+    let nft = random_nft(CLAIMED, 0);
+    let tx = destroy_nft_tx(&mut ctx, &nft, None);
+
+    assert_nft_error(&ctx, &tx, Error::ClassCellsCountError as i8);
+}
+
+#[test]
+fn test_destroy_nft_cell_without_burned_error() {
+    let mut ctx = TestContext::new();
+    let nft = random_nft(CLAIMED, 0);
+    let tx = destroy_nft_tx(&mut ctx, &nft, Some(0));
+
+    // the nft cell is fine, the class cell at input 1 doesn't count the burn
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 1;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::ClassBurnedInvalid as i8)
+            .input_type_script(script_cell_index)
+    );
+}
@@ -0,0 +1,553 @@
+use super::misc::*;
+use super::*;
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    bytes::Bytes,
+    core::{TransactionBuilder, TransactionView},
+    packed::*,
+    prelude::*,
+};
+use mol::{
+    RawExtractTransaction, RawInsertTransaction, RawTransferTransaction, RawUpdateTransaction,
+    Targets, EXTDATAS,
+};
+use script_utils::{
+    error::Error,
+    helpers::{
+        EXTRACT_TRANSACTION, INSERT_TRANSACTION, MULTI_OWNER_CELL, NFT_ITEM, SINGLE_OWNER_CELL,
+        TRANSFER_TRANSACTION, UPDATE_TRANSACTION,
+    },
+    misc::SMT,
+    nft::Nft,
+};
+use sparse_merkle_tree::H256;
+
+// create an empty owner cell, its type args are made from the type id
+fn create_owner_tx(
+    ctx: &mut TestContext,
+    cell_type: u8,
+    type_args: fn(&[u8]) -> Vec<u8>,
+) -> TransactionView {
+    let lock_script = ctx.lock_script(random_20bytes());
+    let input_out_point = ctx
+        .context
+        .create_cell(cell_output(lock_script.clone(), None), Bytes::new());
+    let input = CellInput::new_builder()
+        .previous_output(input_out_point.clone())
+        .build();
+
+    let type_id = calculate_type_id(input_out_point);
+    let owner_type_script = ctx.type_script(type_args(&type_id));
+    let mut data = vec![cell_type, 0];
+    data.extend_from_slice(&[0u8; 32]);
+
+    ctx.complete_tx(
+        TransactionBuilder::default()
+            .input(input)
+            .output(cell_output(lock_script, Some(owner_type_script)))
+            .output_data(Bytes::from(data).pack()),
+    )
+}
+
+fn assert_creation_error(ctx: &TestContext, tx: &TransactionView, error: i8) {
+    let err = ctx.verify_tx(tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(error).output_type_script(script_cell_index)
+    );
+}
+
+#[test]
+fn test_create_single_owner_cell_success() {
+    let mut ctx = TestContext::new();
+    let tx = create_owner_tx(&mut ctx, SINGLE_OWNER_CELL, |type_id| type_id.to_vec());
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_create_multi_owner_cell_success() {
+    let mut ctx = TestContext::new();
+    let tx = create_owner_tx(&mut ctx, MULTI_OWNER_CELL, |type_id| type_id.to_vec());
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_create_single_owner_cell_with_wrong_type_args_error() {
+    let mut ctx = TestContext::new();
+    let tx = create_owner_tx(&mut ctx, SINGLE_OWNER_CELL, |_| random_32bytes().to_vec());
+
+    assert_creation_error(&ctx, &tx, Error::TypeArgsInvalid as i8);
+}
+
+#[test]
+fn test_create_multi_owner_cell_with_issuer_type_args_error() {
+    let mut ctx = TestContext::new();
+    // a valid type id prefix, but the args would be taken for the args of an issuer
+    let tx = create_owner_tx(&mut ctx, MULTI_OWNER_CELL, |type_id| type_id[..20].to_vec());
+
+    assert_creation_error(&ctx, &tx, Error::TypeArgsInvalid as i8);
+}
+
+/// An owner cell and the tree of nft leaves it commits to, the tree is updated by the
+/// tests to build the output owner cell
+struct OwnerCell {
+    cell_type: u8,
+    lock: Script,
+    type_: Script,
+    smt: SMT,
+}
+
+impl OwnerCell {
+    fn new(ctx: &mut TestContext, cell_type: u8, nfts: &[Nft]) -> Self {
+        let lock = ctx.lock_script(random_20bytes());
+        let type_ = ctx.type_script(random_32bytes().to_vec());
+        OwnerCell {
+            cell_type,
+            lock,
+            type_,
+            smt: new_smt(
+                nfts.iter()
+                    .map(|nft| (nft.to_key(), nft.to_leaf_data()))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn data(&self) -> Bytes {
+        let mut data = vec![self.cell_type, 0];
+        data.extend_from_slice(self.smt.root().as_slice());
+        Bytes::from(data)
+    }
+
+    // consume the owner cell in its current state
+    fn input(&self, ctx: &mut TestContext) -> CellInput {
+        ctx.create_input(self.lock.clone(), Some(self.type_.clone()), self.data())
+    }
+}
+
+/// One batch of nft transactions on the tree of an owner cell
+struct OwnerTx<'a> {
+    ctx: &'a mut TestContext,
+    owner: &'a mut OwnerCell,
+    output_lock: Script,
+    inputs: Vec<CellInput>,
+    outputs: Vec<(CellOutput, Bytes)>,
+}
+
+impl<'a> OwnerTx<'a> {
+    fn new(ctx: &'a mut TestContext, owner: &'a mut OwnerCell) -> Self {
+        let inputs = vec![owner.input(ctx)];
+        let output_lock = owner.lock.clone();
+        OwnerTx {
+            ctx,
+            owner,
+            output_lock,
+            inputs,
+            outputs: Vec::new(),
+        }
+    }
+
+    // authorize the transactions by the lock of an input cell
+    fn authorize(mut self, lock: Script) -> Self {
+        let input = self.ctx.create_input(lock, None, Bytes::new());
+        self.inputs.push(input);
+        self
+    }
+
+    fn input(mut self, lock: Script, type_args: Vec<u8>, data: Bytes) -> Self {
+        let type_ = self.ctx.type_script(type_args);
+        let input = self.ctx.create_input(lock, Some(type_), data);
+        self.inputs.push(input);
+        self
+    }
+
+    fn output(mut self, lock: Script, type_args: Vec<u8>, data: Bytes) -> Self {
+        let type_ = self.ctx.type_script(type_args);
+        self.outputs.push((cell_output(lock, Some(type_)), data));
+        self
+    }
+
+    // hand the owner cell to another lock
+    fn output_lock(mut self, lock: Script) -> Self {
+        self.output_lock = lock;
+        self
+    }
+
+    fn build(self, typ: u8, transaction: &[u8], leaves: Vec<(H256, Vec<u8>)>) -> TransactionView {
+        let proof = update_smt(&mut self.owner.smt, leaves);
+        let witness = smt_witness(vec![nft_transaction(typ, transaction, &[])], &proof);
+        let mut builder = TransactionBuilder::default()
+            .inputs(self.inputs)
+            .output(cell_output(
+                self.output_lock,
+                Some(self.owner.type_.clone()),
+            ))
+            .output_data(self.owner.data().pack())
+            .witness(witness.pack());
+        for (output, data) in self.outputs {
+            builder = builder.output(output).output_data(data.pack());
+        }
+        self.ctx.complete_tx(builder)
+    }
+}
+
+fn destroy_owner_tx(ctx: &mut TestContext, owner: &OwnerCell) -> TransactionView {
+    let input = owner.input(ctx);
+    ctx.complete_tx(
+        TransactionBuilder::default()
+            .input(input)
+            .output(cell_output(owner.lock.clone(), None))
+            .output_data(Bytes::new().pack()),
+    )
+}
+
+fn assert_owner_error(ctx: &TestContext, tx: &TransactionView, error: i8) {
+    let err = ctx.verify_tx(tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(error).input_type_script(script_cell_index)
+    );
+}
+
+fn claim_transaction(nft: &Nft) -> (RawUpdateTransaction, Vec<u8>) {
+    let update_tx = RawUpdateTransaction::new_builder()
+        .typ(Byte::new(NFT_ITEM))
+        .nft_ids(nft_ids(vec![nft.to_key()]))
+        .nft_old_values(nft_values(vec![nft.to_leaf_data()]))
+        .state(vec![1u8].pack())
+        .ext_datas(
+            EXTDATAS::new_builder()
+                .push(nft.ext_info.as_slice().pack())
+                .build(),
+        )
+        .build();
+    let mut new_nft = nft.clone();
+    new_nft.state = 1;
+    (update_tx, new_nft.to_leaf_data())
+}
+
+fn transfer_transaction(nft: &Nft, to: [u8; 32]) -> (RawTransferTransaction, Vec<u8>) {
+    let transfer_tx = RawTransferTransaction::new_builder()
+        .typ(Byte::new(NFT_ITEM))
+        .nft_ids(nft_ids(vec![nft.to_key()]))
+        .nft_old_values(nft_values(vec![nft.to_leaf_data()]))
+        .to(Targets::new_builder().push(to.pack()).build())
+        .build();
+    let mut new_nft = nft.clone();
+    new_nft.owner = to;
+    new_nft.nonce += 1;
+    (transfer_tx, new_nft.to_leaf_data())
+}
+
+fn extract_transaction(nft: &Nft) -> RawExtractTransaction {
+    RawExtractTransaction::new_builder()
+        .typ(Byte::new(NFT_ITEM))
+        .nft_ids(nft_ids(vec![nft.to_key()]))
+        .nft_old_values(nft_values(vec![nft.to_leaf_data()]))
+        .build()
+}
+
+fn insert_transaction(nft: &Nft) -> RawInsertTransaction {
+    RawInsertTransaction::new_builder()
+        .typ(Byte::new(NFT_ITEM))
+        .nft_ids(nft_ids(vec![nft.to_key()]))
+        .build()
+}
+
+// a single owner cell holding one nft leaf, which belongs to the lock of the cell
+fn single_owner_with_nft(ctx: &mut TestContext) -> (OwnerCell, Nft) {
+    let mut owner = OwnerCell::new(ctx, SINGLE_OWNER_CELL, &[]);
+    let nft = new_nft(&[1u8; 20], 0, 0, lock_hash(&owner.lock));
+    owner.smt = new_smt(vec![(nft.to_key(), nft.to_leaf_data())]);
+    (owner, nft)
+}
+
+#[test]
+fn test_update_single_owner_cell_success() {
+    let mut ctx = TestContext::new();
+    let (mut owner, nft) = single_owner_with_nft(&mut ctx);
+    let (update_tx, new_leaf) = claim_transaction(&nft);
+    let tx = OwnerTx::new(&mut ctx, &mut owner).build(
+        UPDATE_TRANSACTION,
+        update_tx.as_slice(),
+        vec![(nft.to_key(), new_leaf)],
+    );
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_update_single_owner_cell_with_other_lock_error() {
+    let mut ctx = TestContext::new();
+    let (mut owner, nft) = single_owner_with_nft(&mut ctx);
+    let (update_tx, new_leaf) = claim_transaction(&nft);
+    let other_lock = ctx.lock_script(random_20bytes());
+    let tx = OwnerTx::new(&mut ctx, &mut owner)
+        .output_lock(other_lock)
+        .build(
+            UPDATE_TRANSACTION,
+            update_tx.as_slice(),
+            vec![(nft.to_key(), new_leaf)],
+        );
+
+    assert_owner_error(&ctx, &tx, Error::SingleOwnerLockNotSame as i8);
+}
+
+#[test]
+fn test_transfer_in_single_owner_cell_error() {
+    let mut ctx = TestContext::new();
+    let (mut owner, nft) = single_owner_with_nft(&mut ctx);
+    let (transfer_tx, new_leaf) = transfer_transaction(&nft, [1u8; 32]);
+    let tx = OwnerTx::new(&mut ctx, &mut owner).build(
+        TRANSFER_TRANSACTION,
+        transfer_tx.as_slice(),
+        vec![(nft.to_key(), new_leaf)],
+    );
+
+    assert_owner_error(&ctx, &tx, Error::TransactionNotSupported as i8);
+}
+
+#[test]
+fn test_extract_from_single_owner_cell_success() {
+    let mut ctx = TestContext::new();
+    let (mut owner, nft) = single_owner_with_nft(&mut ctx);
+    let extract_tx = extract_transaction(&nft);
+    let owner_lock = owner.lock.clone();
+    let tx = OwnerTx::new(&mut ctx, &mut owner)
+        .output(owner_lock, nft_type_args(&nft), nft_cell_data(&nft))
+        .build(
+            EXTRACT_TRANSACTION,
+            extract_tx.as_slice(),
+            vec![(nft.to_key(), vec![])],
+        );
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_insert_into_single_owner_cell_success() {
+    let mut ctx = TestContext::new();
+    let mut owner = OwnerCell::new(&mut ctx, SINGLE_OWNER_CELL, &[]);
+    let nft_lock = ctx.lock_script(random_20bytes());
+    let mut nft = new_nft(&[1u8; 20], 0, 0, lock_hash(&nft_lock));
+    let insert_tx = insert_transaction(&nft);
+    let input_data = nft_cell_data(&nft);
+    // the inserted leaf belongs to the lock of the owner cell
+    nft.owner = lock_hash(&owner.lock);
+    let tx = OwnerTx::new(&mut ctx, &mut owner)
+        .input(nft_lock, nft_type_args(&nft), input_data)
+        .build(
+            INSERT_TRANSACTION,
+            insert_tx.as_slice(),
+            vec![(nft.to_key(), nft.to_leaf_data())],
+        );
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_insert_into_single_owner_cell_with_cell_lock_owner_error() {
+    let mut ctx = TestContext::new();
+    let mut owner = OwnerCell::new(&mut ctx, SINGLE_OWNER_CELL, &[]);
+    let nft_lock = ctx.lock_script(random_20bytes());
+    // the leaf keeps the lock of the consumed nft cell as its owner
+    let nft = new_nft(&[1u8; 20], 0, 0, lock_hash(&nft_lock));
+    let insert_tx = insert_transaction(&nft);
+    let tx = OwnerTx::new(&mut ctx, &mut owner)
+        .input(nft_lock, nft_type_args(&nft), nft_cell_data(&nft))
+        .build(
+            INSERT_TRANSACTION,
+            insert_tx.as_slice(),
+            vec![(nft.to_key(), nft.to_leaf_data())],
+        );
+
+    assert_owner_error(&ctx, &tx, Error::SMTProofVerifyFailed as i8);
+}
+
+#[test]
+fn test_destroy_empty_single_owner_cell_success() {
+    let mut ctx = TestContext::new();
+    let owner = OwnerCell::new(&mut ctx, SINGLE_OWNER_CELL, &[]);
+    let tx = destroy_owner_tx(&mut ctx, &owner);
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_destroy_single_owner_cell_with_nfts_error() {
+    let mut ctx = TestContext::new();
+    let (owner, _) = single_owner_with_nft(&mut ctx);
+    let tx = destroy_owner_tx(&mut ctx, &owner);
+
+    assert_owner_error(&ctx, &tx, Error::SingleOwnerCellCannotDestroyed as i8);
+}
+
+// a multi owner cell holding one nft leaf, which belongs to the nft lock
+fn multi_owner_with_nft(ctx: &mut TestContext, nft_lock: &Script) -> (OwnerCell, Nft) {
+    let nft = new_nft(&[1u8; 20], 0, 0, lock_hash(nft_lock));
+    let owner = OwnerCell::new(ctx, MULTI_OWNER_CELL, &[nft.clone()]);
+    (owner, nft)
+}
+
+#[test]
+fn test_transfer_in_multi_owner_cell_success() {
+    let mut ctx = TestContext::new();
+    let nft_lock = ctx.lock_script(random_20bytes());
+    let (mut owner, nft) = multi_owner_with_nft(&mut ctx, &nft_lock);
+    let (transfer_tx, new_leaf) = transfer_transaction(&nft, [1u8; 32]);
+    let tx = OwnerTx::new(&mut ctx, &mut owner)
+        .authorize(nft_lock)
+        .build(
+            TRANSFER_TRANSACTION,
+            transfer_tx.as_slice(),
+            vec![(nft.to_key(), new_leaf)],
+        );
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_transfer_in_multi_owner_cell_without_authorization_error() {
+    let mut ctx = TestContext::new();
+    let nft_lock = ctx.lock_script(random_20bytes());
+    let (mut owner, nft) = multi_owner_with_nft(&mut ctx, &nft_lock);
+    let (transfer_tx, new_leaf) = transfer_transaction(&nft, [1u8; 32]);
+    // the lock of the multi owner cell doesn't own the leaf
+    let tx = OwnerTx::new(&mut ctx, &mut owner).build(
+        TRANSFER_TRANSACTION,
+        transfer_tx.as_slice(),
+        vec![(nft.to_key(), new_leaf)],
+    );
+
+    assert_owner_error(&ctx, &tx, Error::OwnerNotAuthorized as i8);
+}
+
+#[test]
+fn test_update_in_multi_owner_cell_without_authorization_error() {
+    let mut ctx = TestContext::new();
+    let nft_lock = ctx.lock_script(random_20bytes());
+    let (mut owner, nft) = multi_owner_with_nft(&mut ctx, &nft_lock);
+    let (update_tx, new_leaf) = claim_transaction(&nft);
+    let other_lock = ctx.lock_script(random_20bytes());
+    let tx = OwnerTx::new(&mut ctx, &mut owner)
+        .authorize(other_lock)
+        .build(
+            UPDATE_TRANSACTION,
+            update_tx.as_slice(),
+            vec![(nft.to_key(), new_leaf)],
+        );
+
+    assert_owner_error(&ctx, &tx, Error::OwnerNotAuthorized as i8);
+}
+
+#[test]
+fn test_extract_from_multi_owner_cell_success() {
+    let mut ctx = TestContext::new();
+    let nft_lock = ctx.lock_script(random_20bytes());
+    let (mut owner, nft) = multi_owner_with_nft(&mut ctx, &nft_lock);
+    let extract_tx = extract_transaction(&nft);
+    let tx = OwnerTx::new(&mut ctx, &mut owner)
+        .authorize(nft_lock.clone())
+        .output(nft_lock, nft_type_args(&nft), nft_cell_data(&nft))
+        .build(
+            EXTRACT_TRANSACTION,
+            extract_tx.as_slice(),
+            vec![(nft.to_key(), vec![])],
+        );
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_extract_from_multi_owner_cell_with_other_lock_error() {
+    let mut ctx = TestContext::new();
+    let nft_lock = ctx.lock_script(random_20bytes());
+    let (mut owner, nft) = multi_owner_with_nft(&mut ctx, &nft_lock);
+    let extract_tx = extract_transaction(&nft);
+    // the extracted nft cell must keep the owner of the leaf as its lock
+    let owner_lock = owner.lock.clone();
+    let tx = OwnerTx::new(&mut ctx, &mut owner)
+        .authorize(nft_lock)
+        .output(owner_lock, nft_type_args(&nft), nft_cell_data(&nft))
+        .build(
+            EXTRACT_TRANSACTION,
+            extract_tx.as_slice(),
+            vec![(nft.to_key(), vec![])],
+        );
+
+    assert_owner_error(&ctx, &tx, Error::NFTOwnerNotMatch as i8);
+}
+
+#[test]
+fn test_insert_into_multi_owner_cell_success() {
+    let mut ctx = TestContext::new();
+    let mut owner = OwnerCell::new(&mut ctx, MULTI_OWNER_CELL, &[]);
+    let nft_lock = ctx.lock_script(random_20bytes());
+    // the inserted leaf keeps the lock of the consumed nft cell as its owner
+    let nft = new_nft(&[1u8; 20], 0, 0, lock_hash(&nft_lock));
+    let insert_tx = insert_transaction(&nft);
+    let tx = OwnerTx::new(&mut ctx, &mut owner)
+        .input(nft_lock, nft_type_args(&nft), nft_cell_data(&nft))
+        .build(
+            INSERT_TRANSACTION,
+            insert_tx.as_slice(),
+            vec![(nft.to_key(), nft.to_leaf_data())],
+        );
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_insert_into_multi_owner_cell_with_tree_lock_owner_error() {
+    let mut ctx = TestContext::new();
+    let mut owner = OwnerCell::new(&mut ctx, MULTI_OWNER_CELL, &[]);
+    let nft_lock = ctx.lock_script(random_20bytes());
+    let mut nft = new_nft(&[1u8; 20], 0, 0, lock_hash(&nft_lock));
+    let insert_tx = insert_transaction(&nft);
+    let input_data = nft_cell_data(&nft);
+    // the leaf is given to the lock of the multi owner cell instead of the nft cell
+    nft.owner = lock_hash(&owner.lock);
+    let tx = OwnerTx::new(&mut ctx, &mut owner)
+        .input(nft_lock, nft_type_args(&nft), input_data)
+        .build(
+            INSERT_TRANSACTION,
+            insert_tx.as_slice(),
+            vec![(nft.to_key(), nft.to_leaf_data())],
+        );
+
+    assert_owner_error(&ctx, &tx, Error::SMTProofVerifyFailed as i8);
+}
+
+#[test]
+fn test_destroy_empty_multi_owner_cell_success() {
+    let mut ctx = TestContext::new();
+    let owner = OwnerCell::new(&mut ctx, MULTI_OWNER_CELL, &[]);
+    let tx = destroy_owner_tx(&mut ctx, &owner);
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_destroy_multi_owner_cell_with_nfts_error() {
+    let mut ctx = TestContext::new();
+    let nft_lock = ctx.lock_script(random_20bytes());
+    let (owner, _) = multi_owner_with_nft(&mut ctx, &nft_lock);
+    let tx = destroy_owner_tx(&mut ctx, &owner);
+
+    assert_owner_error(&ctx, &tx, Error::MultiOwnerCellCannotDestroyed as i8);
+}
@@ -0,0 +1,203 @@
+use super::misc::*;
+use super::*;
+use ckb_jsonrpc_types::{Byte32 as JsonByte32, JsonBytes};
+use ckb_types::{bytes::Bytes, core::TransactionBuilder, packed::*, prelude::*};
+use mol::NftTransaction;
+use rpc::{
+    chain::{Chain, MockChain},
+    error::Error,
+    server::rpc_handler,
+    service::{Service, ServiceConfig},
+    types::NftSubmission,
+};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+// a service on a mock chain serving the issuer, its stores are in the directory
+fn new_service(issuer: &IssuerCell) -> (Service<MockChain>, StoreDir) {
+    new_service_with_cells(issuer, vec![])
+}
+
+// the cells are live on the mock chain before the issuer is registered
+fn new_service_with_cells(
+    issuer: &IssuerCell,
+    cells: Vec<(CellOutput, Bytes)>,
+) -> (Service<MockChain>, StoreDir) {
+    let store_dir = StoreDir::new();
+    let config = ServiceConfig {
+        store_dir: store_dir.path().to_path_buf(),
+        cell_deps: vec![],
+        fee: 1000,
+    };
+    let mut service = Service::new(MockChain::default(), config);
+    for (output, data) in cells {
+        service.chain_mut().create_cell(output, data);
+    }
+    let output = issuer
+        .output()
+        .as_builder()
+        .capacity(ISSUER_CAPACITY.pack())
+        .build();
+    let out_point = service.chain_mut().create_cell(output, issuer.data());
+    let issuer_id = service.register_issuer(&out_point).unwrap();
+    assert_eq!(issuer_id, issuer.issuer_id);
+    (service, store_dir)
+}
+
+fn submit_all(service: &mut Service<MockChain>, issuer: &IssuerCell, txs: Vec<NftTransaction>) {
+    let issuer_id = JsonBytes::from_vec(issuer.issuer_id.to_vec());
+    for tx in txs {
+        let submission = NftSubmission {
+            transaction: JsonBytes::from_bytes(tx.as_bytes()),
+            output_locks: vec![],
+            input_cells: vec![],
+        };
+        service.submit_transaction(&issuer_id, submission).unwrap();
+    }
+}
+
+#[test]
+fn test_rpc_service_submit_and_query_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let (mut service, _store_dir) = new_service(&issuer);
+    let issuer_id = JsonBytes::from_vec(issuer.issuer_id.to_vec());
+    let owner = [1u8; 32];
+    let txs = issue_and_distribute(&issuer, vec![owner, owner, [2u8; 32]]);
+    submit_all(&mut service, &issuer, txs);
+
+    // the pool is sent in one batch and committed by the mock chain
+    service.poll().unwrap();
+    assert!(service.sent_transaction(&issuer_id).unwrap().is_some());
+    service.poll().unwrap();
+    assert!(service.sent_transaction(&issuer_id).unwrap().is_none());
+
+    let class = service.get_class(&issuer_id, 0).unwrap().unwrap();
+    assert_eq!(class.issued.value(), 3);
+    let nft = service.get_nft(&issuer_id, 0, 1).unwrap().unwrap();
+    assert_eq!(nft.owner, JsonByte32::new(owner));
+    assert!(service.get_nft(&issuer_id, 0, 3).unwrap().is_none());
+    assert_eq!(service.list_nfts_by_owner(&JsonByte32::new(owner)).len(), 2);
+
+    // the proof of a present and an absent leaf
+    let proof = service
+        .get_proof(
+            &issuer_id,
+            vec![nft.key.clone(), JsonByte32::new([0u8; 32])],
+        )
+        .unwrap();
+    assert_eq!(proof.leaves[0], nft.leaf);
+    assert!(proof.leaves[1].is_empty());
+}
+
+#[test]
+fn test_rpc_service_submit_during_poll_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let (mut service, _store_dir) = new_service(&issuer);
+    let issuer_id = JsonBytes::from_vec(issuer.issuer_id.to_vec());
+    let mut txs = issue_and_distribute(&issuer, vec![[1u8; 32]]);
+    let distribute_tx = txs.pop().unwrap();
+    submit_all(&mut service, &issuer, txs);
+
+    // the poller reaches the chain by a client of its own, the service serves the
+    // submission meanwhile
+    let mut chain = service.chain();
+    let tasks = service.start_poll().unwrap();
+    submit_all(&mut service, &issuer, vec![distribute_tx]);
+    let results = tasks.run(&mut chain);
+    service.finish_poll(results).unwrap();
+
+    // the submission follows the sent batch in the next one
+    for _ in 0..3 {
+        service.poll().unwrap();
+    }
+    assert!(service.sent_transaction(&issuer_id).unwrap().is_none());
+    let class = service.get_class(&issuer_id, 0).unwrap().unwrap();
+    assert_eq!(class.issued.value(), 1);
+}
+
+#[test]
+fn test_rpc_service_index_nft_cells_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let nft = new_nft(&issuer.issuer_id, 0, 0, [0u8; 32]);
+    let nft_type = ctx.type_script(nft_type_args(&nft));
+    let nft_output = cell_output(owner_lock.clone(), Some(nft_type));
+    let (mut service, _store_dir) =
+        new_service_with_cells(&issuer, vec![(nft_output.clone(), nft_cell_data(&nft))]);
+
+    // the nft cell created before the registration is found by the type of the issuer
+    let owner = JsonByte32::new(lock_hash(&owner_lock));
+    let nfts = service.list_nfts_by_owner(&owner);
+    assert_eq!(nfts.len(), 1);
+    let cell: OutPoint = nfts[0].cell.clone().unwrap().into();
+
+    // the nft cell is transferred outside of the batches and found in the scanned blocks
+    let other_lock = ctx.lock_script(random_20bytes());
+    let transfer_tx = TransactionBuilder::default()
+        .input(CellInput::new(cell, 0))
+        .output(nft_output.as_builder().lock(other_lock.clone()).build())
+        .output_data(nft_cell_data(&nft).pack())
+        .build();
+    service.chain_mut().send_transaction(&transfer_tx).unwrap();
+    service.poll().unwrap();
+    assert!(service.list_nfts_by_owner(&owner).is_empty());
+    let nfts = service.list_nfts_by_owner(&JsonByte32::new(lock_hash(&other_lock)));
+    assert_eq!(nfts.len(), 1);
+    assert_eq!(
+        nfts[0].cell.clone().map(OutPoint::from),
+        Some(OutPoint::new(transfer_tx.hash(), 0))
+    );
+}
+
+#[test]
+fn test_rpc_service_unknown_issuer_error() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let (service, _store_dir) = new_service(&issuer);
+
+    let result = service.get_nft(&JsonBytes::from_vec(vec![0u8; 20]), 0, 0);
+    assert!(matches!(result, Err(Error::IssuerNotFound)));
+    let result = service.get_nft(&JsonBytes::from_vec(vec![0u8; 4]), 0, 0);
+    assert!(matches!(result, Err(Error::InvalidParams(_))));
+}
+
+#[test]
+fn test_rpc_handler_get_nft_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let (mut service, _store_dir) = new_service(&issuer);
+    let txs = issue_and_distribute(&issuer, vec![[1u8; 32]]);
+    submit_all(&mut service, &issuer, txs);
+    service.poll().unwrap();
+    service.poll().unwrap();
+    let io = rpc_handler(&Arc::new(Mutex::new(service)));
+
+    let issuer_id = JsonBytes::from_vec(issuer.issuer_id.to_vec());
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "get_nft",
+        "params": [issuer_id, "0x0", "0x0"],
+    });
+    let response: Value =
+        serde_json::from_str(&io.handle_request_sync(&request.to_string()).unwrap()).unwrap();
+    assert_eq!(response["result"]["token_id"], json!("0x0"));
+    assert_eq!(
+        response["result"]["owner"],
+        json!(JsonByte32::new([1u8; 32]))
+    );
+
+    // an unknown issuer is an error of the service
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "get_nft",
+        "params": [JsonBytes::from_vec(vec![0u8; 20]), "0x0", "0x0"],
+    });
+    let response: Value =
+        serde_json::from_str(&io.handle_request_sync(&request.to_string()).unwrap()).unwrap();
+    assert_eq!(response["error"]["code"], json!(-3));
+}
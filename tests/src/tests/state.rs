@@ -0,0 +1,216 @@
+use super::misc::*;
+use super::*;
+use aggregator::state::{IssuerState, IssuerUpdate};
+use ckb_types::{
+    bytes::Bytes,
+    core::{TransactionBuilder, TransactionView},
+    packed::*,
+    prelude::*,
+};
+use mol::{NftTransaction, NftTransactionVec, RawDistributeTransaction, Targets};
+use script_utils::helpers::{DISTRIBUTE_TRANSACTION, ISSUE_TRANSACTION};
+use sparse_merkle_tree::H256;
+
+fn issuer_cell_data(state: &IssuerState) -> Bytes {
+    let issuer = state.issuer();
+    issuer_data(issuer.class_count, issuer.set_count, state.root())
+}
+
+// apply the transactions to the state and build the transaction updating the issuer cell
+fn apply_tx(
+    ctx: &mut TestContext,
+    issuer: &IssuerCell,
+    state: &mut IssuerState,
+    txs: Vec<NftTransaction>,
+) -> TransactionView {
+    let input = ctx.create_input(
+        issuer.lock.clone(),
+        Some(issuer.type_.clone()),
+        issuer_cell_data(state),
+    );
+    let txs = NftTransactionVec::new_builder().set(txs).build();
+    let IssuerUpdate {
+        issuer: output_issuer,
+        action,
+        ..
+    } = state.apply(&txs, &[]).expect("apply transactions");
+    let output_data = issuer_data(
+        output_issuer.class_count,
+        output_issuer.set_count,
+        &H256::from(output_issuer.smt_root),
+    );
+    let witness = WitnessArgs::new_builder()
+        .lock(Some(txs.as_bytes()).pack())
+        .input_type(Some(action.as_bytes()).pack())
+        .build();
+    ctx.complete_tx(
+        TransactionBuilder::default()
+            .input(input)
+            .output(issuer.output())
+            .output_data(output_data.pack())
+            .witness(witness.as_bytes().pack()),
+    )
+}
+
+fn issue_tx(issuer: &IssuerCell) -> NftTransaction {
+    let issue_tx = issue_transaction(lock_hash(&issuer.lock));
+    nft_transaction(ISSUE_TRANSACTION, issue_tx.as_slice(), &[])
+}
+
+fn distribute_tx(issuer: &IssuerCell, state: &IssuerState, count: usize) -> NftTransaction {
+    let class = new_class(&issuer.issuer_id, 0, lock_hash(&issuer.lock));
+    let class_old_value = state.leaf(&class.to_key()).expect("class leaf");
+    let targets = (0..count)
+        .map(|_| Byte32::from_slice(&random_32bytes()).unwrap())
+        .collect();
+    let distribute_tx = RawDistributeTransaction::new_builder()
+        .class_old_value(class_old_value.pack())
+        .to(Targets::new_builder().set(targets).build())
+        .build();
+    nft_transaction(DISTRIBUTE_TRANSACTION, distribute_tx.as_slice(), &[])
+}
+
+#[test]
+fn test_issuer_state_issue_and_distribute_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let path = store_path();
+    let mut state = IssuerState::open(&path, issuer.issuer_id).unwrap();
+
+    let tx = apply_tx(&mut ctx, &issuer, &mut state, vec![issue_tx(&issuer)]);
+    ctx.verify_tx(&tx).expect("pass verification");
+    state.commit().unwrap();
+
+    let distribute = distribute_tx(&issuer, &state, 3);
+    let tx = apply_tx(&mut ctx, &issuer, &mut state, vec![distribute]);
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+    state.commit().unwrap();
+    assert_eq!(state.leaves().count(), 4);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_issuer_state_reopen_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let path = store_path();
+    let mut state = IssuerState::open(&path, issuer.issuer_id).unwrap();
+    apply_tx(&mut ctx, &issuer, &mut state, vec![issue_tx(&issuer)]);
+    state.commit().unwrap();
+    let root = *state.root();
+    drop(state);
+
+    // the tree is restored from the store instead of being rebuilt
+    let mut state = IssuerState::open(&path, issuer.issuer_id).unwrap();
+    assert_eq!(state.root(), &root);
+    assert_eq!(state.issuer().class_count, 1);
+    let distribute = distribute_tx(&issuer, &state, 1);
+    let tx = apply_tx(&mut ctx, &issuer, &mut state, vec![distribute]);
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_issuer_state_rollback_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let path = store_path();
+    let mut state = IssuerState::open(&path, issuer.issuer_id).unwrap();
+    apply_tx(&mut ctx, &issuer, &mut state, vec![issue_tx(&issuer)]);
+    state.commit().unwrap();
+    let root = *state.root();
+
+    // the L1 transaction of the batch failed
+    let distribute = distribute_tx(&issuer, &state, 2);
+    apply_tx(&mut ctx, &issuer, &mut state, vec![distribute]);
+    state.rollback().unwrap();
+    assert_eq!(state.root(), &root);
+    assert_eq!(state.leaves().count(), 1);
+
+    let distribute = distribute_tx(&issuer, &state, 1);
+    let tx = apply_tx(&mut ctx, &issuer, &mut state, vec![distribute]);
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_issuer_state_reopen_after_rollback_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let path = store_path();
+    let mut state = IssuerState::open(&path, issuer.issuer_id).unwrap();
+    apply_tx(&mut ctx, &issuer, &mut state, vec![issue_tx(&issuer)]);
+    state.commit().unwrap();
+
+    // the changes of the failed batch are dropped instead of being written with the next one
+    let distribute = distribute_tx(&issuer, &state, 2);
+    apply_tx(&mut ctx, &issuer, &mut state, vec![distribute]);
+    state.rollback().unwrap();
+    let distribute = distribute_tx(&issuer, &state, 1);
+    apply_tx(&mut ctx, &issuer, &mut state, vec![distribute]);
+    state.commit().unwrap();
+    let root = *state.root();
+    drop(state);
+
+    let state = IssuerState::open(&path, issuer.issuer_id).unwrap();
+    assert_eq!(state.root(), &root);
+    assert_eq!(state.leaves().count(), 2);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_issuer_state_recover_committed_batch_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let path = store_path();
+    let mut state = IssuerState::open(&path, issuer.issuer_id).unwrap();
+    let txs = NftTransactionVec::new_builder()
+        .set(vec![issue_tx(&issuer)])
+        .build();
+    let IssuerUpdate {
+        issuer: output_issuer,
+        ..
+    } = state.apply(&txs, &[]).unwrap();
+    // the L1 transaction is committed, but the process stops before the batch is persisted
+    drop(state);
+
+    let mut state = IssuerState::open(&path, issuer.issuer_id).unwrap();
+    assert_eq!(state.issuer().class_count, 0);
+    state.recover(&output_issuer).unwrap();
+    assert_eq!(state.root(), &H256::from(output_issuer.smt_root));
+    assert_eq!(state.issuer().class_count, 1);
+    drop(state);
+
+    // the recovered batch is persisted
+    let state = IssuerState::open(&path, issuer.issuer_id).unwrap();
+    assert_eq!(state.root(), &H256::from(output_issuer.smt_root));
+    assert_eq!(state.leaves().count(), 1);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_issuer_state_recover_failed_batch_success() {
+    let mut ctx = TestContext::new();
+    let issuer = IssuerCell::new(&mut ctx, 0, 0, vec![]);
+    let path = store_path();
+    let mut state = IssuerState::open(&path, issuer.issuer_id).unwrap();
+    apply_tx(&mut ctx, &issuer, &mut state, vec![issue_tx(&issuer)]);
+    drop(state);
+
+    // the L1 transaction failed, the issuer cell is still the one before the batch
+    let mut state = IssuerState::open(&path, issuer.issuer_id).unwrap();
+    let input_issuer = state.issuer().clone();
+    state.recover(&input_issuer).unwrap();
+    assert_eq!(state.issuer().class_count, 0);
+    assert_eq!(state.leaves().count(), 0);
+
+    std::fs::remove_file(path).unwrap();
+}
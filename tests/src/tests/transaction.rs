@@ -0,0 +1,625 @@
+use super::misc::*;
+use super::*;
+use ckb_crypto::secp::{Generator, Message};
+use ckb_error::assert_error_eq;
+use ckb_script::ScriptError;
+use ckb_types::{
+    bytes::Bytes,
+    core::{TransactionBuilder, TransactionView},
+    packed::*,
+    prelude::*,
+};
+use mol::{
+    NftTransaction, RawCreateSetTransaction, RawDistributeTransaction, RawExtractTransaction,
+    RawInsertTransaction, RawTransferTransaction, RawUpdateTransaction, Targets, EXTDATAS,
+};
+use script_utils::{
+    class::Class,
+    error::Error,
+    helpers::{
+        CREATE_SET_TRANSACTION, DISTRIBUTE_TRANSACTION, EXTRACT_TRANSACTION, INSERT_TRANSACTION,
+        NFT_ITEM, SET_ITEM, TRANSFER_TRANSACTION, UPDATE_TRANSACTION,
+    },
+    nft::Nft,
+    nft_set::NftSet,
+    signature::{blake160_lock_hash, signing_message},
+};
+use sparse_merkle_tree::H256;
+
+/// One batch of nft transactions on the tree of an issuer cell
+struct SmtTx<'a> {
+    ctx: &'a mut TestContext,
+    issuer: &'a mut IssuerCell,
+    inputs: Vec<CellInput>,
+    outputs: Vec<(CellOutput, Bytes)>,
+}
+
+impl<'a> SmtTx<'a> {
+    fn new(ctx: &'a mut TestContext, issuer: &'a mut IssuerCell) -> Self {
+        let inputs = vec![issuer.input(ctx)];
+        SmtTx {
+            ctx,
+            issuer,
+            inputs,
+            outputs: Vec::new(),
+        }
+    }
+
+    // authorize the transactions by the lock of an input cell
+    fn authorize(mut self, lock: Script) -> Self {
+        let input = self.ctx.create_input(lock, None, Bytes::new());
+        self.inputs.push(input);
+        self
+    }
+
+    fn input(mut self, lock: Script, type_args: Vec<u8>, data: Bytes) -> Self {
+        let type_ = self.ctx.type_script(type_args);
+        let input = self.ctx.create_input(lock, Some(type_), data);
+        self.inputs.push(input);
+        self
+    }
+
+    fn output(mut self, lock: Script, type_args: Vec<u8>, data: Bytes) -> Self {
+        let type_ = self.ctx.type_script(type_args);
+        self.outputs.push((cell_output(lock, Some(type_)), data));
+        self
+    }
+
+    fn build(
+        self,
+        typ: u8,
+        transaction: &[u8],
+        signature: &[u8],
+        leaves: Vec<(H256, Vec<u8>)>,
+    ) -> TransactionView {
+        self.build_batch(vec![nft_transaction(typ, transaction, signature)], leaves)
+    }
+
+    // the leaves are the ones left by the last transaction of the batch
+    fn build_batch(
+        self,
+        txs: Vec<NftTransaction>,
+        leaves: Vec<(H256, Vec<u8>)>,
+    ) -> TransactionView {
+        let proof = update_smt(&mut self.issuer.smt, leaves);
+        let witness = smt_witness(txs, &proof);
+        let mut builder = TransactionBuilder::default()
+            .inputs(self.inputs)
+            .output(self.issuer.output())
+            .output_data(self.issuer.data().pack())
+            .witness(witness.pack());
+        for (output, data) in self.outputs {
+            builder = builder.output(output).output_data(data.pack());
+        }
+        self.ctx.complete_tx(builder)
+    }
+}
+
+fn assert_issuer_error(ctx: &TestContext, tx: &TransactionView, error: i8) {
+    let err = ctx.verify_tx(tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(error).input_type_script(script_cell_index)
+    );
+}
+
+// an issuer with one class leaf and the nft leaves of the class, all of them owned by
+// the owner lock
+fn issuer_with_nfts(
+    ctx: &mut TestContext,
+    owner_lock: &Script,
+    count: u32,
+) -> (IssuerCell, Class, Vec<Nft>) {
+    let mut issuer = IssuerCell::new(ctx, 1, 0, vec![]);
+    let owner = lock_hash(owner_lock);
+    let mut class = new_class(&issuer.issuer_id, 0, owner);
+    class.issued = count;
+    let nfts: Vec<Nft> = (0..count)
+        .map(|token_id| new_nft(&issuer.issuer_id, 0, token_id, owner))
+        .collect();
+
+    let mut leaves = vec![(class.to_key(), class.to_leaf_data().unwrap())];
+    leaves.extend(nfts.iter().map(|nft| (nft.to_key(), nft.to_leaf_data())));
+    issuer.smt = new_smt(leaves);
+    (issuer, class, nfts)
+}
+
+fn distribute_tx(
+    ctx: &mut TestContext,
+    issuer: &mut IssuerCell,
+    class: &Class,
+    owner_lock: Script,
+    count: u32,
+) -> TransactionView {
+    let mut new_class = class.clone();
+    new_class.issued += count;
+    let mut leaves = vec![(class.to_key(), new_class.to_leaf_data().unwrap())];
+    let mut targets = Vec::new();
+    for token_id in class.issued..new_class.issued {
+        let target = lock_hash(&ctx.lock_script(random_20bytes()));
+        targets.push(target.pack());
+        let nft = new_nft(&issuer.issuer_id, class.class_id, token_id, target);
+        leaves.push((nft.to_key(), nft.to_leaf_data()));
+    }
+
+    let distribute_tx = RawDistributeTransaction::new_builder()
+        .class_old_value(class.to_leaf_data().unwrap().pack())
+        .to(Targets::new_builder().set(targets).build())
+        .build();
+    SmtTx::new(ctx, issuer).authorize(owner_lock).build(
+        DISTRIBUTE_TRANSACTION,
+        distribute_tx.as_slice(),
+        &[],
+        leaves,
+    )
+}
+
+#[test]
+fn test_distribute_success() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, class, _) = issuer_with_nfts(&mut ctx, &owner_lock, 0);
+    let tx = distribute_tx(&mut ctx, &mut issuer, &class, owner_lock, 2);
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_distribute_over_total_error() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, mut class, _) = issuer_with_nfts(&mut ctx, &owner_lock, 0);
+    class.total = 1;
+    issuer.smt = new_smt(vec![(class.to_key(), class.to_leaf_data().unwrap())]);
+    let tx = distribute_tx(&mut ctx, &mut issuer, &class, owner_lock, 2);
+
+    assert_issuer_error(&ctx, &tx, Error::ClassTotalSmallerThanIssued as i8);
+}
+
+fn transfer_transaction(nft: &Nft, to: [u8; 32]) -> (RawTransferTransaction, Vec<u8>) {
+    let transfer_tx = RawTransferTransaction::new_builder()
+        .typ(Byte::new(NFT_ITEM))
+        .nft_ids(nft_ids(vec![nft.to_key()]))
+        .nft_old_values(nft_values(vec![nft.to_leaf_data()]))
+        .to(Targets::new_builder().push(to.pack()).build())
+        .build();
+    let mut new_nft = nft.clone();
+    new_nft.owner = to;
+    new_nft.nonce += 1;
+    (transfer_tx, new_nft.to_leaf_data())
+}
+
+#[test]
+fn test_transfer_success() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, _, nfts) = issuer_with_nfts(&mut ctx, &owner_lock, 1);
+    let (transfer_tx, new_leaf) = transfer_transaction(&nfts[0], [1u8; 32]);
+    let tx = SmtTx::new(&mut ctx, &mut issuer)
+        .authorize(owner_lock)
+        .build(
+            TRANSFER_TRANSACTION,
+            transfer_tx.as_slice(),
+            &[],
+            vec![(nfts[0].to_key(), new_leaf)],
+        );
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_transfer_with_signature_success() {
+    let mut ctx = TestContext::new();
+    let (privkey, pubkey) = Generator::random_keypair();
+    let mut blake160 = [0u8; 20];
+    blake160.copy_from_slice(&ckb_hash::blake2b_256(pubkey.serialize())[..20]);
+
+    let mut issuer = IssuerCell::new(&mut ctx, 1, 0, vec![]);
+    let nft = new_nft(&issuer.issuer_id, 0, 0, blake160_lock_hash(&blake160));
+    issuer.smt = new_smt(vec![(nft.to_key(), nft.to_leaf_data())]);
+
+    let (transfer_tx, new_leaf) = transfer_transaction(&nft, [1u8; 32]);
+    let message = signing_message(
+        &issuer.issuer_id,
+        TRANSFER_TRANSACTION,
+        transfer_tx.as_slice(),
+    );
+    let signature = privkey
+        .sign_recoverable(&Message::from(message))
+        .unwrap()
+        .serialize();
+    let tx = SmtTx::new(&mut ctx, &mut issuer).build(
+        TRANSFER_TRANSACTION,
+        transfer_tx.as_slice(),
+        &signature,
+        vec![(nft.to_key(), new_leaf)],
+    );
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_transfer_with_invalid_signature_error() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, _, nfts) = issuer_with_nfts(&mut ctx, &owner_lock, 1);
+    let (transfer_tx, new_leaf) = transfer_transaction(&nfts[0], [1u8; 32]);
+    // the owner cell authorizes it, but a given signature must be a valid one
+    let tx = SmtTx::new(&mut ctx, &mut issuer)
+        .authorize(owner_lock)
+        .build(
+            TRANSFER_TRANSACTION,
+            transfer_tx.as_slice(),
+            &[0u8; 64],
+            vec![(nfts[0].to_key(), new_leaf)],
+        );
+
+    assert_issuer_error(&ctx, &tx, Error::SignatureInvalid as i8);
+}
+
+#[test]
+fn test_transfer_without_authorization_error() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, _, nfts) = issuer_with_nfts(&mut ctx, &owner_lock, 1);
+    let (transfer_tx, new_leaf) = transfer_transaction(&nfts[0], [1u8; 32]);
+    let tx = SmtTx::new(&mut ctx, &mut issuer).build(
+        TRANSFER_TRANSACTION,
+        transfer_tx.as_slice(),
+        &[],
+        vec![(nfts[0].to_key(), new_leaf)],
+    );
+
+    assert_issuer_error(&ctx, &tx, Error::OwnerNotAuthorized as i8);
+}
+
+#[test]
+fn test_transfer_with_targets_len_error() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, _, nfts) = issuer_with_nfts(&mut ctx, &owner_lock, 1);
+    let transfer_tx = RawTransferTransaction::new_builder()
+        .typ(Byte::new(NFT_ITEM))
+        .nft_ids(nft_ids(vec![nfts[0].to_key()]))
+        .nft_old_values(nft_values(vec![nfts[0].to_leaf_data()]))
+        .to(Targets::new_builder()
+            .push([1u8; 32].pack())
+            .push([2u8; 32].pack())
+            .build())
+        .build();
+    let tx = SmtTx::new(&mut ctx, &mut issuer)
+        .authorize(owner_lock)
+        .build(TRANSFER_TRANSACTION, transfer_tx.as_slice(), &[], vec![]);
+
+    assert_issuer_error(&ctx, &tx, Error::TransactionLenNotMatch as i8);
+}
+
+#[test]
+fn test_transfer_twice_with_stale_old_value_error() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, _, nfts) = issuer_with_nfts(&mut ctx, &owner_lock, 1);
+    let (first_tx, new_leaf) = transfer_transaction(&nfts[0], [1u8; 32]);
+    // the second transfer doesn't start from the leaf left by the first one
+    let (second_tx, _) = transfer_transaction(&nfts[0], [2u8; 32]);
+    let tx = SmtTx::new(&mut ctx, &mut issuer)
+        .authorize(owner_lock)
+        .build_batch(
+            vec![
+                nft_transaction(TRANSFER_TRANSACTION, first_tx.as_slice(), &[]),
+                nft_transaction(TRANSFER_TRANSACTION, second_tx.as_slice(), &[]),
+            ],
+            vec![(nfts[0].to_key(), new_leaf)],
+        );
+
+    assert_issuer_error(&ctx, &tx, Error::SMTLeafValueInvalid as i8);
+}
+
+fn update_tx(
+    ctx: &mut TestContext,
+    issuer: &mut IssuerCell,
+    owner_lock: Script,
+    nft: &Nft,
+    state: u8,
+    ext_info: &[u8],
+) -> TransactionView {
+    let update_tx = RawUpdateTransaction::new_builder()
+        .typ(Byte::new(NFT_ITEM))
+        .nft_ids(nft_ids(vec![nft.to_key()]))
+        .nft_old_values(nft_values(vec![nft.to_leaf_data()]))
+        .state(vec![state].pack())
+        .ext_datas(EXTDATAS::new_builder().push(ext_info.pack()).build())
+        .build();
+    let mut new_nft = nft.clone();
+    new_nft.state = state;
+    new_nft.ext_info = ext_info.to_vec();
+    SmtTx::new(ctx, issuer).authorize(owner_lock).build(
+        UPDATE_TRANSACTION,
+        update_tx.as_slice(),
+        &[],
+        vec![(nft.to_key(), new_nft.to_leaf_data())],
+    )
+}
+
+#[test]
+fn test_update_success() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, _, nfts) = issuer_with_nfts(&mut ctx, &owner_lock, 1);
+    let tx = update_tx(&mut ctx, &mut issuer, owner_lock, &nfts[0], 1, b"ext info");
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_update_claimed_to_unclaimed_error() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, _, mut nfts) = issuer_with_nfts(&mut ctx, &owner_lock, 1);
+    nfts[0].state = 1;
+    issuer.smt = new_smt(vec![(nfts[0].to_key(), nfts[0].to_leaf_data())]);
+    let tx = update_tx(&mut ctx, &mut issuer, owner_lock, &nfts[0], 0, &[]);
+
+    assert_issuer_error(&ctx, &tx, Error::NFTClaimedToUnclaimedError as i8);
+}
+
+// extract the nft leaf into a nft cell owned by the owner lock if it's given
+fn extract_tx(
+    ctx: &mut TestContext,
+    issuer: &mut IssuerCell,
+    owner_lock: Script,
+    nft: &Nft,
+    with_cell: bool,
+) -> TransactionView {
+    let extract_tx = RawExtractTransaction::new_builder()
+        .typ(Byte::new(NFT_ITEM))
+        .nft_ids(nft_ids(vec![nft.to_key()]))
+        .nft_old_values(nft_values(vec![nft.to_leaf_data()]))
+        .build();
+    let mut smt_tx = SmtTx::new(ctx, issuer).authorize(owner_lock.clone());
+    if with_cell {
+        smt_tx = smt_tx.output(owner_lock, nft_type_args(nft), nft_cell_data(nft));
+    }
+    smt_tx.build(
+        EXTRACT_TRANSACTION,
+        extract_tx.as_slice(),
+        &[],
+        vec![(nft.to_key(), vec![])],
+    )
+}
+
+#[test]
+fn test_extract_nft_success() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, _, nfts) = issuer_with_nfts(&mut ctx, &owner_lock, 1);
+    let tx = extract_tx(&mut ctx, &mut issuer, owner_lock, &nfts[0], true);
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_extract_nft_without_nft_cell_error() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, _, nfts) = issuer_with_nfts(&mut ctx, &owner_lock, 1);
+    let tx = extract_tx(&mut ctx, &mut issuer, owner_lock, &nfts[0], false);
+
+    assert_issuer_error(&ctx, &tx, Error::NFTCellsCountError as i8);
+}
+
+#[test]
+fn test_insert_nft_success() {
+    let mut ctx = TestContext::new();
+    let nft_lock = ctx.lock_script(random_20bytes());
+    let mut issuer = IssuerCell::new(&mut ctx, 1, 0, vec![]);
+    let nft = new_nft(&issuer.issuer_id, 0, 0, lock_hash(&nft_lock));
+    let insert_tx = RawInsertTransaction::new_builder()
+        .typ(Byte::new(NFT_ITEM))
+        .nft_ids(nft_ids(vec![nft.to_key()]))
+        .build();
+    let tx = SmtTx::new(&mut ctx, &mut issuer)
+        .input(nft_lock, nft_type_args(&nft), nft_cell_data(&nft))
+        .build(
+            INSERT_TRANSACTION,
+            insert_tx.as_slice(),
+            &[],
+            vec![(nft.to_key(), nft.to_leaf_data())],
+        );
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+// the member leaves are proved by their old values, which are given by old_members
+fn create_set_tx(
+    ctx: &mut TestContext,
+    issuer: &mut IssuerCell,
+    members: &[Nft],
+    old_members: &[Nft],
+    output_set_count: u32,
+) -> TransactionView {
+    let owner = lock_hash(&issuer.lock);
+    let member_keys: Vec<H256> = members.iter().map(|nft| nft.to_key()).collect();
+    let create_set_tx = RawCreateSetTransaction::new_builder()
+        .name(b"set".pack())
+        .description(b"a set of nfts".pack())
+        .members(nft_ids(member_keys.clone()))
+        .member_old_values(nft_values(
+            old_members.iter().map(|nft| nft.to_leaf_data()).collect(),
+        ))
+        .to(owner.pack())
+        .build();
+
+    let set = NftSet {
+        version: 0,
+        issuer_id: issuer.issuer_id,
+        set_id: issuer.set_count,
+        owner,
+        member_count: members.len() as u32,
+        name: b"set".to_vec(),
+        description: b"a set of nfts".to_vec(),
+    };
+    let mut leaves = vec![(set.to_key(), set.to_leaf_data().unwrap())];
+    for key in member_keys {
+        let mut nft_key = [0u8; 32];
+        nft_key.copy_from_slice(key.as_slice());
+        leaves.push((set.to_member_key(&nft_key), nft_key.to_vec()));
+    }
+    // the member leaves are unchanged
+    leaves.extend(members.iter().map(|nft| (nft.to_key(), nft.to_leaf_data())));
+    issuer.set_count = output_set_count;
+    SmtTx::new(ctx, issuer).build(
+        CREATE_SET_TRANSACTION,
+        create_set_tx.as_slice(),
+        &[],
+        leaves,
+    )
+}
+
+#[test]
+fn test_create_set_success() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, _, nfts) = issuer_with_nfts(&mut ctx, &owner_lock, 2);
+    let tx = create_set_tx(&mut ctx, &mut issuer, &nfts, &nfts, 1);
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_create_set_with_set_count_error() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, _, nfts) = issuer_with_nfts(&mut ctx, &owner_lock, 2);
+    let tx = create_set_tx(&mut ctx, &mut issuer, &nfts, &nfts, 0);
+
+    assert_issuer_error(&ctx, &tx, Error::IssuerSetCountError as i8);
+}
+
+#[test]
+fn test_create_set_with_member_not_match_error() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, _, nfts) = issuer_with_nfts(&mut ctx, &owner_lock, 2);
+    let old_members = vec![nfts[1].clone(), nfts[0].clone()];
+    let tx = create_set_tx(&mut ctx, &mut issuer, &nfts, &old_members, 1);
+
+    assert_issuer_error(&ctx, &tx, Error::NFTKeyNotMatch as i8);
+}
+
+fn issuer_with_set(ctx: &mut TestContext, owner_lock: &Script) -> (IssuerCell, NftSet) {
+    let mut issuer = IssuerCell::new(ctx, 1, 1, vec![]);
+    let set = NftSet {
+        version: 0,
+        issuer_id: issuer.issuer_id,
+        set_id: 0,
+        owner: lock_hash(owner_lock),
+        member_count: 0,
+        name: b"set".to_vec(),
+        description: b"a set of nfts".to_vec(),
+    };
+    issuer.smt = new_smt(vec![(set.to_key(), set.to_leaf_data().unwrap())]);
+    (issuer, set)
+}
+
+// extract the set leaf into a set cell owned by the owner lock if with_cell is set
+fn extract_set_tx(
+    ctx: &mut TestContext,
+    issuer: &mut IssuerCell,
+    owner_lock: Script,
+    set: &NftSet,
+    with_cell: bool,
+) -> TransactionView {
+    let extract_tx = RawExtractTransaction::new_builder()
+        .typ(Byte::new(SET_ITEM))
+        .nft_ids(nft_ids(vec![set.to_key()]))
+        .nft_old_values(nft_values(vec![set.to_leaf_data().unwrap()]))
+        .build();
+    let mut smt_tx = SmtTx::new(ctx, issuer).authorize(owner_lock.clone());
+    if with_cell {
+        smt_tx = smt_tx.output(owner_lock, set_type_args(set), set_cell_data(set));
+    }
+    smt_tx.build(
+        EXTRACT_TRANSACTION,
+        extract_tx.as_slice(),
+        &[],
+        vec![(set.to_key(), vec![])],
+    )
+}
+
+#[test]
+fn test_extract_set_cell_success() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, set) = issuer_with_set(&mut ctx, &owner_lock);
+    let tx = extract_set_tx(&mut ctx, &mut issuer, owner_lock, &set, true);
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_extract_set_cell_without_set_cell_error() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, set) = issuer_with_set(&mut ctx, &owner_lock);
+    let tx = extract_set_tx(&mut ctx, &mut issuer, owner_lock, &set, false);
+
+    assert_issuer_error(&ctx, &tx, Error::NFTSetCellsCountError as i8);
+}
+
+#[test]
+fn test_insert_set_cell_success() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    let (mut issuer, set) = issuer_with_set(&mut ctx, &owner_lock);
+    // the set leaf has been extracted into the set cell
+    issuer.smt = new_smt(vec![]);
+    let insert_tx = RawInsertTransaction::new_builder()
+        .typ(Byte::new(SET_ITEM))
+        .nft_ids(nft_ids(vec![set.to_key()]))
+        .build();
+    let tx = SmtTx::new(&mut ctx, &mut issuer)
+        .input(owner_lock, set_type_args(&set), set_cell_data(&set))
+        .build(
+            INSERT_TRANSACTION,
+            insert_tx.as_slice(),
+            &[],
+            vec![(set.to_key(), set.to_leaf_data().unwrap())],
+        );
+
+    let cycles = ctx.verify_tx(&tx).expect("pass verification");
+    println!("consume cycles: {}", cycles);
+}
+
+#[test]
+fn test_destroy_set_cell_error() {
+    let mut ctx = TestContext::new();
+    let owner_lock = ctx.lock_script(random_20bytes());
+    // the set leaf has been extracted into the set cell, its members stay bound to it
+    let (_, set) = issuer_with_set(&mut ctx, &owner_lock);
+    let set_type = ctx.type_script(set_type_args(&set));
+    let input = ctx.create_input(owner_lock.clone(), Some(set_type), set_cell_data(&set));
+    let tx = ctx.complete_tx(
+        TransactionBuilder::default()
+            .input(input)
+            .output(cell_output(owner_lock, None))
+            .output_data(Bytes::new().pack()),
+    );
+
+    let err = ctx.verify_tx(&tx).unwrap_err();
+    let script_cell_index = 0;
+    assert_error_eq!(
+        err,
+        ScriptError::ValidationFailure(Error::NFTSetCellCannotDestroyed as i8)
+            .input_type_script(script_cell_index)
+    );
+}